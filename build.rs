@@ -1,5 +1,4 @@
 use fs_extra::dir::CopyOptions;
-use glob;
 use std::{
     env,
     error::Error,
@@ -115,13 +114,13 @@ fn main() -> Result<(), BuildError> {
     //         .expect("failed to run 'npm install --save-dev typescript'");
 
     Command::new("npm")
-        .args(&["install"])
+        .args(["install"])
         .current_dir(ui_dir.clone())
-        .spawn()
+        .status()
         .expect("failed to run npm install");
 
     let exit_status = Command::new("npx")
-        .args(&["react-scripts", "build"])
+        .args(["react-scripts", "build"])
         .current_dir(ui_dir.clone())
         .status()?;
 
@@ -134,7 +133,7 @@ fn main() -> Result<(), BuildError> {
             writeln!(&mut all_the_files, r##"["##,)?;
 
             for pattern in UI_TARGET_PATTERNS.to_vec() {
-                append_file_bytes(&mut all_the_files, &out_dir, pattern)?;
+                append_file_bytes(&mut all_the_files, out_dir, pattern)?;
             }
 
             writeln!(&mut all_the_files, r##"]"##,)?;
@@ -0,0 +1,548 @@
+use crate::junit::{
+    allure::is_results_file, AttemptKind, FullReport, HasOutcome, TestCase, TestFailure,
+    TestOutcome, TestSkipped, TestSuite, UnreadableReport,
+};
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, warn};
+use serde::Serialize;
+use std::{
+    fs::{self, File},
+    iter,
+    path::{Path, PathBuf},
+};
+
+/// Writes the report as an Allure results directory, for the Allure command
+/// line to render it alongside the results of other test runs.
+pub struct AllureResults {
+    path: PathBuf,
+}
+
+impl AllureResults {
+    /// Overwriting an existing directory removes the results of the previous
+    /// run, so that they are not reported again, while keeping the other files
+    /// (e.g. `environment.properties` or `categories.json`).
+    pub fn new<P>(path: P, overwrite_existing: bool) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        if path.exists() && !path.is_dir() {
+            anyhow::bail!("{} exists and is not a directory", path.to_str().unwrap());
+        }
+        if path.exists() && !overwrite_existing {
+            anyhow::bail!("{} already exists", path.to_str().unwrap());
+        }
+        if path.exists() {
+            remove_results(path)?;
+        }
+
+        Ok(AllureResults {
+            path: path.to_owned(),
+        })
+    }
+
+    pub fn write(&self, full_report: &FullReport) -> anyhow::Result<()> {
+        self.write_at(full_report, Utc::now())
+    }
+
+    /// Suites without a timestamp are assumed to have run one after the other.
+    fn write_at(&self, full_report: &FullReport, now: DateTime<Utc>) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.path)?;
+        let mut clock = full_report.started_at(now);
+        for suite in &full_report.all_suites {
+            clock = self.write_suite(&suite.value, &mut Vec::new(), clock)?;
+        }
+        for report in &full_report.unreadable_reports {
+            self.write_unreadable(report, now)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a result for each test case and a container grouping them,
+    /// returning when the suite finished running.
+    fn write_suite<'a>(
+        &self,
+        suite: &'a TestSuite,
+        path: &mut Vec<&'a str>,
+        clock: DateTime<Utc>,
+    ) -> anyhow::Result<DateTime<Utc>> {
+        path.push(&suite.name);
+        let start = suite.metadata.timestamp.unwrap_or(clock);
+        let mut clock = start;
+        let mut children = Vec::new();
+        for (position, testcase) in suite.testcases.iter().enumerate() {
+            let position = position.to_string();
+            let ids: Vec<&str> = path
+                .iter()
+                .copied()
+                .chain(iter::once(position.as_str()))
+                .collect();
+            let labels = labels(path, suite, testcase);
+            for (n, attempt) in testcase.attempts.iter().enumerate() {
+                let attempt_n = n.to_string();
+                let attempt_id = id(&[&ids[..], &["attempt", attempt_n.as_str()]].concat());
+                let started = clock - Duration::milliseconds((testcase.attempts.len() - n) as i64);
+                let result = TestResult {
+                    status: if attempt.kind == AttemptKind::FlakyError
+                        || attempt.kind == AttemptKind::RerunError
+                    {
+                        "broken"
+                    } else {
+                        "failed"
+                    },
+                    status_details: Some(StatusDetails::from(&attempt.failure)),
+                    ..TestResult::new(attempt_id, testcase, started, started, labels.clone())
+                };
+                self.write_json(&format!("{}-result.json", result.uuid), &result)?;
+                children.push(result.uuid);
+            }
+
+            let mut result =
+                TestResult::new(id(&ids), testcase, clock, clock + testcase.time, labels);
+            match testcase.outcome() {
+                TestOutcome::Passed => (),
+                TestOutcome::Flaky => {
+                    result.status_details = Some(StatusDetails {
+                        flaky: true,
+                        ..StatusDetails::default()
+                    })
+                }
+                TestOutcome::Failed | TestOutcome::Errored => {
+                    result.status = if testcase.failure.is_some() {
+                        "failed"
+                    } else {
+                        "broken"
+                    };
+                    result.status_details = testcase
+                        .failure
+                        .as_ref()
+                        .or(testcase.error.as_ref())
+                        .map(StatusDetails::from);
+                }
+                TestOutcome::Skipped => {
+                    result.status = "skipped";
+                    result.status_details = Some(StatusDetails {
+                        message: testcase.skipped.as_ref().and_then(TestSkipped::reason),
+                        ..StatusDetails::default()
+                    })
+                }
+            }
+            result.attachments = self.write_attachments(&result.uuid, testcase)?;
+            self.write_json(&format!("{}-result.json", result.uuid), &result)?;
+            children.push(result.uuid);
+            clock = clock + testcase.time;
+        }
+
+        for nested in &suite.testsuites {
+            clock = self.write_suite(nested, path, clock)?;
+        }
+        let stop = start + suite.time;
+        let container = Container {
+            uuid: id(path),
+            name: path.join(" > "),
+            children,
+            start: start.timestamp_millis(),
+            stop: stop.timestamp_millis(),
+        };
+        self.write_json(&format!("{}-container.json", container.uuid), &container)?;
+        path.pop();
+        Ok(stop.max(clock))
+    }
+
+    /// Reports which could not be read are accounted for as broken tests.
+    fn write_unreadable(
+        &self,
+        report: &UnreadableReport,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        let name = report.path.display().to_string();
+        let result = TestResult {
+            uuid: id(&["unreadable", &name]),
+            history_id: history_id("unreadable", &name),
+            full_name: name.clone(),
+            name: &name,
+            status: "broken",
+            status_details: Some(StatusDetails {
+                message: Some(&report.error),
+                ..StatusDetails::default()
+            }),
+            stage: "finished",
+            start: now.timestamp_millis(),
+            stop: now.timestamp_millis(),
+            labels: vec![Label::new("suite", "Unreadable reports")],
+            attachments: Vec::new(),
+        };
+        self.write_json(&format!("{}-result.json", result.uuid), &result)
+    }
+
+    /// Copies the files attached to a test next to its result, along with
+    /// its output. Missing files are left out.
+    fn write_attachments(
+        &self,
+        uuid: &str,
+        testcase: &TestCase,
+    ) -> anyhow::Result<Vec<AllureAttachment>> {
+        let mut attachments = Vec::new();
+        let outputs = [
+            ("stdout", &testcase.system_out),
+            ("stderr", &testcase.system_err),
+        ];
+        for (name, output) in outputs.iter() {
+            if let Some(output) = output {
+                let source = format!("{}-attachment.txt", id(&[uuid, name]));
+                fs::write(self.path.join(&source), output)?;
+                attachments.push(AllureAttachment {
+                    name: (*name).to_owned(),
+                    source,
+                    content_type: Some("text/plain".to_owned()),
+                });
+            }
+        }
+        for (n, attachment) in testcase.attachments.iter().enumerate() {
+            if !attachment.path.is_file() {
+                warn!("Skipping missing attachment: {}", attachment.path.display());
+                continue;
+            }
+            let extension = attachment
+                .path
+                .extension()
+                .map(|e| format!(".{}", e.to_string_lossy()))
+                .unwrap_or_default();
+            let source = format!(
+                "{}-attachment{}",
+                id(&[uuid, "attachment", &n.to_string()]),
+                extension
+            );
+            debug!("Copying attachment to: {}", source);
+            fs::copy(&attachment.path, self.path.join(&source))?;
+            attachments.push(AllureAttachment {
+                name: attachment.name.clone(),
+                source,
+                content_type: attachment.content_type.clone(),
+            });
+        }
+        Ok(attachments)
+    }
+
+    fn write_json<T: Serialize>(&self, file_name: &str, value: &T) -> anyhow::Result<()> {
+        let file = File::create(self.path.join(file_name))?;
+        serde_json::to_writer(file, value)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TestResult<'a> {
+    uuid: String,
+    history_id: String,
+    full_name: String,
+    name: &'a str,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status_details: Option<StatusDetails<'a>>,
+    stage: &'static str,
+    start: i64,
+    stop: i64,
+    labels: Vec<Label>,
+    attachments: Vec<AllureAttachment>,
+}
+
+impl<'a> TestResult<'a> {
+    fn new(
+        uuid: String,
+        testcase: &'a TestCase,
+        start: DateTime<Utc>,
+        stop: DateTime<Utc>,
+        labels: Vec<Label>,
+    ) -> Self {
+        TestResult {
+            uuid,
+            history_id: history_id(&testcase.classname, &testcase.name),
+            full_name: format!("{}.{}", testcase.classname, testcase.name),
+            name: &testcase.name,
+            status: "passed",
+            status_details: None,
+            stage: "finished",
+            start: start.timestamp_millis(),
+            stop: stop.timestamp_millis(),
+            labels,
+            attachments: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+struct StatusDetails<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<&'a str>,
+    flaky: bool,
+}
+
+impl<'a> From<&'a TestFailure> for StatusDetails<'a> {
+    fn from(failure: &'a TestFailure) -> Self {
+        StatusDetails {
+            message: failure.message.as_deref(),
+            trace: Some(failure.stack_trace.as_str()).filter(|trace| !trace.is_empty()),
+            flaky: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Label {
+    name: &'static str,
+    value: String,
+}
+
+impl Label {
+    fn new(name: &'static str, value: &str) -> Self {
+        Label {
+            name,
+            value: value.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct AllureAttachment {
+    name: String,
+    source: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Container {
+    uuid: String,
+    name: String,
+    children: Vec<String>,
+    start: i64,
+    stop: i64,
+}
+
+fn remove_results(dir: &Path) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && is_results_file(&path) {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Nested suites are broken down into the three levels Allure groups tests
+/// by, the deepest ones being named after the rest of their path.
+fn labels(path: &[&str], suite: &TestSuite, testcase: &TestCase) -> Vec<Label> {
+    let mut labels = match path {
+        [suite] => vec![Label::new("suite", suite)],
+        [parent, suite] => vec![
+            Label::new("parentSuite", parent),
+            Label::new("suite", suite),
+        ],
+        _ => vec![
+            Label::new("parentSuite", path[0]),
+            Label::new("suite", path[1]),
+            Label::new("subSuite", &path[2..].join(" > ")),
+        ],
+    };
+    if !testcase.classname.is_empty() {
+        labels.push(Label::new("testClass", &testcase.classname));
+    }
+    if let Some(package) = &suite.metadata.package {
+        labels.push(Label::new("package", package));
+    }
+    if let Some(hostname) = &suite.metadata.hostname {
+        labels.push(Label::new("host", hostname));
+    }
+    labels
+}
+
+/// Allure tells runs of the same test apart by their history id, which is
+/// thus derived from the test alone.
+fn history_id(classname: &str, name: &str) -> String {
+    let parts = [classname, name];
+    format!("{:016x}{:016x}", fnv1a(&parts, 0), fnv1a(&parts, 1))
+}
+
+/// Derives a UUID from the given parts, so that writing the same report
+/// twice overwrites the same files.
+fn id(parts: &[&str]) -> String {
+    let (high, low) = (fnv1a(parts, 2), fnv1a(parts, 3));
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+/// A hash which, unlike the standard library's, does not change from one
+/// release of Rust to the next.
+fn fnv1a(parts: &[&str], seed: u64) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325 ^ seed;
+    for part in parts {
+        for byte in part.bytes().chain(iter::once(0)) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+    extern crate uuid;
+
+    use super::*;
+    use crate::junit::{
+        fixtures::{failure, full_report, suite, testcase, unreadable},
+        SuiteMetadata, TestAttempt,
+    };
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+    use std::env;
+    use uuid::Uuid;
+
+    fn read_dir(dir: &Path) -> Vec<Value> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.to_string_lossy().ends_with("-result.json"))
+            .collect();
+        paths.sort();
+        paths
+            .iter()
+            .map(|path| serde_json::from_reader(File::open(path).unwrap()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn write_results() {
+        let failed = TestCase {
+            failure: Some(failure("expected 90")),
+            system_out: Some("adding 2 items".to_owned()),
+            ..testcase("applies discounts")
+        };
+        let flaky = TestCase {
+            attempts: vec![TestAttempt {
+                kind: AttemptKind::FlakyError,
+                failure: failure("timed out"),
+                system_out: None,
+                system_err: None,
+            }],
+            ..testcase("checks out")
+        };
+        let suite = TestSuite {
+            metadata: SuiteMetadata {
+                timestamp: Some(Utc.ymd(2020, 11, 2).and_hms(10, 0, 0)),
+                ..SuiteMetadata::default()
+            },
+            ..suite("cart", vec![failed, flaky])
+        };
+        let full_report = full_report(
+            vec![suite],
+            vec![unreadable(PathBuf::from("reports/broken.xml"))],
+        );
+
+        let mut dir = env::temp_dir();
+        dir.push(format!("cikit/allure-{}", Uuid::new_v4()));
+        AllureResults::new(&dir, false)
+            .unwrap()
+            .write_at(&full_report, Utc::now())
+            .unwrap();
+        assert!(AllureResults::new(&dir, false).is_err());
+
+        let mut results = read_dir(&dir);
+        assert_eq!(results.len(), 4);
+        results.sort_by_key(|r| (r["start"].as_i64(), r["status"].to_string()));
+        let statuses: Vec<(&str, &str)> = results
+            .iter()
+            .map(|r| (r["name"].as_str().unwrap(), r["status"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("applies discounts", "failed"),
+                ("checks out", "broken"),
+                ("checks out", "passed"),
+                ("reports/broken.xml", "broken"),
+            ]
+        );
+        assert_eq!(results[1]["historyId"], results[2]["historyId"]);
+        assert_eq!(results[2]["statusDetails"]["flaky"], true);
+
+        let failed = &results[0];
+        assert_eq!(failed["start"], 1604311200000i64);
+        assert_eq!(failed["stop"], 1604311200010i64);
+        assert_eq!(
+            failed["labels"],
+            json!([
+                {"name": "suite", "value": "cart"},
+                {"name": "testClass", "value": "com.example.CartTest"}
+            ])
+        );
+        assert_eq!(
+            failed["statusDetails"],
+            json!({"message": "expected 90", "trace": "AssertionError: expected 90", "flaky": false})
+        );
+        let source = failed["attachments"][0]["source"].as_str().unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join(source)).unwrap(),
+            "adding 2 items"
+        );
+    }
+
+    #[test]
+    fn stable_ids() {
+        assert_eq!(id(&["cart", "0"]), id(&["cart", "0"]));
+        assert_ne!(id(&["cart", "0"]), id(&["cart0"]));
+        assert_eq!(id(&["cart", "0"]).len(), 36);
+        assert_eq!(history_id("CartTest", "adds items").len(), 32);
+    }
+
+    #[test]
+    fn overwrite_results() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("cikit/allure-{}", Uuid::new_v4()));
+        let failed = TestCase {
+            failure: Some(failure("expected 90")),
+            system_out: Some("adding 2 items".to_owned()),
+            ..testcase("applies discounts")
+        };
+        let first = full_report(
+            vec![suite("cart", vec![failed, testcase("adds items")])],
+            vec![],
+        );
+        AllureResults::new(&dir, false)
+            .unwrap()
+            .write(&first)
+            .unwrap();
+        fs::write(dir.join("environment.properties"), "os=linux\n").unwrap();
+
+        let second = full_report(vec![suite("cart", vec![testcase("adds items")])], vec![]);
+        AllureResults::new(&dir, true)
+            .unwrap()
+            .write(&second)
+            .unwrap();
+
+        let results = read_dir(&dir);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["name"], "adds items");
+        let mut files: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| !name.ends_with("-result.json"))
+            .collect();
+        files.sort();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].ends_with("-container.json"));
+        assert_eq!(files[1], "environment.properties");
+    }
+}
@@ -1,4 +1,9 @@
-use crate::{gcs::PublisherConfig, github::GithubHandle, slack::SlackUserId};
+use crate::{
+    gcs::PublisherConfig,
+    github::GithubHandle,
+    junit::{ReportFormat, ReportLayout},
+    slack::SlackUserId,
+};
 use serde_derive::Deserialize;
 use std::{collections::BTreeMap, io, path::Path};
 use std::{env, fs};
@@ -29,6 +34,46 @@ fn default_github_token() -> String {
 #[derive(PartialEq, Debug, Deserialize)]
 pub struct Junit {
     pub report_dir_pattern: String,
+    /// Report nested test suites as a flat list rather than as a tree
+    #[serde(default)]
+    pub flatten_suites: bool,
+    /// Fail when a report file cannot be parsed, rather than listing it
+    /// among the unreadable reports
+    #[serde(default)]
+    pub strict: bool,
+    /// Additional report files, possibly in formats other than JUnit XML
+    #[serde(default)]
+    pub inputs: Vec<ReportInput>,
+}
+
+impl Junit {
+    /// The reports matching `report_dir_pattern`, in any detected format,
+    /// followed by any additional input.
+    pub fn all_inputs(&self) -> Vec<ReportInput> {
+        let mut inputs = vec![ReportInput::new(&self.report_dir_pattern, None)];
+        inputs.extend(self.inputs.iter().cloned());
+        inputs
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Deserialize)]
+pub struct ReportInput {
+    pub pattern: String,
+    /// Detected from the content of each file when left out
+    #[serde(default)]
+    pub format: Option<ReportFormat>,
+    #[serde(default)]
+    pub layout: ReportLayout,
+}
+
+impl ReportInput {
+    pub fn new(pattern: &str, format: Option<ReportFormat>) -> Self {
+        ReportInput {
+            pattern: pattern.to_owned(),
+            format,
+            layout: ReportLayout::default(),
+        }
+    }
 }
 
 #[derive(PartialEq, Debug, Deserialize)]
@@ -69,6 +114,17 @@ mod tests {
 
         [junit]
         report_dir_pattern = "**/target/**/test-reports"
+
+        [[junit.inputs]]
+        pattern = "**/t/*.tap"
+        format = "tap"
+
+        [[junit.inputs]]
+        pattern = "**/results/*.json"
+
+        [[junit.inputs]]
+        pattern = "bazel-testlogs"
+        layout = "bazel"
     "#,
         )
         .unwrap();
@@ -99,7 +155,17 @@ mod tests {
                     })
                 },
                 junit: Junit {
-                    report_dir_pattern: "**/target/**/test-reports".to_owned()
+                    report_dir_pattern: "**/target/**/test-reports".to_owned(),
+                    flatten_suites: false,
+                    strict: false,
+                    inputs: vec![
+                        ReportInput::new("**/t/*.tap", Some(ReportFormat::Tap)),
+                        ReportInput::new("**/results/*.json", None),
+                        ReportInput {
+                            layout: ReportLayout::Bazel,
+                            ..ReportInput::new("bazel-testlogs", None)
+                        },
+                    ],
                 }
             }
         )
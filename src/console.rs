@@ -1,4 +1,4 @@
-use crate::junit::{FullReport, TestOutcome};
+use crate::junit::{FullReport, HasOutcome, TestOutcome};
 use colored::{Color, ColoredString, Colorize};
 use io::Result;
 use std::io;
@@ -6,6 +6,7 @@ use std::io;
 use crate::{github::GithubEvent, junit::*};
 
 const INDENT_STR: &str = " ";
+const MAX_OUTPUT_LINES: usize = 10;
 
 fn color_if_pos<S: Into<Color>>(value: usize, color: S) -> ColoredString {
     if value > 0 {
@@ -24,7 +25,7 @@ impl ConsoleDisplay for GithubEvent {
         writeln!(f, "> {:<11}: {}", "Title", self.pull_request.title)?;
         writeln!(f, "> {:<11}: {:<4}", "URL", self.pull_request.html_url)?;
         writeln!(f, "> {:<11}: {:<4}", "Author", self.sender.login.0)?;
-        writeln!(f, "")
+        writeln!(f)
     }
 }
 
@@ -53,7 +54,13 @@ impl ConsoleDisplay for Summary {
             f,
             "> {:<11}: {:<4}",
             "Skipped",
-            color_if_pos(self.errors, Color::Blue)
+            color_if_pos(self.skipped, Color::Blue)
+        )?;
+        writeln!(
+            f,
+            "> {:<11}: {:<4}",
+            "Flaky",
+            color_if_pos(self.flaky, Color::Yellow)
         )
     }
 }
@@ -73,11 +80,75 @@ impl ConsoleDisplay for TestFailure {
     }
 }
 
+impl ConsoleDisplay for TestAttempt {
+    fn display(&self, f: &mut Box<dyn io::Write>, depth: usize) -> Result<()> {
+        let label = if self.kind.is_flaky() {
+            "flaky run".yellow()
+        } else {
+            "re-run".red()
+        };
+        writeln!(
+            f,
+            "{}-- {}: {}",
+            INDENT_STR.repeat(depth),
+            label,
+            &self
+                .failure
+                .message
+                .clone()
+                .unwrap_or_else(|| "n/a".to_owned())
+        )
+    }
+}
+
+impl ConsoleDisplay for Attachment {
+    fn display(&self, f: &mut Box<dyn io::Write>, depth: usize) -> Result<()> {
+        writeln!(
+            f,
+            "{}-- {}: {}",
+            INDENT_STR.repeat(depth),
+            self.name.dimmed(),
+            self.path.display()
+        )
+    }
+}
+
+fn display_output(
+    f: &mut Box<dyn io::Write>,
+    depth: usize,
+    label: &str,
+    output: &str,
+) -> Result<()> {
+    let lines: Vec<&str> = output.trim_end().lines().collect();
+    let skip = lines.len().saturating_sub(MAX_OUTPUT_LINES);
+    let indent = INDENT_STR.repeat(depth);
+
+    writeln!(f, "{}-- {}:", indent, label.dimmed())?;
+    if skip > 0 {
+        writeln!(f, "{}   [{} lines omitted]", indent, skip)?;
+    }
+    for line in &lines[skip..] {
+        writeln!(f, "{}   {}", indent, line.dimmed())?;
+    }
+    Ok(())
+}
+
 fn outcome_gpyph(outcome: &TestOutcome) -> ColoredString {
     match outcome {
+        TestOutcome::Passed => "-".normal(),
+        TestOutcome::Failed => "✗".red(),
+        TestOutcome::Errored => "!".red(),
         TestOutcome::Skipped => "↪".blue(),
-        TestOutcome::Failure => "✗".red(),
-        TestOutcome::Success => "-".normal(),
+        TestOutcome::Flaky => "~".yellow(),
+    }
+}
+
+fn suite_outcome_gpyph(outcome: &TestOutcome) -> ColoredString {
+    match outcome {
+        TestOutcome::Passed => "✓".green(),
+        TestOutcome::Failed | TestOutcome::Errored => "✗".red(),
+        TestOutcome::Skipped => "↪".blue(),
+        TestOutcome::Flaky => "✓".yellow(),
     }
 }
 
@@ -91,37 +162,66 @@ impl ConsoleDisplay for TestCase {
             display::duration(self.time.to_std().unwrap()),
             self.name
         )?;
-        if let Some(failure) = &self.failure {
-            failure.display(f, depth)
-        } else {
-            Ok(())
+        if let Some(failure) = self.failure.as_ref().or(self.error.as_ref()) {
+            failure.display(f, depth)?;
         }
+        for attempt in &self.attempts {
+            attempt.display(f, depth)?;
+        }
+        if !self.is_successful() {
+            for attachment in &self.attachments {
+                attachment.display(f, depth)?;
+            }
+            if let Some(system_out) = &self.system_out {
+                display_output(f, depth, "system-out", system_out)?;
+            }
+            if let Some(system_err) = &self.system_err {
+                display_output(f, depth, "system-err", system_err)?;
+            }
+        }
+        Ok(())
     }
 }
 
-impl ConsoleDisplay for SuiteWithSummary {
+impl ConsoleDisplay for TestSuite {
     fn display(&self, f: &mut Box<dyn io::Write>, depth: usize) -> Result<()> {
-        let suite = &self.value;
-        let outcome_gpyph = if self.is_successful() {
-            "✓".green()
-        } else {
-            "✗".red()
-        };
         writeln!(
             f,
             "{}{} {:10} {}",
             INDENT_STR.repeat(depth),
-            outcome_gpyph,
-            display::duration(suite.time.to_std().unwrap()),
-            suite.name.bold()
+            suite_outcome_gpyph(&self.outcome()),
+            display::duration(self.time.to_std().unwrap()),
+            self.name.bold()
         )?;
-        for test_case in &suite.testcases {
+        for test_case in &self.testcases {
             test_case.display(f, depth + 1)?;
         }
+        for suite in &self.testsuites {
+            suite.display(f, depth + 1)?;
+        }
         Ok(())
     }
 }
 
+impl ConsoleDisplay for UnreadableReport {
+    fn display(&self, f: &mut Box<dyn io::Write>, depth: usize) -> Result<()> {
+        writeln!(
+            f,
+            "{}{} {}",
+            INDENT_STR.repeat(depth),
+            outcome_gpyph(&TestOutcome::Errored),
+            self.path.display().to_string().bold()
+        )?;
+        writeln!(f, "{}-- {}", INDENT_STR.repeat(depth), self.error.red())
+    }
+}
+
+impl ConsoleDisplay for SuiteWithSummary {
+    fn display(&self, f: &mut Box<dyn io::Write>, depth: usize) -> Result<()> {
+        self.value.display(f, depth)
+    }
+}
+
 pub struct ConsoleTextReport {
     sink: Box<dyn io::Write>,
 }
@@ -142,6 +242,58 @@ impl ConsoleTextReport {
         for suite in &full_report.all_suites {
             suite.display(&mut self.sink, 0)?;
         }
+        if !full_report.unreadable_reports.is_empty() {
+            writeln!(self.sink, "\n{}", "Unreadable reports".red().bold())?;
+            for report in &full_report.unreadable_reports {
+                report.display(&mut self.sink, 1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lists the skipped tests grouped by the reason they were skipped for, so
+/// that long forgotten `@Ignore`s can be tracked down.
+pub struct ConsoleSkippedReport {
+    sink: Box<dyn io::Write>,
+}
+impl ConsoleSkippedReport {
+    pub fn sink_to(sink: Box<dyn io::Write>) -> Self {
+        ConsoleSkippedReport { sink }
+    }
+    pub fn stdout() -> Self {
+        ConsoleSkippedReport::sink_to(Box::new(io::stdout()))
+    }
+}
+
+impl ConsoleSkippedReport {
+    pub fn render(&mut self, full_report: &FullReport) -> anyhow::Result<()> {
+        let by_reason = full_report.skipped_by_reason();
+        for (reason, tests) in &by_reason {
+            writeln!(
+                self.sink,
+                "{} {} ({})",
+                outcome_gpyph(&TestOutcome::Skipped),
+                reason.unwrap_or("no reason given").bold(),
+                tests.len()
+            )?;
+            for skipped in tests {
+                writeln!(
+                    self.sink,
+                    "{}{} :: {}",
+                    INDENT_STR.repeat(2),
+                    skipped.suite.dimmed(),
+                    skipped.testcase.name
+                )?;
+            }
+        }
+        let total: usize = by_reason.values().map(Vec::len).sum();
+        writeln!(
+            self.sink,
+            "\n{} skipped tests, {} distinct reasons",
+            color_if_pos(total, Color::Blue),
+            by_reason.len()
+        )?;
         Ok(())
     }
 }
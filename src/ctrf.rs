@@ -0,0 +1,351 @@
+use crate::junit::{
+    Attachment, FullReport, HasOutcome, TestCase, TestOutcome, TestSkipped, TestSuite,
+    UnreadableReport,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::io;
+
+const SPEC_VERSION: &str = "0.0.0";
+
+/// Renders the report as a Common Test Report Format document, for other
+/// tools to consume the merged results.
+pub struct CtrfReport {
+    compact: bool,
+    sink: Box<dyn io::Write>,
+}
+impl CtrfReport {
+    pub fn sink_to(compact: bool, sink: Box<dyn io::Write>) -> Self {
+        CtrfReport { compact, sink }
+    }
+    pub fn stdout(compact: bool) -> Self {
+        CtrfReport::sink_to(compact, Box::new(io::stdout()))
+    }
+}
+
+impl CtrfReport {
+    pub fn render(&mut self, full_report: &FullReport) -> anyhow::Result<()> {
+        let document = Document::new(full_report, Utc::now());
+        if self.compact {
+            serde_json::ser::to_writer(&mut self.sink, &document)?;
+        } else {
+            serde_json::ser::to_writer_pretty(&mut self.sink, &document)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Document<'a> {
+    report_format: &'static str,
+    spec_version: &'static str,
+    generated_by: &'static str,
+    results: Results<'a>,
+}
+
+#[derive(Debug, Serialize)]
+struct Results<'a> {
+    tool: Tool,
+    summary: Summary,
+    tests: Vec<Test<'a>>,
+    extra: Extra<'a>,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+    name: &'static str,
+    version: &'static str,
+}
+
+/// Start and stop times are in milliseconds since the epoch.
+#[derive(Debug, Default, Serialize)]
+struct Summary {
+    tests: usize,
+    passed: usize,
+    failed: usize,
+    pending: usize,
+    skipped: usize,
+    other: usize,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Test<'a> {
+    name: &'a str,
+    status: &'static str,
+    raw_status: &'static str,
+    duration: i64,
+    suite: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<&'a str>,
+    retries: usize,
+    flaky: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stdout: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stderr: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<&'a Attachment>,
+    extra: TestExtra<'a>,
+}
+
+/// What cikit knows of a test besides what CTRF describes.
+#[derive(Debug, Serialize)]
+struct TestExtra<'a> {
+    classname: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Extra<'a> {
+    unreadable_reports: &'a [UnreadableReport],
+}
+
+impl<'a> Document<'a> {
+    fn new(full_report: &'a FullReport, now: DateTime<Utc>) -> Self {
+        let mut tests = Vec::new();
+        for suite in &full_report.all_suites {
+            collect_tests(&suite.value, &mut Vec::new(), &mut tests);
+        }
+
+        let mut summary = Summary::default();
+        for test in &tests {
+            summary.tests += 1;
+            match test.status {
+                "passed" => summary.passed += 1,
+                "failed" => summary.failed += 1,
+                _ => summary.skipped += 1,
+            }
+        }
+        let started = full_report.started_at(now);
+        summary.start = started.timestamp_millis();
+        summary.stop = (started + full_report.summary.time).timestamp_millis();
+
+        Document {
+            report_format: "CTRF",
+            spec_version: SPEC_VERSION,
+            generated_by: "cikit",
+            results: Results {
+                tool: Tool {
+                    name: "cikit",
+                    version: env!("CARGO_PKG_VERSION"),
+                },
+                summary,
+                tests,
+                extra: Extra {
+                    unreadable_reports: &full_report.unreadable_reports,
+                },
+            },
+        }
+    }
+}
+
+/// Nested suites are named after their path, e.g. `root > nested`.
+fn collect_tests<'a>(suite: &'a TestSuite, path: &mut Vec<&'a str>, tests: &mut Vec<Test<'a>>) {
+    path.push(&suite.name);
+    for testcase in &suite.testcases {
+        tests.push(test(testcase, path.join(" > ")));
+    }
+    for nested in &suite.testsuites {
+        collect_tests(nested, path, tests);
+    }
+    path.pop();
+}
+
+fn test(testcase: &TestCase, suite: String) -> Test<'_> {
+    let outcome = testcase.outcome();
+    let (status, raw_status) = match outcome {
+        TestOutcome::Passed => ("passed", "passed"),
+        TestOutcome::Flaky => ("passed", "flaky"),
+        TestOutcome::Failed => ("failed", "failure"),
+        TestOutcome::Errored => ("failed", "error"),
+        TestOutcome::Skipped => ("skipped", "skipped"),
+    };
+    let failure = testcase.failure.as_ref().or(testcase.error.as_ref());
+    let message = match failure {
+        Some(failure) => failure.message.as_deref(),
+        None => testcase.skipped.as_ref().and_then(TestSkipped::reason),
+    };
+    Test {
+        name: &testcase.name,
+        status,
+        raw_status,
+        duration: testcase.time.num_milliseconds(),
+        suite,
+        message,
+        trace: failure
+            .map(|f| f.stack_trace.as_str())
+            .filter(|trace| !trace.is_empty()),
+        retries: testcase.attempts.len(),
+        flaky: outcome == TestOutcome::Flaky,
+        stdout: lines(testcase.system_out.as_deref()),
+        stderr: lines(testcase.system_err.as_deref()),
+        attachments: testcase.attachments.iter().collect(),
+        extra: TestExtra {
+            classname: &testcase.classname,
+        },
+    }
+}
+
+fn lines(output: Option<&str>) -> Vec<&str> {
+    output.map(|o| o.lines().collect()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use crate::junit::{
+        AttemptKind, SuiteMetadata, Summary as TestSummary, TestAttempt, TestFailure,
+    };
+    use chrono::{Duration, TimeZone};
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    fn testcase(name: &str) -> TestCase {
+        TestCase {
+            name: name.to_owned(),
+            classname: "com.example.CartTest".to_owned(),
+            time: Duration::milliseconds(10),
+            failure: None,
+            error: None,
+            skipped: None,
+            system_out: None,
+            system_err: None,
+            attempts: vec![],
+            attachments: vec![],
+            location: None,
+        }
+    }
+
+    fn suite(name: &str, testcases: Vec<TestCase>, testsuites: Vec<TestSuite>) -> TestSuite {
+        TestSuite {
+            name: name.to_owned(),
+            time: Duration::milliseconds(100),
+            testcases,
+            system_out: None,
+            system_err: None,
+            metadata: SuiteMetadata::default(),
+            testsuites,
+        }
+    }
+
+    fn failure(message: &str) -> TestFailure {
+        TestFailure {
+            message: Some(message.to_owned()),
+            classname: "AssertionError".to_owned(),
+            stack_trace: format!("AssertionError: {}\n\tat CartTest.java:12", message),
+        }
+    }
+
+    fn full_report() -> FullReport {
+        let failed = TestCase {
+            failure: Some(failure("expected 90")),
+            system_out: Some("line 1\nline 2".to_owned()),
+            ..testcase("applies discounts")
+        };
+        let flaky = TestCase {
+            attempts: vec![TestAttempt {
+                kind: AttemptKind::FlakyFailure,
+                failure: failure("timed out"),
+                system_out: None,
+                system_err: None,
+            }],
+            ..testcase("checks out")
+        };
+        let skipped = TestCase {
+            skipped: Some(TestSkipped {
+                message: Some("shipping is disabled".to_owned()),
+                details: None,
+            }),
+            ..testcase("ships abroad")
+        };
+        let suites = vec![suite(
+            "cart",
+            vec![testcase("adds items"), failed],
+            vec![suite("checkout", vec![flaky, skipped], vec![])],
+        )
+        .with_summary()];
+        let summary = TestSummary {
+            time: Duration::milliseconds(100),
+            tests: 4,
+            failures: 1,
+            errors: 0,
+            skipped: 1,
+            flaky: 1,
+        };
+        FullReport::new(suites, vec![], summary, None)
+    }
+
+    #[test]
+    fn render_tests() {
+        let full_report = full_report();
+        let document = Document::new(&full_report, Utc.ymd(2020, 11, 2).and_hms(10, 0, 0));
+        let value: Value = serde_json::to_value(&document).unwrap();
+
+        assert_eq!(value["reportFormat"], "CTRF");
+        assert_eq!(
+            value["results"]["summary"],
+            json!({
+                "tests": 4, "passed": 2, "failed": 1, "pending": 0, "skipped": 1, "other": 0,
+                "start": 1604311199900i64, "stop": 1604311200000i64
+            })
+        );
+        assert_eq!(
+            value["results"]["tests"][1],
+            json!({
+                "name": "applies discounts",
+                "status": "failed",
+                "rawStatus": "failure",
+                "duration": 10,
+                "suite": "cart",
+                "message": "expected 90",
+                "trace": "AssertionError: expected 90\n\tat CartTest.java:12",
+                "retries": 0,
+                "flaky": false,
+                "stdout": ["line 1", "line 2"],
+                "extra": { "classname": "com.example.CartTest" }
+            })
+        );
+        let nested: Vec<(&str, &str, bool)> = value["results"]["tests"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .skip(2)
+            .map(|t| {
+                (
+                    t["suite"].as_str().unwrap(),
+                    t["status"].as_str().unwrap(),
+                    t["flaky"].as_bool().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            nested,
+            vec![
+                ("cart > checkout", "passed", true),
+                ("cart > checkout", "skipped", false)
+            ]
+        );
+        assert_eq!(
+            value["results"]["tests"][3]["message"],
+            "shipping is disabled"
+        );
+    }
+
+    #[test]
+    fn start_from_suite_timestamps() {
+        let mut full_report = full_report();
+        full_report.all_suites[0].value.metadata.timestamp =
+            Some(Utc.ymd(2020, 6, 7).and_hms(14, 18, 12));
+        let document = Document::new(&full_report, Utc::now());
+        assert_eq!(document.results.summary.start, 1591539492000);
+        assert_eq!(document.results.summary.stop, 1591539492100);
+    }
+}
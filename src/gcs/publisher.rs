@@ -38,15 +38,13 @@ impl GCSPublisher {
         github_run_id: String,
     ) -> Result<Self> {
         let mut report_files: Vec<PathBuf> = Vec::new();
-        let report_dir_pattern = vec![report_dir.to_str().unwrap(), "**/*"].join("/");
+        let report_dir_pattern = [report_dir.to_str().unwrap(), "**/*"].join("/");
 
         let paths = glob(&report_dir_pattern)?;
 
-        for path in paths {
-            if let Ok(path) = path {
-                if path.is_file() {
-                    report_files.push(path);
-                }
+        for path in paths.flatten() {
+            if path.is_file() {
+                report_files.push(path);
             }
         }
 
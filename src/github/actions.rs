@@ -0,0 +1,331 @@
+use crate::{
+    gcs::ReportUrl,
+    junit::{FailedTestCase, FullReport, SourceLocation},
+    markdown,
+};
+use anyhow::Context;
+use log::info;
+use std::{
+    env,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// GitHub only shows this many error annotations for each step.
+const ANNOTATIONS_MAX: usize = 10;
+
+/// The environment files and directories the GitHub Actions runner passes
+/// to the steps of a job.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionsEnv {
+    /// `$GITHUB_STEP_SUMMARY`, the markdown shown on the summary of the run
+    pub step_summary: Option<PathBuf>,
+    /// `$GITHUB_OUTPUT`, the `name=value` outputs of the step
+    pub output: Option<PathBuf>,
+    /// `$GITHUB_WORKSPACE`, which annotated files are relative to
+    pub workspace: PathBuf,
+}
+
+impl ActionsEnv {
+    /// Only set when running within a GitHub Actions workflow.
+    pub fn from_env() -> Option<Self> {
+        if env::var("GITHUB_ACTIONS").ok().as_deref() != Some("true") {
+            return None;
+        }
+        let workspace = env::var_os("GITHUB_WORKSPACE")
+            .map(PathBuf::from)
+            .or_else(|| env::current_dir().ok())
+            .unwrap_or_default();
+        Some(ActionsEnv {
+            step_summary: env::var_os("GITHUB_STEP_SUMMARY").map(PathBuf::from),
+            output: env::var_os("GITHUB_OUTPUT").map(PathBuf::from),
+            workspace,
+        })
+    }
+}
+
+/// Reports the outcome of the tests to the workflow running them: failed
+/// tests are annotated through workflow commands, the markdown report is
+/// added to the job summary and the counts are set as step outputs.
+pub struct ActionsPublisher {
+    env: ActionsEnv,
+    sink: Box<dyn io::Write>,
+}
+
+impl ActionsPublisher {
+    pub fn sink_to(env: ActionsEnv, sink: Box<dyn io::Write>) -> Self {
+        ActionsPublisher { env, sink }
+    }
+    /// Workflow commands are read from the output of the step.
+    pub fn stdout(env: ActionsEnv) -> Self {
+        ActionsPublisher::sink_to(env, Box::new(io::stdout()))
+    }
+
+    pub fn publish(
+        &mut self,
+        full_report: &FullReport,
+        report_url: Option<&ReportUrl>,
+    ) -> anyhow::Result<()> {
+        self.annotate(full_report)?;
+        if let Some(step_summary) = &self.env.step_summary {
+            info!("Adding the report to the job summary");
+            let summary = markdown::document_with_link(
+                full_report,
+                markdown::GITHUB_STEP_SUMMARY_MAX_SIZE,
+                report_url,
+            );
+            append(step_summary, &summary).context("Couldn't write the job summary")?;
+        }
+        if let Some(output) = &self.env.output {
+            let summary = &full_report.summary;
+            let mut outputs = format!(
+                "tests={}\nfailures={}\nerrors={}\nskipped={}\nflaky={}\n",
+                summary.tests, summary.failures, summary.errors, summary.skipped, summary.flaky
+            );
+            if let Some(report_url) = report_url {
+                outputs.push_str(&format!("report-url={}\n", report_url.0));
+            }
+            append(output, &outputs).context("Couldn't write the step outputs")?;
+        }
+        Ok(())
+    }
+
+    /// Unreadable reports come first, as they may hide any number of failed
+    /// tests. Failures GitHub would not show are summed up in a notice.
+    fn annotate(&mut self, full_report: &FullReport) -> anyhow::Result<()> {
+        let mut annotations: Vec<String> = full_report
+            .unreadable_reports
+            .iter()
+            .map(|report| {
+                error_command(
+                    None,
+                    "Unreadable report",
+                    &format!("{}: {}", report.path.display(), report.error),
+                )
+            })
+            .collect();
+        for suite in &full_report.failed {
+            for testcase in &suite.value.failed_testcases {
+                let title = format!("{} > {}", suite.value.name, testcase.name);
+                let location = testcase.source_location(&self.env.workspace);
+                annotations.push(error_command(
+                    location.as_ref(),
+                    &title,
+                    failure_message(testcase),
+                ));
+            }
+        }
+
+        for annotation in annotations.iter().take(ANNOTATIONS_MAX) {
+            writeln!(self.sink, "{}", annotation)?;
+        }
+        if annotations.len() > ANNOTATIONS_MAX {
+            writeln!(
+                self.sink,
+                "::notice::{} more failures are not annotated",
+                annotations.len() - ANNOTATIONS_MAX
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn failure_message(testcase: &FailedTestCase) -> &str {
+    let failure = &testcase.failure;
+    failure
+        .message
+        .as_deref()
+        .filter(|m| !m.trim().is_empty())
+        .unwrap_or_else(|| failure.stack_trace.trim())
+}
+
+fn error_command(location: Option<&SourceLocation>, title: &str, message: &str) -> String {
+    let mut properties = Vec::new();
+    if let Some(location) = location {
+        properties.push(format!("file={}", escape_property(&location.file)));
+        if let Some(line) = location.line {
+            properties.push(format!("line={}", line));
+        }
+    }
+    properties.push(format!("title={}", escape_property(title)));
+    format!("::error {}::{}", properties.join(","), escape_data(message))
+}
+
+/// Workflow commands are single lines.
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+fn append(path: &Path, content: &str) -> anyhow::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+    extern crate uuid;
+
+    use super::*;
+    use crate::junit::{
+        fixtures::{self, failure, suite, testcase, unreadable},
+        TestCase, TestFailure, UnreadableReport,
+    };
+    use pretty_assertions::assert_eq;
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+    use uuid::Uuid;
+
+    /// Keeps what is written to it readable once the publisher owns it.
+    #[derive(Clone, Default)]
+    struct SharedSink(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for SharedSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn failed(name: &str, stack_trace: &str, location: Option<SourceLocation>) -> TestCase {
+        TestCase {
+            classname: "tests.test_cart".to_owned(),
+            failure: Some(TestFailure {
+                stack_trace: stack_trace.to_owned(),
+                ..failure("assert 100 == 90\nwhere 100 = total()")
+            }),
+            location,
+            ..testcase(name)
+        }
+    }
+
+    fn workspace() -> PathBuf {
+        let mut dir = env::temp_dir();
+        dir.push(format!("cikit/actions-{}", Uuid::new_v4()));
+        fs::create_dir_all(dir.join("tests")).unwrap();
+        fs::write(dir.join("tests/test_cart.py"), "").unwrap();
+        dir
+    }
+
+    fn full_report(workspace: &Path, unreadable_reports: Vec<UnreadableReport>) -> FullReport {
+        let python_trace = format!(
+            "Traceback (most recent call last):\n  File \"/usr/lib/python3/unittest.py\", line 3, in run\n  File \"{}\", line 42, in test_totals\nAssertionError",
+            workspace.join("tests/test_cart.py").display()
+        );
+        let testcases = vec![
+            failed(
+                "test_discounts",
+                "",
+                Some(SourceLocation {
+                    file: "tests/test_cart.py".to_owned(),
+                    line: Some(12),
+                }),
+            ),
+            failed("test_totals", &python_trace, None),
+            failed(
+                "test_checkout",
+                "tests/test_cart.py:7: AssertionError",
+                None,
+            ),
+            failed("test_shipping", "at Cart.ship(Cart.java:3)", None),
+        ];
+        fixtures::full_report(
+            vec![suite("tests.test_cart", testcases)],
+            unreadable_reports,
+        )
+    }
+
+    #[test]
+    fn publish_to_the_runner() {
+        let workspace = workspace();
+        let env = ActionsEnv {
+            step_summary: Some(workspace.join("step_summary.md")),
+            output: Some(workspace.join("output")),
+            workspace: workspace.clone(),
+        };
+        fs::write(workspace.join("step_summary.md"), "# Build\n").unwrap();
+        let sink = SharedSink::default();
+        let report_url = ReportUrl("https://storage.example.com/report/index.html".to_owned());
+
+        ActionsPublisher::sink_to(env, Box::new(sink.clone()))
+            .publish(
+                &full_report(
+                    &workspace,
+                    vec![unreadable(PathBuf::from("reports/broken.xml"))],
+                ),
+                Some(&report_url),
+            )
+            .unwrap();
+
+        let commands = String::from_utf8(sink.0.lock().unwrap().clone()).unwrap();
+        let message = "assert 100 == 90%0Awhere 100 = total()";
+        assert_eq!(
+            commands.lines().collect::<Vec<&str>>(),
+            vec![
+                "::error title=Unreadable report::reports/broken.xml: unexpected end of file".to_owned(),
+                format!("::error file=tests/test_cart.py,line=12,title=tests.test_cart > test_discounts::{}", message),
+                format!("::error file=tests/test_cart.py,line=42,title=tests.test_cart > test_totals::{}", message),
+                format!("::error file=tests/test_cart.py,line=7,title=tests.test_cart > test_checkout::{}", message),
+                format!("::error title=tests.test_cart > test_shipping::{}", message),
+            ]
+        );
+
+        let step_summary = fs::read_to_string(workspace.join("step_summary.md")).unwrap();
+        assert!(step_summary.starts_with("# Build\n### :x: Test suite failed with _4_ failures"));
+        assert!(step_summary
+            .ends_with("[Test report](https://storage.example.com/report/index.html)\n"));
+
+        let output = fs::read_to_string(workspace.join("output")).unwrap();
+        assert_eq!(
+            output,
+            "tests=4\nfailures=4\nerrors=1\nskipped=0\nflaky=0\nreport-url=https://storage.example.com/report/index.html\n"
+        );
+    }
+
+    #[test]
+    fn limit_annotations() {
+        let workspace = workspace();
+        let env = ActionsEnv {
+            step_summary: None,
+            output: None,
+            workspace: workspace.clone(),
+        };
+        let unreadable_reports = (0..11)
+            .map(|i| unreadable(PathBuf::from(format!("reports/{}.xml", i))))
+            .collect();
+        let full_report = full_report(&workspace, unreadable_reports);
+        let sink = SharedSink::default();
+
+        ActionsPublisher::sink_to(env, Box::new(sink.clone()))
+            .publish(&full_report, None)
+            .unwrap();
+
+        let commands = String::from_utf8(sink.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(commands.lines().count(), ANNOTATIONS_MAX + 1);
+        assert!(commands.ends_with("::notice::5 more failures are not annotated\n"));
+    }
+
+    #[test]
+    fn escape_workflow_commands() {
+        let location = SourceLocation {
+            file: "tests/a,b.py".to_owned(),
+            line: None,
+        };
+        assert_eq!(
+            error_command(Some(&location), "suite: 100%", "50%\r\ndone"),
+            "::error file=tests/a%2Cb.py,title=suite%3A 100%25::50%25%0D%0Adone"
+        );
+    }
+}
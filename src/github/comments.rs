@@ -1,5 +1,5 @@
 use super::GithubContext;
-use crate::{config::GithubNotifications, gcs::ReportUrl, junit::FullReport};
+use crate::{config::GithubNotifications, gcs::ReportUrl, junit::FullReport, markdown};
 use log::{info, warn};
 use std::io::Read;
 
@@ -22,20 +22,11 @@ impl CommentPublisher {
         report_url: Option<&ReportUrl>,
     ) -> anyhow::Result<()> {
         let mut response_body = String::new();
-        let mut comment = if full_report.is_successful() {
-            ":heavy_check_mark: Test suite passed!".to_owned()
-        } else {
-            format!(
-                ":x: Test suite failed with _{}_ errors",
-                full_report.failed.len()
-            )
-        };
-        if let Some(report_url) = report_url {
-            comment.push_str(&format!(
-                "\n:bookmark_tabs: [Test report]({})",
-                report_url.0
-            ));
-        }
+        let comment = markdown::document_with_link(
+            full_report,
+            markdown::GITHUB_COMMENT_MAX_SIZE,
+            report_url,
+        );
 
         let endpoint_url = format!(
             "https://api.github.com/repos/{}/issues/{}/comments",
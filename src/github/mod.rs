@@ -68,4 +68,5 @@ mod tests {
     }
 }
 
+pub mod actions;
 pub mod comments;
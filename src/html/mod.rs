@@ -3,16 +3,18 @@ use std::path::{Path, PathBuf};
 
 use crate::{console::ConsoleJsonReport, junit::FullReport};
 use fs::File;
-use log::debug;
+use log::{debug, warn};
 
 const UI_BUILD_ASSETS: &[(&str, &[u8])] =
     &include!(concat!(env!("OUT_DIR"), "/ui_build_assets.rs"));
 
 pub struct HTMLReport {
     path: PathBuf,
+    /// Attachments referenced by a relative path are found from there
+    project_dir: PathBuf,
 }
 impl HTMLReport {
-    pub fn new<P>(path: P, overwrite_existing: bool) -> anyhow::Result<Self>
+    pub fn new<P>(path: P, overwrite_existing: bool, project_dir: PathBuf) -> anyhow::Result<Self>
     where
         P: AsRef<Path>,
     {
@@ -26,6 +28,7 @@ impl HTMLReport {
 
         Ok(HTMLReport {
             path: path.to_owned(),
+            project_dir,
         })
     }
 
@@ -38,10 +41,67 @@ impl HTMLReport {
             fs::write(file_path, *file_content)?;
         }
 
+        self.copy_attachments(full_report)?;
+
         let json_data_path = &self.path.join("data.json");
         let json_data = File::create(json_data_path)?;
         let mut json_report = ConsoleJsonReport::sink_to(true, Box::new(json_data));
         json_report.render(full_report)?;
         Ok(())
     }
+
+    /// Copies the files attached to the tests next to the report, so that the
+    /// UI can link to them. Missing files are left out.
+    fn copy_attachments(&self, full_report: &FullReport) -> anyhow::Result<()> {
+        for attachment in full_report.attachments() {
+            let source = self.project_dir.join(&attachment.path);
+            if !source.is_file() {
+                warn!("Skipping missing attachment: {}", source.display());
+                continue;
+            }
+            let file_path = &self.path.join(attachment.report_path());
+            debug!("Copying attachment to: {}", file_path.display());
+            fs::create_dir_all(file_path.parent().expect("File has no parent dir!"))?;
+            fs::copy(&source, file_path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate uuid;
+
+    use super::*;
+    use crate::junit::{
+        fixtures::{full_report, suite, testcase},
+        Attachment, TestCase,
+    };
+    use std::env;
+    use uuid::Uuid;
+
+    #[test]
+    fn copy_attachments_of_passed_tests() {
+        let mut project_dir = env::temp_dir();
+        project_dir.push(format!("cikit/html-{}", Uuid::new_v4()));
+        fs::create_dir_all(project_dir.join("screenshots")).unwrap();
+        fs::write(project_dir.join("screenshots/cart.png"), "png").unwrap();
+        let passed = TestCase {
+            attachments: vec![Attachment {
+                name: "screenshot".to_owned(),
+                content_type: Some("image/png".to_owned()),
+                path: PathBuf::from("screenshots/cart.png"),
+            }],
+            ..testcase("adds items")
+        };
+        let full_report = full_report(vec![suite("cart", vec![passed])], vec![]);
+
+        HTMLReport::new(project_dir.join("report"), false, project_dir.clone())
+            .unwrap()
+            .copy_attachments(&full_report)
+            .unwrap();
+
+        let copied = project_dir.join("report/attachments/screenshots/cart.png");
+        assert_eq!(fs::read_to_string(copied).unwrap(), "png");
+    }
 }
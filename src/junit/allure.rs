@@ -0,0 +1,570 @@
+use super::{
+    non_blank, Attachment, AttemptKind, SuiteWithSummary, TestAttempt, TestCase, TestFailure,
+    TestSkipped, TestSuite,
+};
+use anyhow::{bail, Context, Result};
+use chrono::{Duration, TimeZone, Utc};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+const RESULT_SUFFIX: &str = "-result.json";
+const CONTAINER_SUFFIX: &str = "-container.json";
+
+/// The outcome of a single run of a test, written to `<uuid>-result.json`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AllureResult {
+    uuid: String,
+    history_id: Option<String>,
+    name: String,
+    status: Option<String>,
+    #[serde(default)]
+    status_details: StatusDetails,
+    start: Option<i64>,
+    stop: Option<i64>,
+    #[serde(default)]
+    labels: Vec<Label>,
+    #[serde(default)]
+    attachments: Vec<AllureAttachment>,
+    #[serde(default)]
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StatusDetails {
+    message: Option<String>,
+    trace: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Label {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AllureAttachment {
+    name: String,
+    source: String,
+    #[serde(rename = "type")]
+    content_type: Option<String>,
+}
+
+/// Either a step of a test or a set up/tear down fixture, which may have
+/// steps of its own.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Step {
+    #[serde(default)]
+    name: String,
+    status: Option<String>,
+    #[serde(default)]
+    status_details: StatusDetails,
+    start: Option<i64>,
+    stop: Option<i64>,
+    #[serde(default)]
+    attachments: Vec<AllureAttachment>,
+    #[serde(default)]
+    steps: Vec<Step>,
+}
+
+/// Groups test results, along with the fixtures they share, written to
+/// `<uuid>-container.json`.
+#[derive(Debug, Deserialize)]
+struct Container {
+    name: Option<String>,
+    #[serde(default)]
+    children: Vec<String>,
+    #[serde(default)]
+    befores: Vec<Step>,
+    #[serde(default)]
+    afters: Vec<Step>,
+}
+
+/// Whether a directory holds Allure results.
+pub(super) fn is_results_dir(path: &Path) -> bool {
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .any(|entry| entry.file_name().to_string_lossy().ends_with(RESULT_SUFFIX))
+        })
+        .unwrap_or(false)
+}
+
+/// Whether a file is one of those Allure writes to its results directory,
+/// which are only read as part of the whole directory.
+pub(crate) fn is_results_file(path: &Path) -> bool {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    file_name.ends_with(RESULT_SUFFIX)
+        || file_name.ends_with(CONTAINER_SUFFIX)
+        || file_name.contains("-attachment.")
+}
+
+/// Reads an Allure results directory. Tests are grouped into suites after
+/// their `parentSuite`, `suite` and `subSuite` labels, or else after the
+/// container they belong to. Results sharing a history id are runs of the
+/// same test, the latest of which is the one reported.
+pub(super) fn read_suites<F>(dir: &Path, mut on_testcase: F) -> Result<Vec<SuiteWithSummary>>
+where
+    F: FnMut(&TestCase),
+{
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    paths.sort();
+
+    let mut results: Vec<AllureResult> = Vec::new();
+    let mut containers: Vec<Container> = Vec::new();
+    for path in &paths {
+        let with_path = || format!("Couldn't read {}", path.display());
+        if path.to_string_lossy().ends_with(RESULT_SUFFIX) {
+            let file = BufReader::new(File::open(path)?);
+            results.push(serde_json::from_reader(file).with_context(with_path)?);
+        } else if path.to_string_lossy().ends_with(CONTAINER_SUFFIX) {
+            let file = BufReader::new(File::open(path)?);
+            containers.push(serde_json::from_reader(file).with_context(with_path)?);
+        }
+    }
+    if results.is_empty() {
+        bail!("No Allure results found");
+    }
+    // tests are listed in the order they ran, rather than by uuid
+    results.sort_by_key(|r| (r.start.is_none(), r.start, r.stop));
+
+    let default_suite = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut container_names: BTreeMap<&str, &str> = BTreeMap::new();
+    for container in &containers {
+        if let Some(name) = container.name.as_deref().filter(|n| !n.trim().is_empty()) {
+            for child in &container.children {
+                container_names.entry(child).or_insert(name);
+            }
+        }
+    }
+
+    let mut runs: Vec<Vec<AllureResult>> = Vec::new();
+    let mut run_positions: BTreeMap<String, usize> = BTreeMap::new();
+    for result in results {
+        let history_id = result
+            .history_id
+            .clone()
+            .unwrap_or_else(|| result.uuid.clone());
+        match run_positions.get(&history_id) {
+            Some(position) => runs[*position].push(result),
+            None => {
+                run_positions.insert(history_id, runs.len());
+                runs.push(vec![result]);
+            }
+        }
+    }
+
+    let mut suites: Vec<TestSuite> = Vec::new();
+    let mut suite_positions: BTreeMap<String, usize> = BTreeMap::new();
+    let mut result_suites: BTreeMap<String, String> = BTreeMap::new();
+    for mut results in runs {
+        let last = results.pop().expect("Results are grouped by history id");
+        let name = suite_name(&last, &container_names).unwrap_or_else(|| default_suite.clone());
+        for result in results.iter().chain(std::iter::once(&last)) {
+            result_suites.insert(result.uuid.clone(), name.clone());
+        }
+
+        let started = last
+            .start
+            .and_then(|start| Utc.timestamp_millis_opt(start).single());
+        let testcase = testcase(last, results, &name, dir);
+        on_testcase(&testcase);
+        let suite = suite(&mut suites, &mut suite_positions, &name);
+        suite.time = suite.time + testcase.time;
+        suite.metadata.timestamp = match (suite.metadata.timestamp, started) {
+            (Some(timestamp), Some(started)) => Some(timestamp.min(started)),
+            (timestamp, started) => timestamp.or(started),
+        };
+        suite.testcases.push(testcase);
+    }
+
+    // failed set ups and tear downs are reported as errors of the suite of
+    // the tests they ran around
+    for container in &containers {
+        let name = container
+            .children
+            .iter()
+            .find_map(|child| result_suites.get(child))
+            .cloned()
+            .or_else(|| container.name.clone().and_then(non_blank))
+            .unwrap_or_else(|| default_suite.clone());
+        for fixture in container.befores.iter().chain(&container.afters) {
+            if !is_failed(fixture.status.as_deref()) {
+                continue;
+            }
+            let testcase = TestCase {
+                time: elapsed(fixture.start, fixture.stop),
+                error: Some(failure(&fixture.status_details)),
+                attachments: attachments(&fixture.attachments, &fixture.steps, dir),
+                ..TestCase::new(fixture.name.clone(), name.clone())
+            };
+            on_testcase(&testcase);
+            let suite = suite(&mut suites, &mut suite_positions, &name);
+            suite.time = suite.time + testcase.time;
+            suite.testcases.push(testcase);
+        }
+    }
+
+    Ok(suites.into_iter().map(|s| s.with_summary()).collect())
+}
+
+fn suite<'a>(
+    suites: &'a mut Vec<TestSuite>,
+    positions: &mut BTreeMap<String, usize>,
+    name: &str,
+) -> &'a mut TestSuite {
+    let position = match positions.get(name) {
+        Some(position) => *position,
+        None => {
+            positions.insert(name.to_owned(), suites.len());
+            suites.push(TestSuite::new(name.to_owned()));
+            suites.len() - 1
+        }
+    };
+    &mut suites[position]
+}
+
+fn suite_name(result: &AllureResult, container_names: &BTreeMap<&str, &str>) -> Option<String> {
+    let path: Vec<&str> = ["parentSuite", "suite", "subSuite"]
+        .iter()
+        .filter_map(|name| label(result, name))
+        .collect();
+    if path.is_empty() {
+        container_names
+            .get(result.uuid.as_str())
+            .map(|name| (*name).to_owned())
+            .or_else(|| label(result, "testClass").map(str::to_owned))
+    } else {
+        Some(path.join(" > "))
+    }
+}
+
+fn label<'a>(result: &'a AllureResult, name: &str) -> Option<&'a str> {
+    result
+        .labels
+        .iter()
+        .find(|label| label.name == name && !label.value.trim().is_empty())
+        .map(|label| label.value.as_str())
+}
+
+/// Earlier runs of the test are kept as its failed attempts.
+fn testcase(result: AllureResult, earlier: Vec<AllureResult>, suite: &str, dir: &Path) -> TestCase {
+    let status = result.status.as_deref().unwrap_or("unknown");
+    let passed = status == "passed";
+    let mut testcase = TestCase {
+        time: elapsed(result.start, result.stop),
+        attempts: earlier
+            .iter()
+            .filter(|run| is_failed(run.status.as_deref()))
+            .map(|run| TestAttempt {
+                kind: match (passed, run.status.as_deref()) {
+                    (true, Some("broken")) => AttemptKind::FlakyError,
+                    (true, _) => AttemptKind::FlakyFailure,
+                    (false, Some("broken")) => AttemptKind::RerunError,
+                    (false, _) => AttemptKind::RerunFailure,
+                },
+                failure: failure(&run.status_details),
+                system_out: None,
+                system_err: None,
+            })
+            .collect(),
+        ..TestCase::new(
+            result.name.clone(),
+            label(&result, "testClass").unwrap_or(suite).to_owned(),
+        )
+    };
+
+    // output captured as attachments is read back as the test output
+    for attachment in attachments(&result.attachments, &result.steps, dir) {
+        let output = match attachment.name.as_str() {
+            "stdout" => &mut testcase.system_out,
+            "stderr" => &mut testcase.system_err,
+            _ => {
+                testcase.attachments.push(attachment);
+                continue;
+            }
+        };
+        match fs::read_to_string(&attachment.path) {
+            Ok(content) if output.is_none() => *output = non_blank(content),
+            _ => testcase.attachments.push(attachment),
+        }
+    }
+
+    match status {
+        "passed" => (),
+        "failed" => testcase.failure = Some(failure(&result.status_details)),
+        "broken" => testcase.error = Some(failure(&result.status_details)),
+        "skipped" => {
+            testcase.skipped = Some(TestSkipped {
+                message: result.status_details.message.and_then(non_blank),
+                details: None,
+            })
+        }
+        // the test did not finish, or its status could not be told
+        _ => {
+            testcase.skipped = Some(TestSkipped {
+                message: result
+                    .status_details
+                    .message
+                    .and_then(non_blank)
+                    .or_else(|| Some(format!("{} status", status))),
+                details: None,
+            })
+        }
+    }
+    testcase
+}
+
+fn is_failed(status: Option<&str>) -> bool {
+    status == Some("failed") || status == Some("broken")
+}
+
+fn failure(details: &StatusDetails) -> TestFailure {
+    TestFailure {
+        message: details.message.clone().and_then(non_blank),
+        classname: String::new(),
+        stack_trace: details
+            .trace
+            .as_deref()
+            .unwrap_or_default()
+            .trim()
+            .to_owned(),
+    }
+}
+
+/// Start and stop times are in milliseconds since the epoch.
+fn elapsed(start: Option<i64>, stop: Option<i64>) -> Duration {
+    match (start, stop) {
+        (Some(start), Some(stop)) if stop > start => Duration::milliseconds(stop - start),
+        _ => Duration::zero(),
+    }
+}
+
+/// Collects the files attached to a test and to any of its steps.
+fn attachments(attachments: &[AllureAttachment], steps: &[Step], dir: &Path) -> Vec<Attachment> {
+    let mut all: Vec<Attachment> = attachments
+        .iter()
+        .map(|a| Attachment {
+            name: a.name.clone(),
+            content_type: a.content_type.clone(),
+            path: dir.join(&a.source),
+        })
+        .collect();
+    for step in steps {
+        all.extend(self::attachments(&step.attachments, &step.steps, dir));
+    }
+    all
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+    extern crate uuid;
+
+    use super::*;
+    use crate::{
+        allure::AllureResults,
+        junit::{FullReport, HasOutcome, Summary, TestOutcome},
+    };
+    use pretty_assertions::assert_eq;
+    use std::env;
+    use uuid::Uuid;
+
+    const RESULTS: [(&str, &str); 7] = [
+        (
+            "a1-result.json",
+            r##"{"uuid": "a1", "historyId": "h1", "name": "adds items", "status": "passed", "stage": "finished",
+                "start": 1604311200000, "stop": 1604311200012,
+                "labels": [{"name": "parentSuite", "value": "shop"}, {"name": "suite", "value": "cart"}, {"name": "testClass", "value": "tests.test_cart"}],
+                "attachments": [{"name": "stdout", "source": "a1-attachment.txt", "type": "text/plain"}]}"##,
+        ),
+        (
+            "a2-result.json",
+            r##"{"uuid": "a2", "historyId": "h2", "name": "applies discounts", "status": "failed",
+                "statusDetails": {"message": "AssertionError: expected 90", "trace": "tests/test_cart.py:12: AssertionError\n"},
+                "start": 1604311200012, "stop": 1604311200042,
+                "labels": [{"name": "parentSuite", "value": "shop"}, {"name": "suite", "value": "cart"}],
+                "steps": [{"name": "checks the total", "status": "failed", "attachments": [{"name": "screenshot", "source": "a2-attachment.png", "type": "image/png"}]}]}"##,
+        ),
+        (
+            "a3-result.json",
+            r##"{"uuid": "a3", "historyId": "h3", "name": "checks out", "status": "broken",
+                "statusDetails": {"message": "TimeoutError"}, "start": 1604311200042, "stop": 1604311200100}"##,
+        ),
+        (
+            "a4-result.json",
+            r##"{"uuid": "a4", "historyId": "h3", "name": "checks out", "status": "passed",
+                "start": 1604311200100, "stop": 1604311200140}"##,
+        ),
+        (
+            "a5-result.json",
+            r##"{"uuid": "a5", "name": "ships abroad", "status": "skipped",
+                "statusDetails": {"message": "shipping is disabled"}}"##,
+        ),
+        (
+            "c1-container.json",
+            r##"{"uuid": "c1", "name": "checkout", "children": ["a3", "a4", "a5"],
+                "afters": [{"name": "close_browser", "status": "broken", "statusDetails": {"message": "browser crashed"}, "start": 1604311200140, "stop": 1604311200150}]}"##,
+        ),
+        ("a1-attachment.txt", "adding 2 items\n"),
+    ];
+
+    fn read_results() -> (PathBuf, Vec<TestSuite>) {
+        let mut dir = env::temp_dir();
+        dir.push(format!("cikit/allure-{}/allure-results", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, content) in &RESULTS {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        let suites = read_suites(&dir, |_| ())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.value)
+            .collect();
+        (dir, suites)
+    }
+
+    #[test]
+    fn group_results_by_suite() {
+        let (dir, suites) = read_results();
+        assert!(is_results_dir(&dir));
+
+        let names: Vec<&str> = suites.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["shop > cart", "checkout"]);
+        assert_eq!(suites[0].time, Duration::milliseconds(42));
+        assert_eq!(
+            suites[0].metadata.timestamp,
+            Some(Utc.ymd(2020, 11, 2).and_hms(10, 0, 0))
+        );
+        assert_eq!(suites[0].testcases[0].classname, "tests.test_cart");
+        assert_eq!(suites[0].testcases[1].classname, "shop > cart");
+        assert_eq!(
+            suites[0].testcases[0].system_out,
+            Some("adding 2 items\n".to_owned())
+        );
+        assert!(suites[0].testcases[0].attachments.is_empty());
+        assert_eq!(
+            suites[0].testcases[1].attachments[0].path,
+            dir.join("a2-attachment.png")
+        );
+    }
+
+    #[test]
+    fn parse_statuses() {
+        let (_, suites) = read_results();
+        let outcomes: Vec<(&str, TestOutcome)> = suites
+            .iter()
+            .flat_map(|s| s.testcases.iter())
+            .map(|t| (t.name.as_str(), t.outcome()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("adds items", TestOutcome::Passed),
+                ("applies discounts", TestOutcome::Failed),
+                ("checks out", TestOutcome::Flaky),
+                ("ships abroad", TestOutcome::Skipped),
+                ("close_browser", TestOutcome::Errored),
+            ]
+        );
+
+        let failure = suites[0].testcases[1].failure.as_ref().unwrap();
+        assert_eq!(
+            failure.message,
+            Some("AssertionError: expected 90".to_owned())
+        );
+        assert_eq!(failure.stack_trace, "tests/test_cart.py:12: AssertionError");
+
+        let flaky = &suites[1].testcases[0];
+        assert_eq!(flaky.attempts.len(), 1);
+        assert_eq!(flaky.attempts[0].kind, AttemptKind::FlakyError);
+        assert_eq!(
+            flaky.attempts[0].failure.message,
+            Some("TimeoutError".to_owned())
+        );
+        assert_eq!(
+            suites[1].testcases[2].error.as_ref().unwrap().message,
+            Some("browser crashed".to_owned())
+        );
+    }
+
+    #[test]
+    fn read_written_results() {
+        let (dir, suites) = read_results();
+        let full_report = FullReport::new(
+            suites.into_iter().map(TestSuite::with_summary).collect(),
+            vec![],
+            Summary::zero(),
+            None,
+        );
+        let written = dir.with_file_name("written-results");
+        AllureResults::new(&written, false)
+            .unwrap()
+            .write(&full_report)
+            .unwrap();
+
+        let (_, suites) = read_results();
+        let read_back: Vec<TestSuite> = read_suites(&written, |_| ())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.value)
+            .collect();
+        assert_eq!(read_back.len(), suites.len());
+        for (read_back, suite) in read_back.iter().zip(&suites) {
+            assert_eq!(read_back.name, suite.name);
+            assert_eq!(read_back.time, suite.time);
+            assert_eq!(read_back.testcases.len(), suite.testcases.len());
+            for (read_back, testcase) in read_back.testcases.iter().zip(&suite.testcases) {
+                assert_eq!(read_back.name, testcase.name);
+                assert_eq!(read_back.classname, testcase.classname);
+                assert_eq!(read_back.outcome(), testcase.outcome());
+                assert_eq!(read_back.system_out, testcase.system_out);
+                assert_eq!(read_back.attempts, testcase.attempts);
+            }
+        }
+    }
+
+    #[test]
+    fn ignore_out_of_range_start_times() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("cikit/allure-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("a1-result.json"),
+            r##"{"uuid": "a1", "name": "adds items", "status": "passed", "start": 9223372036854775807}"##,
+        )
+        .unwrap();
+        let suites = read_suites(&dir, |_| ()).unwrap();
+        assert_eq!(suites[0].value.metadata.timestamp, None);
+        assert_eq!(suites[0].value.testcases[0].time, Duration::zero());
+    }
+
+    #[test]
+    fn not_allure_results() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("cikit/allure-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("report.json"), "{}").unwrap();
+        assert!(!is_results_dir(&dir));
+        assert!(read_suites(&dir, |_| ()).is_err());
+        assert!(is_results_file(Path::new(
+            "allure-results/a1-attachment.png"
+        )));
+        assert!(!is_results_file(Path::new("reports/jest-results.json")));
+    }
+}
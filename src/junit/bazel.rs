@@ -0,0 +1,457 @@
+use super::{
+    AttemptKind, HasOutcome, SuiteMetadata, SuiteWithSummary, TestAttempt, TestCase, TestOutcome,
+    TestSuite,
+};
+use anyhow::{Context, Result};
+use chrono::Duration;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// The logs Bazel keeps for a test target, under
+/// `bazel-testlogs/<package>/<target>`.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Target {
+    /// e.g. `//app/cart:cart_test`
+    pub label: String,
+    pub dir: PathBuf,
+    runs: Vec<Run>,
+}
+
+/// The `test.xml` report and the `test.log` next to it, for a shard and run
+/// of a target, or for an earlier attempt at running a flaky one.
+#[derive(Debug, Clone, PartialEq)]
+struct Run {
+    shard: u32,
+    run: u32,
+    /// Left out for the final attempt
+    attempt: Option<u32>,
+    xml: PathBuf,
+    log: PathBuf,
+}
+
+impl Target {
+    /// Whether the given file is one of the reports of this target.
+    pub fn has_report(&self, path: &Path) -> bool {
+        self.runs.iter().any(|run| run.xml == path)
+    }
+}
+
+impl Run {
+    /// Tells the target, shard and run a report belongs to from its path,
+    /// which is one of:
+    /// - `<target>/test.xml`
+    /// - `<target>/shard_1_of_2/test.xml`
+    /// - `<target>/run_1_of_3/test.xml`
+    /// - `<target>/shard_1_of_2_run_1_of_3/test.xml`
+    /// - any of the above with `test.xml` replaced by `test_attempts/attempt_1.xml`
+    fn from_path(path: &Path) -> Option<(PathBuf, Run)> {
+        let file_name = path.file_name()?.to_str()?;
+        let mut dir = path.parent()?;
+        let attempt = if file_name == "test.xml" {
+            None
+        } else {
+            let attempt = numbered(file_name.strip_suffix(".xml")?, "attempt_")?;
+            if dir.file_name()? != "test_attempts" {
+                return None;
+            }
+            dir = dir.parent()?;
+            Some(attempt)
+        };
+        let log = match attempt {
+            None => path.with_file_name("test.log"),
+            Some(_) => path.with_extension("log"),
+        };
+        let (shard, run) = match dir.file_name()?.to_str().and_then(shard_and_run) {
+            Some(numbers) => {
+                dir = dir.parent()?;
+                numbers
+            }
+            None => (1, 1),
+        };
+        let run = Run {
+            shard,
+            run,
+            attempt,
+            xml: path.to_owned(),
+            log,
+        };
+        Some((dir.to_owned(), run))
+    }
+
+    /// Earlier attempts come before the final one.
+    fn order(&self) -> (u32, u32, bool, u32) {
+        (
+            self.shard,
+            self.run,
+            self.attempt.is_none(),
+            self.attempt.unwrap_or_default(),
+        )
+    }
+}
+
+fn shard_and_run(dir_name: &str) -> Option<(u32, u32)> {
+    if let Some(run) = numbered(dir_name, "run_") {
+        return Some((1, run));
+    }
+    let (shard, run) = match dir_name.find("_run_") {
+        Some(i) => (&dir_name[..i], numbered(&dir_name[i + 1..], "run_")?),
+        None => (dir_name, 1),
+    };
+    Some((numbered(shard, "shard_")?, run))
+}
+
+/// Parses names such as `shard_1_of_2` or `attempt_1`.
+fn numbered(name: &str, prefix: &str) -> Option<u32> {
+    let mut parts = name.strip_prefix(prefix)?.splitn(2, "_of_");
+    let number = parts.next()?.parse().ok()?;
+    match parts.next() {
+        Some(total) => total.parse::<u32>().ok().map(|_| number),
+        None => Some(number),
+    }
+}
+
+/// Finds the test targets whose logs are kept under a `bazel-testlogs`
+/// directory.
+pub(super) fn find_targets(testlogs: &Path) -> io::Result<Vec<Target>> {
+    let mut runs: BTreeMap<PathBuf, Vec<Run>> = BTreeMap::new();
+    collect_runs(testlogs, &mut runs)?;
+    Ok(runs
+        .into_iter()
+        .filter_map(|(dir, mut runs)| {
+            runs.sort_by_key(Run::order);
+            let label = label(testlogs, &dir)?;
+            Some(Target { label, dir, runs })
+        })
+        .collect())
+}
+
+fn collect_runs(dir: &Path, runs: &mut BTreeMap<PathBuf, Vec<Run>>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_runs(&path, runs)?;
+        } else if let Some((target_dir, run)) = Run::from_path(&path) {
+            runs.entry(target_dir).or_default().push(run);
+        }
+    }
+    Ok(())
+}
+
+/// Targets are labelled after their directory, the last component of which
+/// is the target name and the others its package.
+fn label(testlogs: &Path, dir: &Path) -> Option<String> {
+    let components: Vec<String> = dir
+        .strip_prefix(testlogs)
+        .ok()?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let (target, package) = components.split_last()?;
+    Some(format!("//{}:{}", package.join("/"), target))
+}
+
+/// The runs of a test case, and of the suite reporting it.
+struct MergedSuite {
+    suite: TestSuite,
+    testcases: Vec<Vec<TestCase>>,
+    /// The runs of each test case, by name and classname
+    testcase_positions: HashMap<(String, String), usize>,
+    /// The longest run of each shard
+    shard_times: BTreeMap<u32, Duration>,
+}
+
+/// Reads all the shards and runs of a target into a suite named after its
+/// label, nesting the suites they report. Tests which failed in some of the
+/// runs but passed in others are flaky. Failed targets are given the
+/// `test.log` of their failed runs as output.
+pub(super) fn read_target<F>(target: &Target, mut on_testcase: F) -> Result<Vec<SuiteWithSummary>>
+where
+    F: FnMut(&TestCase),
+{
+    let mut merged: Vec<MergedSuite> = Vec::new();
+    let mut suite_positions: HashMap<String, usize> = HashMap::new();
+    let mut failed_logs: Vec<String> = Vec::new();
+    for run in &target.runs {
+        let file = File::open(&run.xml).context("Couldn't open report file")?;
+        let suites = super::read_suites(file, |_| ())
+            .with_context(|| format!("Couldn't parse {}", run.xml.display()))?;
+        let failed = suites
+            .iter()
+            .any(|s| !s.outcome().is_successful() && s.outcome() != TestOutcome::Skipped);
+        if failed {
+            if let Ok(log) = fs::read_to_string(&run.log) {
+                failed_logs.push(log);
+            }
+        }
+
+        for suite in suites.into_iter().flat_map(|s| s.value.flatten()) {
+            let position = match suite_positions.get(&suite.name) {
+                Some(position) => *position,
+                None => {
+                    suite_positions.insert(suite.name.clone(), merged.len());
+                    merged.push(MergedSuite {
+                        suite: TestSuite {
+                            testcases: Vec::new(),
+                            ..suite.clone()
+                        },
+                        testcases: Vec::new(),
+                        testcase_positions: HashMap::new(),
+                        shard_times: BTreeMap::new(),
+                    });
+                    merged.len() - 1
+                }
+            };
+            let merged = &mut merged[position];
+            let time = merged
+                .shard_times
+                .entry(run.shard)
+                .or_insert_with(Duration::zero);
+            *time = (*time).max(suite.time);
+            for testcase in suite.testcases {
+                let key = (testcase.name.clone(), testcase.classname.clone());
+                match merged.testcase_positions.get(&key) {
+                    Some(position) => merged.testcases[*position].push(testcase),
+                    None => {
+                        merged
+                            .testcase_positions
+                            .insert(key, merged.testcases.len());
+                        merged.testcases.push(vec![testcase]);
+                    }
+                }
+            }
+        }
+    }
+
+    let testsuites: Vec<TestSuite> = merged
+        .into_iter()
+        .map(|m| TestSuite {
+            time: m
+                .shard_times
+                .values()
+                .fold(Duration::zero(), |acc, time| acc + *time),
+            testcases: m.testcases.into_iter().map(merge_runs).collect(),
+            ..m.suite
+        })
+        .collect();
+    for testcase in testsuites.iter().flat_map(|s| s.testcases.iter()) {
+        on_testcase(testcase);
+    }
+
+    let mut suite = TestSuite {
+        time: testsuites
+            .iter()
+            .fold(Duration::zero(), |acc, s| acc + s.time),
+        metadata: SuiteMetadata {
+            file: Some(target.dir.display().to_string()),
+            ..SuiteMetadata::default()
+        },
+        testsuites,
+        ..TestSuite::new(target.label.clone())
+    };
+    let outcome = suite.outcome();
+    if outcome == TestOutcome::Failed || outcome == TestOutcome::Errored {
+        suite.system_out = super::non_blank(failed_logs.join("\n"));
+    }
+    Ok(vec![suite.with_summary()])
+}
+
+/// Keeps the last run which passed, if any, or else the last one, the other
+/// failed runs becoming its attempts.
+fn merge_runs(runs: Vec<TestCase>) -> TestCase {
+    let (failed, passed): (Vec<TestCase>, Vec<TestCase>) = runs.into_iter().partition(|t| {
+        let outcome = t.outcome();
+        outcome == TestOutcome::Failed || outcome == TestOutcome::Errored
+    });
+    let flaky = !passed.is_empty();
+    let (mut testcase, failed) = match passed.into_iter().last() {
+        Some(testcase) => (testcase, failed),
+        None => {
+            let mut failed = failed;
+            let last = failed.pop().expect("A test case has at least one run");
+            (last, failed)
+        }
+    };
+
+    let mut attempts: Vec<TestAttempt> = failed
+        .into_iter()
+        .map(|run| {
+            let (kind, failure) = match (run.failure, run.error) {
+                (Some(failure), _) if flaky => (AttemptKind::FlakyFailure, failure),
+                (Some(failure), _) => (AttemptKind::RerunFailure, failure),
+                (None, Some(error)) if flaky => (AttemptKind::FlakyError, error),
+                (None, Some(error)) => (AttemptKind::RerunError, error),
+                (None, None) => unreachable!("Failed runs have a failure or an error"),
+            };
+            TestAttempt {
+                kind,
+                failure,
+                system_out: run.system_out,
+                system_err: run.system_err,
+            }
+        })
+        .collect();
+    attempts.append(&mut testcase.attempts);
+    testcase.attempts = attempts;
+    testcase
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+    extern crate uuid;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::env;
+    use uuid::Uuid;
+
+    fn report(suite: &str, testcases: &[(&str, bool)]) -> String {
+        let testcases: Vec<String> = testcases
+            .iter()
+            .map(|(name, passed)| {
+                let failure = if *passed {
+                    ""
+                } else {
+                    "<failure message=\"expected 90\">AssertionError</failure>"
+                };
+                format!(
+                    "<testcase classname=\"{}\" name=\"{}\" time=\"0.1\">{}</testcase>",
+                    suite, name, failure
+                )
+            })
+            .collect();
+        format!(
+            "<testsuites><testsuite name=\"{}\" tests=\"{}\" time=\"0.5\">{}</testsuite></testsuites>",
+            suite,
+            testcases.len(),
+            testcases.join("")
+        )
+    }
+
+    fn testlogs() -> PathBuf {
+        let mut testlogs = env::temp_dir();
+        testlogs.push(format!("cikit/bazel-{}/bazel-testlogs", Uuid::new_v4()));
+        let files = vec![
+            (
+                "app/cart/cart_test/shard_1_of_2/test.xml",
+                report("CartTest", &[("adds items", true)]),
+            ),
+            (
+                "app/cart/cart_test/shard_2_of_2/test.xml",
+                report("CartTest", &[("applies discounts", true)]),
+            ),
+            (
+                "app/login_test/run_1_of_2/test.xml",
+                report("LoginTest", &[("logs in", false), ("logs out", true)]),
+            ),
+            (
+                "app/login_test/run_1_of_2/test.log",
+                "login failed".to_owned(),
+            ),
+            (
+                "app/login_test/run_2_of_2/test.xml",
+                report("LoginTest", &[("logs in", true), ("logs out", true)]),
+            ),
+            (
+                "lib/util_test/test_attempts/attempt_1.xml",
+                report("UtilTest", &[("parses", false)]),
+            ),
+            (
+                "lib/util_test/test_attempts/attempt_1.log",
+                "attempt 1".to_owned(),
+            ),
+            (
+                "lib/util_test/test.xml",
+                report("UtilTest", &[("parses", false)]),
+            ),
+            ("lib/util_test/test.log", "attempt 2".to_owned()),
+        ];
+        for (path, content) in files {
+            let path = testlogs.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        testlogs
+    }
+
+    fn read(target: &Target) -> TestSuite {
+        let mut suites = read_target(target, |_| ()).unwrap();
+        assert_eq!(suites.len(), 1);
+        suites.remove(0).value
+    }
+
+    #[test]
+    fn parse_run_dirs() {
+        assert_eq!(shard_and_run("shard_2_of_3"), Some((2, 1)));
+        assert_eq!(shard_and_run("run_3_of_4"), Some((1, 3)));
+        assert_eq!(shard_and_run("shard_2_of_3_run_3_of_4"), Some((2, 3)));
+        assert_eq!(shard_and_run("cart_test"), None);
+        assert_eq!(shard_and_run("shard_x_of_3"), None);
+    }
+
+    #[test]
+    fn find_targets_by_label() {
+        let testlogs = testlogs();
+        let targets = find_targets(&testlogs).unwrap();
+        let labels: Vec<(&str, usize)> = targets
+            .iter()
+            .map(|t| (t.label.as_str(), t.runs.len()))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                ("//app/cart:cart_test", 2),
+                ("//app:login_test", 2),
+                ("//lib:util_test", 2),
+            ]
+        );
+        assert!(targets[2].runs[0].attempt == Some(1));
+        assert!(targets[0].has_report(&testlogs.join("app/cart/cart_test/shard_2_of_2/test.xml")));
+    }
+
+    #[test]
+    fn merge_shards() {
+        let targets = find_targets(&testlogs()).unwrap();
+        let suite = read(&targets[0]);
+        assert_eq!(suite.name, "//app/cart:cart_test");
+        assert_eq!(suite.testsuites.len(), 1);
+        assert_eq!(suite.testsuites[0].testcases.len(), 2);
+        assert_eq!(suite.time, Duration::milliseconds(1000));
+        assert_eq!(suite.outcome(), TestOutcome::Passed);
+    }
+
+    #[test]
+    fn merge_runs_into_flaky_tests() {
+        let targets = find_targets(&testlogs()).unwrap();
+        let suite = read(&targets[1]);
+        let outcomes: Vec<(&str, TestOutcome)> = suite.testsuites[0]
+            .testcases
+            .iter()
+            .map(|t| (t.name.as_str(), t.outcome()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("logs in", TestOutcome::Flaky),
+                ("logs out", TestOutcome::Passed)
+            ]
+        );
+        assert_eq!(suite.time, Duration::milliseconds(500));
+        assert_eq!(suite.system_out, None);
+    }
+
+    #[test]
+    fn keep_logs_of_failed_targets() {
+        let targets = find_targets(&testlogs()).unwrap();
+        let suite = read(&targets[2]);
+        let testcase = &suite.testsuites[0].testcases[0];
+        assert_eq!(testcase.outcome(), TestOutcome::Failed);
+        assert_eq!(testcase.attempts.len(), 1);
+        assert_eq!(testcase.attempts[0].kind, AttemptKind::RerunFailure);
+        assert_eq!(suite.system_out, Some("attempt 1\nattempt 2".to_owned()));
+    }
+}
@@ -0,0 +1,280 @@
+use super::{
+    non_blank, parser::float_millis, Attachment, AttemptKind, SourceLocation, SuiteWithSummary,
+    TestAttempt, TestCase, TestFailure, TestSkipped, TestSuite,
+};
+use anyhow::Result;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    io::{self, BufReader},
+    path::PathBuf,
+};
+
+/// A Common Test Report Format document.
+#[derive(Debug, Deserialize)]
+struct CtrfReport {
+    results: Results,
+}
+
+#[derive(Debug, Deserialize)]
+struct Results {
+    tool: Tool,
+    #[serde(default)]
+    tests: Vec<Test>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tool {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Test {
+    name: String,
+    status: String,
+    #[serde(default)]
+    duration: f64,
+    suite: Option<SuitePath>,
+    message: Option<String>,
+    trace: Option<String>,
+    raw_status: Option<String>,
+    file_path: Option<String>,
+    line: Option<u32>,
+    #[serde(default)]
+    retries: usize,
+    #[serde(default)]
+    flaky: bool,
+    #[serde(default)]
+    stdout: Vec<String>,
+    #[serde(default)]
+    stderr: Vec<String>,
+    #[serde(default)]
+    attachments: Vec<CtrfAttachment>,
+}
+
+/// Later versions of the specification break the suite down into its path.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SuitePath {
+    Name(String),
+    Path(Vec<String>),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CtrfAttachment {
+    name: String,
+    content_type: Option<String>,
+    path: PathBuf,
+}
+
+/// Reads a CTRF JSON report, grouping its tests into suites after the suite
+/// they belong to, or after the tool which ran them.
+pub(super) fn read_suites<R, F>(input: R, mut on_testcase: F) -> Result<Vec<SuiteWithSummary>>
+where
+    R: io::Read,
+    F: FnMut(&TestCase),
+{
+    let report: CtrfReport = serde_json::from_reader(BufReader::new(input))?;
+
+    let mut suites: Vec<TestSuite> = Vec::new();
+    let mut suite_positions: HashMap<String, usize> = HashMap::new();
+    for test in report.results.tests {
+        let name = match &test.suite {
+            Some(SuitePath::Name(name)) => name.clone(),
+            Some(SuitePath::Path(path)) => path.join(" > "),
+            None => report.results.tool.name.clone(),
+        };
+        let position = match suite_positions.get(&name) {
+            Some(position) => *position,
+            None => {
+                suite_positions.insert(name.clone(), suites.len());
+                suites.push(TestSuite::new(name.clone()));
+                suites.len() - 1
+            }
+        };
+        let suite = &mut suites[position];
+        let testcase = testcase(test, name);
+        on_testcase(&testcase);
+        suite.time = suite.time + testcase.time;
+        suite.testcases.push(testcase);
+    }
+
+    Ok(suites.into_iter().map(|s| s.with_summary()).collect())
+}
+
+fn testcase(test: Test, suite: String) -> TestCase {
+    let location = test.file_path.clone().map(|file| SourceLocation {
+        file,
+        line: test.line,
+    });
+    let mut testcase = TestCase {
+        time: float_millis(test.duration),
+        system_out: non_blank(test.stdout.join("\n")),
+        system_err: non_blank(test.stderr.join("\n")),
+        attachments: test
+            .attachments
+            .into_iter()
+            .map(|a| Attachment {
+                name: a.name,
+                content_type: a.content_type,
+                path: a.path,
+            })
+            .collect(),
+        location,
+        ..TestCase::new(test.name, test.file_path.unwrap_or(suite))
+    };
+
+    // only the number of retries is reported, not why they failed
+    let (retries, kind) = if test.status == "failed" {
+        (test.retries, AttemptKind::RerunFailure)
+    } else if test.flaky {
+        (test.retries.max(1), AttemptKind::FlakyFailure)
+    } else {
+        (0, AttemptKind::RerunFailure)
+    };
+    testcase.attempts = (0..retries)
+        .map(|_| TestAttempt {
+            kind,
+            failure: TestFailure {
+                message: None,
+                classname: String::new(),
+                stack_trace: String::new(),
+            },
+            system_out: None,
+            system_err: None,
+        })
+        .collect();
+
+    match test.status.as_str() {
+        "failed" => {
+            testcase.failure = Some(TestFailure {
+                message: test.message.and_then(non_blank),
+                classname: String::new(),
+                stack_trace: test.trace.unwrap_or_default().trim().to_owned(),
+            })
+        }
+        "skipped" | "pending" => {
+            testcase.skipped = Some(TestSkipped {
+                message: test.message.and_then(non_blank),
+                details: None,
+            })
+        }
+        // a status the tool could not map onto any of the others
+        "other" => {
+            testcase.skipped = Some(TestSkipped {
+                message: test.raw_status.or(test.message).and_then(non_blank),
+                details: None,
+            })
+        }
+        _ => (),
+    }
+    testcase
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use crate::junit::{HasOutcome, TestOutcome};
+    use chrono::Duration;
+    use pretty_assertions::assert_eq;
+
+    const CTRF_JSON: &str = r##"{
+  "reportFormat": "CTRF",
+  "specVersion": "0.0.0",
+  "results": {
+    "tool": { "name": "mocha" },
+    "summary": { "tests": 5, "passed": 2, "failed": 1, "pending": 1, "skipped": 0, "other": 1, "start": 1600000000000, "stop": 1600000000500 },
+    "tests": [
+      { "name": "adds items", "status": "passed", "duration": 12, "suite": "cart", "filePath": "test/cart.spec.js", "stdout": ["adding", "2 items"] },
+      { "name": "applies discounts", "status": "failed", "duration": 30, "suite": "cart", "message": "expected 100 to equal 90", "trace": "AssertionError: expected 100 to equal 90\n    at test/cart.spec.js:12:7\n", "retries": 2 },
+      { "name": "ships abroad", "status": "pending", "duration": 0, "suite": ["cart", "shipping"], "message": "shipping is disabled" },
+      { "name": "checks out", "status": "passed", "duration": 40, "flaky": true, "retries": 1,
+        "attachments": [{ "name": "screenshot", "contentType": "image/png", "path": "screenshots/checkout.png" }] },
+      { "name": "logs in", "status": "other", "rawStatus": "cancelled", "duration": 0 }
+    ]
+  }
+}"##;
+
+    fn read(json: &str) -> Vec<TestSuite> {
+        read_suites(json.as_bytes(), |_| ())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.value)
+            .collect()
+    }
+
+    #[test]
+    fn group_tests_by_suite() {
+        let suites = read(CTRF_JSON);
+        let names: Vec<&str> = suites.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["cart", "cart > shipping", "mocha"]);
+        assert_eq!(suites[0].time, Duration::milliseconds(42));
+        assert_eq!(suites[0].testcases[0].classname, "test/cart.spec.js");
+        assert_eq!(suites[0].testcases[1].classname, "cart");
+        assert_eq!(
+            suites[0].testcases[0].system_out,
+            Some("adding\n2 items".to_owned())
+        );
+    }
+
+    #[test]
+    fn parse_statuses() {
+        let suites = read(CTRF_JSON);
+        let outcomes: Vec<(&str, TestOutcome)> = suites
+            .iter()
+            .flat_map(|s| s.testcases.iter())
+            .map(|t| (t.name.as_str(), t.outcome()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("adds items", TestOutcome::Passed),
+                ("applies discounts", TestOutcome::Failed),
+                ("ships abroad", TestOutcome::Skipped),
+                ("checks out", TestOutcome::Flaky),
+                ("logs in", TestOutcome::Skipped),
+            ]
+        );
+
+        let failed = &suites[0].testcases[1];
+        let failure = failed.failure.as_ref().unwrap();
+        assert_eq!(failure.message, Some("expected 100 to equal 90".to_owned()));
+        assert!(failure.stack_trace.ends_with("at test/cart.spec.js:12:7"));
+        assert_eq!(failed.attempts.len(), 2);
+        assert_eq!(failed.attempts[0].kind, AttemptKind::RerunFailure);
+
+        assert_eq!(
+            suites[1].testcases[0]
+                .skipped
+                .as_ref()
+                .and_then(TestSkipped::reason),
+            Some("shipping is disabled")
+        );
+        assert_eq!(
+            suites[2].testcases[1]
+                .skipped
+                .as_ref()
+                .and_then(TestSkipped::reason),
+            Some("cancelled")
+        );
+        assert_eq!(
+            suites[2].testcases[0].attachments[0].path,
+            PathBuf::from("screenshots/checkout.png")
+        );
+    }
+
+    #[test]
+    fn not_a_ctrf_report() {
+        assert!(read_suites("{}".as_bytes(), |_| ()).is_err());
+        assert!(read_suites("{\"results\": {\"tests\": []}}".as_bytes(), |_| ()).is_err());
+    }
+
+    #[test]
+    fn no_tests() {
+        assert!(read("{\"results\": {\"tool\": {\"name\": \"mocha\"}, \"tests\": []}}").is_empty());
+    }
+}
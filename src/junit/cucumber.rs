@@ -0,0 +1,333 @@
+use super::{
+    non_blank, SuiteMetadata, SuiteWithSummary, TestCase, TestFailure, TestSkipped, TestSuite,
+};
+use anyhow::Result;
+use chrono::Duration;
+use serde::Deserialize;
+use std::io::{self, BufReader};
+
+#[derive(Debug, Deserialize)]
+struct Feature {
+    #[serde(default)]
+    uri: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    elements: Vec<Element>,
+}
+
+/// A scenario, or a background whose steps run before each of the following
+/// scenarios.
+#[derive(Debug, Deserialize)]
+struct Element {
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    before: Vec<Step>,
+    #[serde(default)]
+    steps: Vec<Step>,
+    #[serde(default)]
+    after: Vec<Step>,
+}
+
+/// Hooks are reported as steps without a name.
+#[derive(Debug, Clone, Deserialize)]
+struct Step {
+    #[serde(default)]
+    keyword: String,
+    #[serde(default)]
+    name: String,
+    result: Option<StepResult>,
+    #[serde(default)]
+    output: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct StepResult {
+    status: String,
+    /// In nanoseconds
+    duration: Option<u64>,
+    error_message: Option<String>,
+}
+
+impl Step {
+    fn status(&self) -> &str {
+        self.result
+            .as_ref()
+            .map(|r| r.status.as_str())
+            .unwrap_or("undefined")
+    }
+
+    /// The step as written in the feature file, e.g. `Given a cart`.
+    fn text(&self) -> String {
+        let text = format!("{}{}", self.keyword, self.name);
+        match text.trim() {
+            "" => "hook".to_owned(),
+            text => text.to_owned(),
+        }
+    }
+}
+
+/// Reads a Cucumber JSON report, mapping each feature onto a suite and each
+/// scenario onto a test case. A scenario fails on its first failed step, and
+/// is skipped when one of its steps is undefined or pending.
+pub(super) fn read_suites<R, F>(input: R, mut on_testcase: F) -> Result<Vec<SuiteWithSummary>>
+where
+    R: io::Read,
+    F: FnMut(&TestCase),
+{
+    let features: Vec<Feature> = serde_json::from_reader(BufReader::new(input))?;
+    Ok(features
+        .into_iter()
+        .map(|feature| feature_suite(feature, &mut on_testcase).with_summary())
+        .collect())
+}
+
+fn feature_suite<F: FnMut(&TestCase)>(feature: Feature, on_testcase: &mut F) -> TestSuite {
+    let uri = feature.uri;
+    let name = non_blank(feature.name).unwrap_or_else(|| uri.clone());
+    let mut suite = TestSuite {
+        metadata: SuiteMetadata {
+            file: non_blank(uri),
+            ..SuiteMetadata::default()
+        },
+        ..TestSuite::new(name.clone())
+    };
+
+    let mut background: Vec<Step> = Vec::new();
+    for element in feature.elements {
+        if element.kind == "background" {
+            background = element.steps;
+            continue;
+        }
+        let steps: Vec<&Step> = element
+            .before
+            .iter()
+            .chain(background.iter())
+            .chain(element.steps.iter())
+            .chain(element.after.iter())
+            .collect();
+        let testcase = scenario_testcase(element.name, name.clone(), &steps);
+        on_testcase(&testcase);
+        suite.time = suite.time + testcase.time;
+        suite.testcases.push(testcase);
+        background.clear();
+    }
+    suite
+}
+
+fn scenario_testcase(name: String, classname: String, steps: &[&Step]) -> TestCase {
+    let nanos: u64 = steps
+        .iter()
+        .filter_map(|step| step.result.as_ref().and_then(|r| r.duration))
+        .sum();
+    let output: Vec<&str> = steps
+        .iter()
+        .flat_map(|step| step.output.iter().map(String::as_str))
+        .collect();
+
+    let mut testcase = TestCase {
+        time: Duration::nanoseconds(nanos as i64),
+        system_out: non_blank(output.join("\n")),
+        ..TestCase::new(name, classname)
+    };
+
+    if let Some(step) = steps
+        .iter()
+        .find(|step| step.status() == "failed" || step.status() == "ambiguous")
+    {
+        testcase.failure = Some(failure(step));
+    } else if let Some(step) = steps
+        .iter()
+        .find(|step| step.status() == "undefined" || step.status() == "pending")
+    {
+        testcase.skipped = Some(TestSkipped {
+            message: Some(format!("{} step: {}", step.status(), step.text())),
+            details: None,
+        });
+    } else if !steps.is_empty() && steps.iter().all(|step| step.status() == "skipped") {
+        testcase.skipped = Some(TestSkipped::default());
+    }
+    testcase
+}
+
+/// Reports the failed step, followed by the first line of its error.
+fn failure(step: &Step) -> TestFailure {
+    let error = step
+        .result
+        .as_ref()
+        .and_then(|r| r.error_message.clone())
+        .unwrap_or_default();
+    let message = match error.trim().lines().next() {
+        Some(first_line) => format!("{}: {}", step.text(), first_line.trim()),
+        None => format!("{} {}", step.text(), step.status()),
+    };
+    TestFailure {
+        message: Some(message),
+        classname: String::new(),
+        stack_trace: error.trim().to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use crate::junit::{HasOutcome, TestOutcome};
+    use pretty_assertions::assert_eq;
+
+    const CUCUMBER_JSON: &str = r##"[
+  {
+    "uri": "features/checkout.feature",
+    "id": "checkout",
+    "keyword": "Feature",
+    "name": "Checkout",
+    "elements": [
+      {
+        "keyword": "Background",
+        "name": "",
+        "type": "background",
+        "steps": [
+          {"keyword": "Given ", "name": "a cart with 2 books", "result": {"status": "passed", "duration": 1000000}}
+        ]
+      },
+      {
+        "id": "checkout;discounts",
+        "keyword": "Scenario",
+        "name": "Discounts are applied",
+        "type": "scenario",
+        "before": [{"match": {}, "result": {"status": "passed", "duration": 500000}}],
+        "steps": [
+          {"keyword": "When ", "name": "I apply the code SAVE10", "result": {"status": "passed", "duration": 2000000}, "output": ["applied SAVE10"]},
+          {"keyword": "Then ", "name": "the total is 90", "result": {"status": "failed", "duration": 3000000, "error_message": "expected: 90\n     got: 100 (RSpec::Expectations::ExpectationNotMetError)\n./features/step_definitions/cart.rb:12"}},
+          {"keyword": "And ", "name": "I get a receipt", "result": {"status": "skipped"}}
+        ]
+      },
+      {
+        "keyword": "Background",
+        "name": "",
+        "type": "background",
+        "steps": [
+          {"keyword": "Given ", "name": "a cart with 2 books", "result": {"status": "passed", "duration": 1000000}}
+        ]
+      },
+      {
+        "keyword": "Scenario",
+        "name": "Gift cards",
+        "type": "scenario",
+        "steps": [
+          {"keyword": "When ", "name": "I pay with a gift card", "result": {"status": "undefined"}},
+          {"keyword": "Then ", "name": "the card is charged", "result": {"status": "skipped"}}
+        ]
+      },
+      {
+        "keyword": "Scenario",
+        "name": "Free shipping",
+        "type": "scenario",
+        "steps": [
+          {"keyword": "When ", "name": "I order for 50 or more", "result": {"status": "passed", "duration": 1000000}},
+          {"keyword": "Then ", "name": "shipping is free", "result": {"status": "pending", "duration": 0}}
+        ]
+      }
+    ]
+  },
+  {
+    "uri": "features/login.feature",
+    "name": "",
+    "elements": [
+      {
+        "keyword": "Scenario",
+        "name": "Logging in",
+        "type": "scenario",
+        "steps": [
+          {"keyword": "Given ", "name": "a user", "result": {"status": "passed", "duration": 1000000}}
+        ]
+      }
+    ]
+  }
+]"##;
+
+    fn read(json: &str) -> Vec<TestSuite> {
+        read_suites(json.as_bytes(), |_| ())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.value)
+            .collect()
+    }
+
+    #[test]
+    fn parse_scenarios() {
+        let suites = read(CUCUMBER_JSON);
+        assert_eq!(suites[0].name, "Checkout");
+        assert_eq!(
+            suites[0].metadata.file,
+            Some("features/checkout.feature".to_owned())
+        );
+        assert_eq!(suites[1].name, "features/login.feature");
+
+        let outcomes: Vec<(&str, TestOutcome)> = suites[0]
+            .testcases
+            .iter()
+            .map(|t| (t.name.as_str(), t.outcome()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("Discounts are applied", TestOutcome::Failed),
+                ("Gift cards", TestOutcome::Skipped),
+                ("Free shipping", TestOutcome::Skipped),
+            ]
+        );
+        // including the background and the hooks
+        assert_eq!(suites[0].testcases[0].time, Duration::microseconds(6500));
+        assert_eq!(suites[0].time, Duration::microseconds(8500));
+        assert_eq!(suites[0].testcases[0].classname, "Checkout");
+        assert_eq!(
+            suites[0].testcases[0].system_out,
+            Some("applied SAVE10".to_owned())
+        );
+    }
+
+    #[test]
+    fn parse_failed_step() {
+        let suites = read(CUCUMBER_JSON);
+        let failure = suites[0].testcases[0].failure.as_ref().unwrap();
+        assert_eq!(
+            failure.message,
+            Some("Then the total is 90: expected: 90".to_owned())
+        );
+        assert!(failure
+            .stack_trace
+            .ends_with("./features/step_definitions/cart.rb:12"));
+    }
+
+    #[test]
+    fn undefined_and_pending_steps() {
+        let suites = read(CUCUMBER_JSON);
+        let reasons: Vec<Option<&str>> = suites[0].testcases[1..]
+            .iter()
+            .map(|t| t.skipped.as_ref().and_then(TestSkipped::reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                Some("undefined step: When I pay with a gift card"),
+                Some("pending step: Then shipping is free")
+            ]
+        );
+    }
+
+    #[test]
+    fn not_a_cucumber_report() {
+        assert!(read_suites("{}".as_bytes(), |_| ()).is_err());
+        assert!(read_suites("[\"checkout.feature\"]".as_bytes(), |_| ()).is_err());
+    }
+
+    #[test]
+    fn no_features() {
+        assert!(read("[]").is_empty());
+    }
+}
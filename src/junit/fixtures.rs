@@ -0,0 +1,55 @@
+//! Test cases and reports shared by the tests of the report writers.
+
+use super::{FullReport, Summary, TestCase, TestFailure, TestSuite, UnreadableReport};
+use chrono::Duration;
+use std::path::PathBuf;
+
+/// A passed test case of `com.example.CartTest`, taking 10ms.
+pub(crate) fn testcase(name: &str) -> TestCase {
+    TestCase {
+        time: Duration::milliseconds(10),
+        ..TestCase::new(name.to_owned(), "com.example.CartTest".to_owned())
+    }
+}
+
+pub(crate) fn failure(message: &str) -> TestFailure {
+    TestFailure {
+        message: Some(message.to_owned()),
+        classname: "AssertionError".to_owned(),
+        stack_trace: format!("AssertionError: {}", message),
+    }
+}
+
+/// A suite taking as long as its test cases.
+pub(crate) fn suite(name: &str, testcases: Vec<TestCase>) -> TestSuite {
+    TestSuite {
+        time: testcases
+            .iter()
+            .fold(Duration::zero(), |acc, testcase| acc + testcase.time),
+        testcases,
+        ..TestSuite::new(name.to_owned())
+    }
+}
+
+pub(crate) fn unreadable(path: PathBuf) -> UnreadableReport {
+    UnreadableReport {
+        path,
+        error: "unexpected end of file".to_owned(),
+    }
+}
+
+/// Summarises the suites the way the reader does, counting each unreadable
+/// report as an error.
+pub(crate) fn full_report(
+    suites: Vec<TestSuite>,
+    unreadable_reports: Vec<UnreadableReport>,
+) -> FullReport {
+    let suites: Vec<_> = suites.into_iter().map(TestSuite::with_summary).collect();
+    let mut summary = Summary::zero();
+    let mut acc = &mut summary;
+    for suite in &suites {
+        acc += &suite.summary;
+    }
+    summary.errors += unreadable_reports.len();
+    FullReport::new(suites, unreadable_reports, summary, None)
+}
@@ -0,0 +1,315 @@
+use super::{parser::root_element, ReportFormat, SuiteWithSummary, TestCase};
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::{fs::File, io::Read, path::Path};
+
+/// How much of a report file is read to tell its format.
+const HEAD_BYTES: u64 = 64 * 1024;
+
+type ReadSuites = fn(&Path, &mut dyn FnMut(&TestCase)) -> Result<Vec<SuiteWithSummary>>;
+
+/// One of the supported report formats, along with how to recognise and read
+/// its files.
+pub(super) struct Reader {
+    pub format: ReportFormat,
+    /// Names the format in logs and errors, e.g. `JUnit XML`
+    pub name: &'static str,
+    sniff: fn(&Head) -> bool,
+    pub read: ReadSuites,
+}
+
+/// Formats are detected in this order, the first one recognising a file
+/// being used to read it.
+static READERS: [Reader; 13] = [
+    Reader {
+        format: ReportFormat::Junit,
+        name: "JUnit XML",
+        sniff: |head| {
+            matches!(
+                head.xml_root().as_deref(),
+                Some("testsuites") | Some("testsuite")
+            )
+        },
+        read: |path, on_testcase| super::read_suites(open(path)?, on_testcase),
+    },
+    Reader {
+        format: ReportFormat::Trx,
+        name: "TRX",
+        sniff: |head| head.xml_root().as_deref() == Some("TestRun"),
+        read: |path, on_testcase| super::trx::read_suites(open(path)?, on_testcase),
+    },
+    Reader {
+        format: ReportFormat::Nunit,
+        name: "NUnit XML",
+        sniff: |head| head.xml_root().as_deref() == Some("test-run"),
+        read: |path, on_testcase| super::nunit::read_suites(open(path)?, on_testcase),
+    },
+    Reader {
+        format: ReportFormat::Xunit,
+        name: "xUnit.net XML",
+        sniff: |head| {
+            matches!(
+                head.xml_root().as_deref(),
+                Some("assemblies") | Some("assembly")
+            )
+        },
+        read: |path, on_testcase| super::xunit::read_suites(open(path)?, on_testcase),
+    },
+    Reader {
+        format: ReportFormat::Libtest,
+        name: "libtest JSON",
+        sniff: |head| {
+            head.first_json_line()
+                .map(|event| event.get("type").is_some() && event.get("event").is_some())
+                .unwrap_or(false)
+        },
+        read: |path, on_testcase| {
+            super::libtest::read_suites(open(path)?, &super::fs::suite_name(path), on_testcase)
+        },
+    },
+    Reader {
+        format: ReportFormat::Go,
+        name: "go test JSON",
+        sniff: |head| {
+            head.first_json_line()
+                .map(|event| event.get("Action").is_some())
+                .unwrap_or(false)
+        },
+        read: |path, on_testcase| super::gotest::read_suites(open(path)?, on_testcase),
+    },
+    Reader {
+        format: ReportFormat::Jest,
+        name: "Jest JSON",
+        sniff: |head| head.is_json_object_with(&["\"testResults\"", "\"numTotalTests\""]),
+        read: |path, on_testcase| super::jest::read_suites(open(path)?, on_testcase),
+    },
+    Reader {
+        format: ReportFormat::Playwright,
+        name: "Playwright JSON",
+        sniff: |head| head.is_json_object_with(&["\"specs\""]),
+        read: |path, on_testcase| super::playwright::read_suites(open(path)?, on_testcase),
+    },
+    Reader {
+        format: ReportFormat::Cucumber,
+        name: "Cucumber JSON",
+        sniff: |head| head.text.starts_with('[') && head.text.contains("\"elements\""),
+        read: |path, on_testcase| super::cucumber::read_suites(open(path)?, on_testcase),
+    },
+    Reader {
+        format: ReportFormat::Ctrf,
+        name: "CTRF JSON",
+        sniff: |head| head.is_json_object_with(&["\"reportFormat\"", "\"tool\""]),
+        read: |path, on_testcase| super::ctrf::read_suites(open(path)?, on_testcase),
+    },
+    Reader {
+        format: ReportFormat::Tap,
+        name: "TAP",
+        sniff: |head| {
+            head.first_line()
+                .map(|line| {
+                    line.starts_with("TAP version")
+                        || line.starts_with("1..")
+                        || line == "ok"
+                        || line.starts_with("ok ")
+                        || line.starts_with("not ok")
+                })
+                .unwrap_or(false)
+        },
+        read: |path, on_testcase| {
+            super::tap::read_suites(open(path)?, &super::fs::suite_name(path), on_testcase)
+        },
+    },
+    Reader {
+        format: ReportFormat::Subunit,
+        name: "subunit v2",
+        sniff: |head| head.bytes.len() > 1 && head.bytes[0] == 0xb3 && head.bytes[1] >> 4 == 0x2,
+        read: |path, on_testcase| super::subunit::read_suites(open(path)?, on_testcase),
+    },
+    Reader {
+        format: ReportFormat::Allure,
+        name: "Allure results",
+        // results are directories rather than files
+        sniff: |_| false,
+        read: |path, on_testcase| super::allure::read_suites(path, on_testcase),
+    },
+];
+
+/// The reader of the given format.
+pub(super) fn reader(format: ReportFormat) -> &'static Reader {
+    READERS
+        .iter()
+        .find(|reader| reader.format == format)
+        .expect("All formats have a reader")
+}
+
+/// Finds the reader of a report file, from its first few kilobytes.
+pub(super) fn detect(path: &Path) -> Result<&'static Reader> {
+    if path.is_dir() {
+        return if super::allure::is_results_dir(path) {
+            Ok(reader(ReportFormat::Allure))
+        } else {
+            Err(anyhow!("Unrecognised report directory"))
+        };
+    }
+    let mut bytes = Vec::new();
+    File::open(path)?.take(HEAD_BYTES).read_to_end(&mut bytes)?;
+    detect_head(&bytes)
+}
+
+fn open(path: &Path) -> Result<File> {
+    File::open(path).context("Couldn't open report file")
+}
+
+fn detect_head(bytes: &[u8]) -> Result<&'static Reader> {
+    let text = String::from_utf8_lossy(bytes);
+    let head = Head {
+        bytes,
+        text: text.trim_start_matches('\u{feff}').trim_start(),
+    };
+    READERS
+        .iter()
+        .find(|reader| (reader.sniff)(&head))
+        .ok_or_else(|| anyhow!("Unrecognised report format"))
+}
+
+/// The start of a report file, possibly cut short.
+struct Head<'a> {
+    bytes: &'a [u8],
+    /// Leaves out leading whitespace and byte order marks
+    text: &'a str,
+}
+
+impl<'a> Head<'a> {
+    fn xml_root(&self) -> Option<String> {
+        if self.text.starts_with('<') {
+            root_element(self.text.as_bytes()).ok()
+        } else {
+            None
+        }
+    }
+
+    /// The first event of a stream of JSON documents, one per line.
+    fn first_json_line(&self) -> Option<Value> {
+        let line = self.first_line()?;
+        if line.starts_with('{') {
+            serde_json::from_str::<Value>(line)
+                .ok()
+                .filter(Value::is_object)
+        } else {
+            None
+        }
+    }
+
+    /// Whether this is a JSON object mentioning any of the given keys.
+    fn is_json_object_with(&self, keys: &[&str]) -> bool {
+        self.text.starts_with('{') && keys.iter().any(|key| self.text.contains(key))
+    }
+
+    /// Skips blank lines and comments.
+    fn first_line(&self) -> Option<&'a str> {
+        self.text
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn detected(head: &str) -> Option<ReportFormat> {
+        detect_head(head.as_bytes())
+            .ok()
+            .map(|reader| reader.format)
+    }
+
+    #[test]
+    fn detect_xml_formats() {
+        let formats: Vec<Option<ReportFormat>> = vec![
+            "<?xml version=\"1.0\"?>\n<testsuites><testsuite name=\"a\">",
+            "<testsuite name=\"a\" tests=\"1\">",
+            "\u{feff}<?xml version=\"1.0\" encoding=\"utf-8\"?><TestRun id=\"1\" xmlns=\"http://microsoft.com/schemas/VisualStudio/TeamTest/2010\">",
+            "<!-- nunit3-console -->\n<test-run id=\"0\" result=\"Passed\">",
+            "<assemblies timestamp=\"11/02/2020 10:12:31\">",
+            "<html><body>",
+        ]
+        .into_iter()
+        .map(detected)
+        .collect();
+        assert_eq!(
+            formats,
+            vec![
+                Some(ReportFormat::Junit),
+                Some(ReportFormat::Junit),
+                Some(ReportFormat::Trx),
+                Some(ReportFormat::Nunit),
+                Some(ReportFormat::Xunit),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn detect_json_formats() {
+        let formats: Vec<Option<ReportFormat>> = vec![
+            "{ \"type\": \"suite\", \"event\": \"started\", \"test_count\": 2 }\n{ \"type\": \"test\"",
+            "{\"Time\":\"2020-11-02T10:12:31Z\",\"Action\":\"run\",\"Package\":\"example.com/shop\"}\n{\"Time\"",
+            "{\"numFailedTestSuites\":1,\"numTotalTests\":5,\"testResults\":[{\"name\"",
+            "{\n  \"config\": {\n    \"projects\": []\n  },\n  \"suites\": [\n    {\n      \"title\": \"login.spec.ts\",\n      \"specs\": [",
+            "[\n  {\n    \"uri\": \"features/checkout.feature\",\n    \"elements\": [",
+            "{\"reportFormat\": \"CTRF\", \"specVersion\": \"0.0.0\", \"results\": {\"tool\": {\"name\": \"mocha\"}",
+            "{\"name\": \"package\", \"version\": \"1.0.0\"}",
+            "[]",
+        ]
+        .into_iter()
+        .map(detected)
+        .collect();
+        assert_eq!(
+            formats,
+            vec![
+                Some(ReportFormat::Libtest),
+                Some(ReportFormat::Go),
+                Some(ReportFormat::Jest),
+                Some(ReportFormat::Playwright),
+                Some(ReportFormat::Cucumber),
+                Some(ReportFormat::Ctrf),
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn detect_tap() {
+        assert_eq!(
+            detected("TAP version 13\n1..2\nok 1 - adds"),
+            Some(ReportFormat::Tap)
+        );
+        assert_eq!(
+            detected("# running tests\nok 1 - adds\n1..1"),
+            Some(ReportFormat::Tap)
+        );
+        assert_eq!(detected("okay then"), None);
+        assert_eq!(detected("some build output"), None);
+    }
+
+    #[test]
+    fn detect_subunit() {
+        let enumeration = b"\xb3\x29\x01\x0c\x03foo\x08\x55\x5f\x1b";
+        assert_eq!(
+            detect_head(enumeration).ok().map(|reader| reader.format),
+            Some(ReportFormat::Subunit)
+        );
+    }
+
+    #[test]
+    fn every_format_has_a_reader() {
+        for reader in &READERS {
+            assert_eq!(super::reader(reader.format).name, reader.name);
+        }
+    }
+}
@@ -1,63 +1,172 @@
-use crate::console::ConsoleDisplay;
-use anyhow::Result;
+use crate::{config::ReportInput, console::ConsoleDisplay};
+use anyhow::{Context, Result};
 use glob::{glob_with, MatchOptions};
-use log::{debug, info};
+use log::{debug, info, warn};
 use std::{
-    ffi::OsStr,
-    fs, io,
+    collections::HashSet,
+    io,
     path::{Path, PathBuf},
     sync::mpsc::{channel, Sender},
 };
 use threadpool::ThreadPool;
 
-use super::{Summary, SummaryWith, TestSuite};
+use super::{
+    allure, bazel, formats, ReportFormat, ReportLayout, Summary, SummaryWith, TestCase, TestSuite,
+    UnreadableReport,
+};
+
+const SUMMARY_CURSOR_UP: &str = "\x1b[6A";
+const SUMMARY_CURSOR_DOWN: &str = "\x1b[6B";
+const PROGRESS_UPDATE_EVERY: usize = 100;
+
+/// Sent by the parser workers: test cases are accounted for as soon as they
+/// are parsed, while the suites they belong to are sent once a file is done.
+/// Files which cannot be parsed are reported back rather than aborting the run.
+enum ParseEvent {
+    TestCase(Summary),
+    Suites(Vec<SummaryWith<TestSuite>>),
+    Unreadable(UnreadableReport),
+}
+
+/// What each parser worker reads.
+#[derive(Debug, Clone)]
+enum Report {
+    File(PathBuf, ReportFormat),
+    /// All the report files of a Bazel test target
+    BazelTarget(bazel::Target),
+}
+
+impl Report {
+    fn path(&self) -> &Path {
+        match self {
+            Report::File(path, _) => path,
+            Report::BazelTarget(target) => &target.dir,
+        }
+    }
 
-const SUMMARY_CURSOR_UP: &str = "\x1b[5A";
-const SUMMARY_CURSOR_DOWN: &str = "\x1b[5B";
+    fn parse<F: FnMut(&TestCase)>(&self, on_testcase: F) -> Result<Vec<SummaryWith<TestSuite>>> {
+        match self {
+            Report::File(path, format) => parse_report(path, *format, on_testcase),
+            Report::BazelTarget(target) => {
+                info!("Reading {} as a Bazel test target", target.label);
+                bazel::read_target(target, on_testcase)
+                    .with_context(|| format!("Couldn't read Bazel target {}", target.label))
+            }
+        }
+    }
+}
 
 struct ReportVisitor {
-    report_files: Vec<PathBuf>,
+    reports: Vec<Report>,
     position: usize,
 }
 
 impl ReportVisitor {
-    pub fn from_basedir<P: AsRef<Path>>(base_dir: P, report_dir_pattern: &str) -> Result<Self> {
-        let mut report_files: Vec<PathBuf> = Vec::new();
-        let prefixed_dir_pattern =
-            vec![base_dir.as_ref().to_str().unwrap(), report_dir_pattern].join("/");
-
-        let paths = glob_with(
-            &prefixed_dir_pattern,
-            MatchOptions {
-                case_sensitive: true,
-                require_literal_separator: true,
-                require_literal_leading_dot: true,
-            },
-        )?;
-
-        for path in paths {
-            if let Ok(path) = path {
-                if path.is_file() && path.extension() == Some(OsStr::new("xml")) {
-                    report_files.push(path);
+    /// Files matching more than one input are only read once, using the
+    /// format of the first input they match. Those reporting a Bazel
+    /// target are only read along with the rest of the target.
+    pub fn from_basedir<P: AsRef<Path>>(base_dir: P, inputs: &[ReportInput]) -> Result<Self> {
+        let mut reports: Vec<Report> = Vec::new();
+        let mut found: HashSet<PathBuf> = HashSet::new();
+
+        for input in inputs {
+            let prefixed_dir_pattern =
+                [base_dir.as_ref().to_str().unwrap(), &input.pattern].join("/");
+
+            let paths = glob_with(
+                &prefixed_dir_pattern,
+                MatchOptions {
+                    case_sensitive: true,
+                    require_literal_separator: true,
+                    require_literal_leading_dot: true,
+                },
+            )?;
+
+            for path in paths.flatten() {
+                match input.layout {
+                    ReportLayout::Files => {
+                        if !is_report(&path, input.format) || found.contains(&path) {
+                            continue;
+                        }
+                        if let Some(format) = report_format(&path, input.format) {
+                            found.insert(path.clone());
+                            reports.push(Report::File(path, format));
+                        }
+                    }
+                    ReportLayout::Bazel if path.is_dir() => {
+                        for target in bazel::find_targets(&path)? {
+                            if found.insert(target.dir.clone()) {
+                                reports.push(Report::BazelTarget(target));
+                            }
+                        }
+                    }
+                    ReportLayout::Bazel => (),
                 }
             }
         }
 
-        info!("{} report files found", report_files.len());
+        let targets: Vec<bazel::Target> = reports
+            .iter()
+            .filter_map(|r| match r {
+                Report::BazelTarget(target) => Some(target.clone()),
+                Report::File(..) => None,
+            })
+            .collect();
+        reports.retain(|r| match r {
+            Report::File(path, _) => !targets.iter().any(|t| t.has_report(path)),
+            Report::BazelTarget(_) => true,
+        });
+
+        info!("{} report files found", reports.len());
 
         Ok(ReportVisitor {
-            report_files,
+            reports,
             position: 0,
         })
     }
 }
+
+/// Directories are only read as Allure results, which are made up of many
+/// files. Inputs of an unspecified format leave out the files Allure writes
+/// alongside its results, which are only read along with the directory.
+fn is_report(path: &Path, format: Option<ReportFormat>) -> bool {
+    if path.is_dir() {
+        match format {
+            None => allure::is_results_dir(path),
+            Some(format) => format == ReportFormat::Allure,
+        }
+    } else {
+        match format {
+            None => path.is_file() && !allure::is_results_file(path),
+            Some(format) => path.is_file() && format != ReportFormat::Allure,
+        }
+    }
+}
+
+/// The format of inputs which do not name one is detected from the content
+/// of each file. Files in none of the supported formats are skipped rather
+/// than reported as unreadable, and can still be read by a later input
+/// naming their format.
+fn report_format(path: &Path, format: Option<ReportFormat>) -> Option<ReportFormat> {
+    match format {
+        Some(format) => Some(format),
+        None => match formats::detect(path) {
+            Ok(reader) => Some(reader.format),
+            Err(e) => {
+                debug!("Skipping {}: {:#}", path.display(), e);
+                None
+            }
+        },
+    }
+}
+
 impl Iterator for ReportVisitor {
-    type Item = PathBuf;
+    type Item = Report;
     fn next(&mut self) -> Option<Self::Item> {
-        if self.position >= self.report_files.len() {
+        if self.position >= self.reports.len() {
             None
         } else {
-            let item = self.report_files[self.position].to_owned();
+            let item = self.reports[self.position].to_owned();
             self.position += 1;
             Some(item)
         }
@@ -75,11 +184,11 @@ pub(super) struct TestSuiteReader<'s> {
 impl<'s> TestSuiteReader<'s> {
     pub fn from_basedir<P: AsRef<Path>>(
         base_dir: P,
-        report_dir_pattern: &str,
+        inputs: &[ReportInput],
         summary: &'s mut Summary,
         display_progress: bool,
     ) -> Result<Self> {
-        let visitor = ReportVisitor::from_basedir(base_dir, report_dir_pattern)?;
+        let visitor = ReportVisitor::from_basedir(base_dir, inputs)?;
         //parameterise thread pool size
         let parser_pool = ThreadPool::new(5);
         let sink = Box::new(io::stdout());
@@ -93,29 +202,37 @@ impl<'s> TestSuiteReader<'s> {
         })
     }
 
-    fn par_parse_suites(&mut self, suite_tx: Sender<Vec<SummaryWith<TestSuite>>>) {
-        for path in &mut self.visitor {
-            let suite_tx = suite_tx.clone();
+    fn par_parse_suites(&mut self, events_tx: Sender<ParseEvent>) {
+        for report in &mut self.visitor {
+            let events_tx = events_tx.clone();
             self.parser_pool.execute(move || {
-                let display_path = path.display();
-                debug!("parsing Junit suite: {}", display_path);
-                let file = fs::File::open(display_path.to_string())
-                    .expect(&format!("Couldn't open report file: {}", display_path));
-                let suites = super::read_suites(file).expect(&format!(
-                    "Couldn't parse junit TestSuite from XML report {}",
-                    display_path
-                ));
-                suite_tx.send(suites).unwrap();
+                let path = report.path().to_owned();
+                debug!("parsing report: {}", path.display());
+                let testcase_tx = events_tx.clone();
+                let parsed = report.parse(|testcase| {
+                    testcase_tx
+                        .send(ParseEvent::TestCase(testcase.summary()))
+                        .unwrap();
+                });
+                let event = match parsed {
+                    Ok(suites) => ParseEvent::Suites(suites),
+                    Err(e) => {
+                        warn!("Skipping unreadable report {}: {:#}", path.display(), e);
+                        ParseEvent::Unreadable(UnreadableReport {
+                            path,
+                            error: format!("{:#}", e),
+                        })
+                    }
+                };
+                events_tx.send(event).unwrap();
             })
         }
     }
 
-    fn progress_update(&mut self) {
+    fn progress_update(&mut self, progress: &Summary) {
         if self.display_progress {
-            self.summary.display(&mut self.sink, 0).unwrap();
+            progress.display(&mut self.sink, 0).unwrap();
             write!(&mut self.sink, "\r{}", SUMMARY_CURSOR_UP).unwrap();
-        } else {
-            ()
         }
     }
     fn end_progress_update(&mut self) {
@@ -124,27 +241,69 @@ impl<'s> TestSuiteReader<'s> {
         }
     }
 
-    pub fn all_suites(mut self) -> Vec<SummaryWith<TestSuite>> {
+    /// Parses all the report files found. Test cases are only accounted for
+    /// in the final summary once the file containing them has been fully
+    /// parsed, so that truncated files do not skew it.
+    pub fn all_suites(mut self) -> (Vec<SummaryWith<TestSuite>>, Vec<UnreadableReport>) {
         let mut suites: Vec<SummaryWith<TestSuite>> = Vec::new();
-        let (suite_tx, suite_rx) = channel::<Vec<SummaryWith<TestSuite>>>();
-        self.par_parse_suites(suite_tx);
-        loop {
-            if let Ok(mut parsed_suites) = suite_rx.recv() {
-                debug!(
-                    "Appending {} new suites. Total: {}",
-                    parsed_suites.len(),
-                    suites.len()
-                );
-                for summary_with_suite in &parsed_suites {
-                    self.summary += &summary_with_suite.summary;
+        let mut unreadable: Vec<UnreadableReport> = Vec::new();
+        let mut progress = Summary::zero();
+        let mut unreported_testcases: usize = 0;
+        let (events_tx, events_rx) = channel::<ParseEvent>();
+        self.par_parse_suites(events_tx);
+        while let Ok(event) = events_rx.recv() {
+            match event {
+                ParseEvent::TestCase(testcase_summary) => {
+                    let mut acc = &mut progress;
+                    acc += &testcase_summary;
+                    unreported_testcases += 1;
+                    if unreported_testcases == PROGRESS_UPDATE_EVERY {
+                        unreported_testcases = 0;
+                        self.progress_update(&progress);
+                    }
+                }
+                ParseEvent::Suites(mut parsed_suites) => {
+                    debug!(
+                        "Appending {} new suites. Total: {}",
+                        parsed_suites.len(),
+                        suites.len()
+                    );
+                    for summary_with_suite in &parsed_suites {
+                        self.summary += &summary_with_suite.summary;
+                        // test case counts have already been accumulated as they were parsed
+                        progress.time = progress.time + summary_with_suite.summary.time;
+                    }
+                    self.progress_update(&progress);
+                    suites.append(&mut parsed_suites);
+                }
+                ParseEvent::Unreadable(report) => {
+                    self.summary.errors += 1;
+                    progress.errors += 1;
+                    self.progress_update(&progress);
+                    unreadable.push(report);
                 }
-                self.progress_update();
-                suites.append(&mut parsed_suites);
-            } else {
-                break;
             }
         }
         self.end_progress_update();
-        suites
+        (suites, unreadable)
     }
 }
+
+/// Reads a report file in the format it was found in.
+fn parse_report<F: FnMut(&TestCase)>(
+    path: &Path,
+    format: ReportFormat,
+    mut on_testcase: F,
+) -> Result<Vec<SummaryWith<TestSuite>>> {
+    let reader = formats::reader(format);
+    info!("Reading {} as a {} report", path.display(), reader.name);
+    (reader.read)(path, &mut on_testcase)
+        .with_context(|| format!("Couldn't parse {} report", reader.name))
+}
+
+/// Formats which do not name their suites are named after the report file.
+pub(super) fn suite_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
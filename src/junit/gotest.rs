@@ -0,0 +1,360 @@
+use super::{
+    non_blank, parser::float_seconds, SuiteWithSummary, TestCase, TestFailure, TestSkipped,
+    TestSuite,
+};
+use anyhow::{anyhow, bail, Result};
+use chrono::Duration;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader},
+};
+
+/// An event emitted by `go test -json` (i.e. `go tool test2json`).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Event {
+    action: String,
+    package: Option<String>,
+    test: Option<String>,
+    elapsed: Option<f64>,
+    output: Option<String>,
+}
+
+/// Reads a `go test -json` event stream, mapping each package onto a suite.
+/// Subtests are reported as test cases named after their full path (e.g.
+/// `TestParse/empty_input`), while their parent tests are only kept when they
+/// failed on their own.
+pub(super) fn read_suites<R, F>(input: R, mut on_testcase: F) -> Result<Vec<SuiteWithSummary>>
+where
+    R: io::Read,
+    F: FnMut(&TestCase),
+{
+    let mut packages: Vec<PackageRun> = Vec::new();
+    let mut package_positions: HashMap<String, usize> = HashMap::new();
+
+    for (n, line) in BufReader::new(input).lines().enumerate() {
+        let line = line?;
+        if !line.trim_start().starts_with('{') {
+            continue;
+        }
+        let event: Event = serde_json::from_str(&line)
+            .map_err(|e| anyhow!("Cannot parse test2json event at line {}: {}", n + 1, e))?;
+        // e.g. `build-output` events, which refer to an import path instead
+        let package = match &event.package {
+            Some(package) => package,
+            None => continue,
+        };
+        let position = match package_positions.get(package) {
+            Some(position) => *position,
+            None => {
+                package_positions.insert(package.clone(), packages.len());
+                packages.push(PackageRun::new(package.clone()));
+                packages.len() - 1
+            }
+        };
+        let run = &mut packages[position];
+
+        match (&event.test, event.action.as_str()) {
+            (Some(test), "run") => run.running.push((test.clone(), String::new())),
+            (Some(test), "output") => run.test_output(test, &event.output.unwrap_or_default()),
+            (Some(test), "pass") | (Some(test), "fail") | (Some(test), "skip") => {
+                if let Some(testcase) = run.test_result(test, &event.action, event.elapsed) {
+                    on_testcase(&testcase);
+                    run.suite.testcases.push(testcase);
+                }
+            }
+            (None, "output") => run.output.push_str(&event.output.unwrap_or_default()),
+            (None, "pass") | (None, "fail") | (None, "skip") => {
+                run.finish(event.action == "fail", event.elapsed, &mut on_testcase)
+            }
+            _ => (),
+        }
+    }
+
+    if packages.is_empty() {
+        bail!("No test2json package events found");
+    }
+    // packages without a result, e.g. when the stream was cut short
+    for run in packages.iter_mut().filter(|p| !p.finished) {
+        run.finish(false, None, &mut on_testcase);
+    }
+    Ok(packages
+        .into_iter()
+        .map(|run| run.suite)
+        .filter(|suite| !suite.testcases.is_empty())
+        .map(|suite| suite.with_summary())
+        .collect())
+}
+
+struct PackageRun {
+    suite: TestSuite,
+    /// Tests which have started but not yet reported a result, along with
+    /// their output so far
+    running: Vec<(String, String)>,
+    output: String,
+    finished: bool,
+}
+
+impl PackageRun {
+    fn new(package: String) -> Self {
+        PackageRun {
+            suite: TestSuite::new(package),
+            running: Vec::new(),
+            output: String::new(),
+            finished: false,
+        }
+    }
+
+    fn test_output(&mut self, test: &str, output: &str) {
+        match self.running.iter_mut().find(|(name, _)| name == test) {
+            Some((_, test_output)) => test_output.push_str(output),
+            None => self.running.push((test.to_owned(), output.to_owned())),
+        }
+    }
+
+    fn test_result(&mut self, test: &str, action: &str, elapsed: Option<f64>) -> Option<TestCase> {
+        let output = match self.running.iter().position(|(name, _)| name == test) {
+            Some(position) => self.running.remove(position).1,
+            None => String::new(),
+        };
+        // subtests always complete before their parent
+        let prefix = format!("{}/", test);
+        let subtests: Vec<&TestCase> = self
+            .suite
+            .testcases
+            .iter()
+            .filter(|t| t.name.starts_with(&prefix))
+            .collect();
+        let failed_subtests = subtests.iter().any(|t| t.failure.is_some());
+        if !subtests.is_empty() && (action != "fail" || failed_subtests) {
+            return None;
+        }
+
+        let mut testcase = TestCase::new(test.to_owned(), self.suite.name.clone());
+        testcase.time = elapsed.map(float_seconds).unwrap_or_else(Duration::zero);
+        let output = test_output(&output);
+        match action {
+            "fail" => {
+                testcase.failure = Some(TestFailure {
+                    message: output.lines().next().map(|l| l.trim().to_owned()),
+                    classname: failure_type(&output),
+                    stack_trace: output.clone(),
+                });
+                testcase.system_out = non_blank(output);
+            }
+            "skip" => {
+                testcase.skipped = Some(TestSkipped {
+                    message: output.lines().next().map(|l| l.trim().to_owned()),
+                    details: None,
+                })
+            }
+            _ => (),
+        }
+        Some(testcase)
+    }
+
+    /// Tests still running when the package completes never reported a
+    /// result (e.g. on a timeout) and are accounted for as errors, as are
+    /// failed packages without any failed test, e.g. when they do not build.
+    fn finish<F: FnMut(&TestCase)>(
+        &mut self,
+        failed: bool,
+        elapsed: Option<f64>,
+        on_testcase: &mut F,
+    ) {
+        for (name, output) in std::mem::take(&mut self.running) {
+            let output = test_output(&output);
+            let testcase = TestCase {
+                error: Some(TestFailure {
+                    message: Some("Test did not report a result".to_owned()),
+                    classname: failure_type(&output),
+                    stack_trace: output,
+                }),
+                ..TestCase::new(name, self.suite.name.clone())
+            };
+            on_testcase(&testcase);
+            self.suite.testcases.push(testcase);
+        }
+
+        let output = std::mem::take(&mut self.output);
+        let has_failures = self
+            .suite
+            .testcases
+            .iter()
+            .any(|t| t.failure.is_some() || t.error.is_some());
+        if failed && !has_failures {
+            let testcase = TestCase {
+                error: Some(TestFailure {
+                    message: output.lines().next().map(|l| l.trim().to_owned()),
+                    classname: failure_type(&output),
+                    stack_trace: output.trim().to_owned(),
+                }),
+                ..TestCase::new(self.suite.name.clone(), self.suite.name.clone())
+            };
+            on_testcase(&testcase);
+            self.suite.testcases.push(testcase);
+        }
+
+        self.suite.time = elapsed.map(float_seconds).unwrap_or_else(|| {
+            self.suite
+                .testcases
+                .iter()
+                .fold(Duration::zero(), |acc, t| acc + t.time)
+        });
+        self.suite.system_out = non_blank(output);
+        self.finished = true;
+    }
+}
+
+/// Drops the lines the test runner emits to frame each test's output.
+fn test_output(output: &str) -> String {
+    const FRAMING: [&str; 7] = [
+        "=== RUN",
+        "=== PAUSE",
+        "=== CONT",
+        "=== NAME",
+        "--- PASS",
+        "--- FAIL",
+        "--- SKIP",
+    ];
+    output
+        .lines()
+        .filter(|line| !FRAMING.iter().any(|f| line.trim_start().starts_with(f)))
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+fn failure_type(output: &str) -> String {
+    if output.lines().any(|l| l.starts_with("panic:")) {
+        "panic".to_owned()
+    } else {
+        String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use crate::junit::{HasOutcome, TestOutcome};
+    use pretty_assertions::assert_eq;
+
+    const GO_TEST_JSON: &str = r##"{"Time":"2021-03-01T10:00:00Z","Action":"start","Package":"example.com/calc"}
+{"Time":"2021-03-01T10:00:00Z","Action":"run","Package":"example.com/calc","Test":"TestAdd"}
+{"Time":"2021-03-01T10:00:00Z","Action":"output","Package":"example.com/calc","Test":"TestAdd","Output":"=== RUN   TestAdd\n"}
+{"Time":"2021-03-01T10:00:00Z","Action":"output","Package":"example.com/calc","Test":"TestAdd","Output":"--- PASS: TestAdd (0.00s)\n"}
+{"Time":"2021-03-01T10:00:00Z","Action":"pass","Package":"example.com/calc","Test":"TestAdd","Elapsed":0.001}
+{"Time":"2021-03-01T10:00:00Z","Action":"run","Package":"example.com/calc","Test":"TestDiv"}
+{"Time":"2021-03-01T10:00:00Z","Action":"run","Package":"example.com/calc","Test":"TestDiv/by_zero"}
+{"Time":"2021-03-01T10:00:00Z","Action":"output","Package":"example.com/calc","Test":"TestDiv/by_zero","Output":"    calc_test.go:21: expected an error, got 0\n"}
+{"Time":"2021-03-01T10:00:00Z","Action":"output","Package":"example.com/calc","Test":"TestDiv/by_zero","Output":"    --- FAIL: TestDiv/by_zero (0.00s)\n"}
+{"Time":"2021-03-01T10:00:00Z","Action":"fail","Package":"example.com/calc","Test":"TestDiv/by_zero","Elapsed":0.002}
+{"Time":"2021-03-01T10:00:00Z","Action":"run","Package":"example.com/calc","Test":"TestDiv/by_one"}
+{"Time":"2021-03-01T10:00:00Z","Action":"pass","Package":"example.com/calc","Test":"TestDiv/by_one","Elapsed":0}
+{"Time":"2021-03-01T10:00:00Z","Action":"output","Package":"example.com/calc","Test":"TestDiv","Output":"--- FAIL: TestDiv (0.00s)\n"}
+{"Time":"2021-03-01T10:00:00Z","Action":"fail","Package":"example.com/calc","Test":"TestDiv","Elapsed":0.002}
+{"Time":"2021-03-01T10:00:00Z","Action":"run","Package":"example.com/calc","Test":"TestSqrt"}
+{"Time":"2021-03-01T10:00:00Z","Action":"output","Package":"example.com/calc","Test":"TestSqrt","Output":"    calc_test.go:30: not supported on this platform\n"}
+{"Time":"2021-03-01T10:00:00Z","Action":"skip","Package":"example.com/calc","Test":"TestSqrt","Elapsed":0}
+{"Time":"2021-03-01T10:00:00Z","Action":"output","Package":"example.com/calc","Output":"FAIL\n"}
+{"Time":"2021-03-01T10:00:00Z","Action":"fail","Package":"example.com/calc","Elapsed":0.25}
+{"Time":"2021-03-01T10:00:00Z","Action":"output","Package":"example.com/empty","Output":"?   \texample.com/empty\t[no test files]\n"}
+{"Time":"2021-03-01T10:00:00Z","Action":"skip","Package":"example.com/empty","Elapsed":0}
+"##;
+
+    fn read(json: &str) -> Vec<TestSuite> {
+        read_suites(json.as_bytes(), |_| ())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.value)
+            .collect()
+    }
+
+    #[test]
+    fn parse_packages_and_subtests() {
+        let suites = read(GO_TEST_JSON);
+        assert_eq!(suites.len(), 1);
+        assert_eq!(suites[0].name, "example.com/calc");
+        assert_eq!(suites[0].time, Duration::milliseconds(250));
+
+        let tests: Vec<(&str, TestOutcome)> = suites[0]
+            .testcases
+            .iter()
+            .map(|t| (t.name.as_str(), t.outcome()))
+            .collect();
+        assert_eq!(
+            tests,
+            vec![
+                ("TestAdd", TestOutcome::Passed),
+                ("TestDiv/by_zero", TestOutcome::Failed),
+                ("TestDiv/by_one", TestOutcome::Passed),
+                ("TestSqrt", TestOutcome::Skipped),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_failure_output() {
+        let suites = read(GO_TEST_JSON);
+        let failed = &suites[0].testcases[1];
+        assert_eq!(failed.time, Duration::milliseconds(2));
+        assert_eq!(
+            failed.failure,
+            Some(TestFailure {
+                message: Some("calc_test.go:21: expected an error, got 0".to_owned()),
+                classname: String::new(),
+                stack_trace: "    calc_test.go:21: expected an error, got 0".to_owned(),
+            })
+        );
+        assert_eq!(
+            suites[0].testcases[3]
+                .skipped
+                .as_ref()
+                .and_then(TestSkipped::reason),
+            Some("calc_test.go:30: not supported on this platform")
+        );
+    }
+
+    #[test]
+    fn failed_package_without_failed_tests() {
+        let suites = read(
+            r##"{"Action":"output","Package":"example.com/broken","Output":"# example.com/broken\n"}
+{"Action":"output","Package":"example.com/broken","Output":"./main.go:3:2: undefined: fmt\n"}
+{"Action":"output","Package":"example.com/broken","Output":"FAIL\texample.com/broken [build failed]\n"}
+{"Action":"fail","Package":"example.com/broken","Elapsed":0}
+"##,
+        );
+        let errored = &suites[0].testcases[0];
+        assert_eq!(errored.name, "example.com/broken");
+        assert_eq!(errored.outcome(), TestOutcome::Errored);
+        assert!(errored
+            .error
+            .as_ref()
+            .unwrap()
+            .stack_trace
+            .contains("undefined: fmt"));
+    }
+
+    #[test]
+    fn unfinished_tests_are_errors() {
+        let suites = read(
+            r##"{"Action":"run","Package":"example.com/slow","Test":"TestForever"}
+{"Action":"output","Package":"example.com/slow","Test":"TestForever","Output":"panic: test timed out after 10m0s\n"}
+{"Action":"output","Package":"example.com/slow","Output":"FAIL\texample.com/slow\t600.01s\n"}
+{"Action":"fail","Package":"example.com/slow","Elapsed":600.01}
+"##,
+        );
+        assert_eq!(suites[0].testcases.len(), 1);
+        let error = suites[0].testcases[0].error.as_ref().unwrap();
+        assert_eq!(error.classname, "panic");
+        assert_eq!(error.stack_trace, "panic: test timed out after 10m0s");
+    }
+
+    #[test]
+    fn not_a_test2json_stream() {
+        assert!(read_suites("<testsuite/>".as_bytes(), |_| ()).is_err());
+    }
+}
@@ -0,0 +1,355 @@
+use super::{
+    non_blank, parser::float_millis, HasOutcome, SuiteWithSummary, TestCase, TestFailure,
+    TestSkipped, TestSuite,
+};
+use anyhow::Result;
+use chrono::Duration;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    io::{self, BufReader},
+};
+
+/// The document written by `jest --json`, or by Vitest's `json` reporter
+/// which follows the same shape.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JestResults {
+    test_results: Vec<FileResult>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileResult {
+    name: String,
+    status: Option<String>,
+    message: Option<String>,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    #[serde(default)]
+    assertion_results: Vec<AssertionResult>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AssertionResult {
+    #[serde(default)]
+    ancestor_titles: Vec<String>,
+    title: String,
+    status: String,
+    duration: Option<f64>,
+    #[serde(default)]
+    failure_messages: Vec<String>,
+}
+
+/// Reads a Jest or Vitest JSON report, mapping each test file onto a suite
+/// and each `describe` block onto a suite nested within it.
+pub(super) fn read_suites<R, F>(input: R, mut on_testcase: F) -> Result<Vec<SuiteWithSummary>>
+where
+    R: io::Read,
+    F: FnMut(&TestCase),
+{
+    let results: JestResults = serde_json::from_reader(BufReader::new(input))?;
+    Ok(results
+        .test_results
+        .into_iter()
+        .map(|file| file_suite(file, &mut on_testcase).with_summary())
+        .collect())
+}
+
+fn file_suite<F: FnMut(&TestCase)>(file: FileResult, on_testcase: &mut F) -> TestSuite {
+    let mut suite = TestSuite::new(file.name.clone());
+    // the position of each describe block within its parent, by its path
+    let mut block_positions: HashMap<Vec<String>, usize> = HashMap::new();
+
+    for assertion in file.assertion_results {
+        let testcase = testcase(
+            &file.name,
+            assertion.ancestor_titles.join(" › "),
+            &assertion,
+        );
+        on_testcase(&testcase);
+        let mut parent = &mut suite;
+        for depth in 0..assertion.ancestor_titles.len() {
+            let path = &assertion.ancestor_titles[..=depth];
+            let position = match block_positions.get(path) {
+                Some(position) => *position,
+                None => {
+                    block_positions.insert(path.to_vec(), parent.testsuites.len());
+                    parent.testsuites.push(TestSuite::new(path[depth].clone()));
+                    parent.testsuites.len() - 1
+                }
+            };
+            parent = &mut parent.testsuites[position];
+        }
+        parent.testcases.push(testcase);
+    }
+
+    // the test file failed on its own, e.g. it could not be compiled
+    let message = file.message.and_then(|m| non_blank(strip_ansi(&m)));
+    if file.status.as_deref() == Some("failed") && suite.is_successful() {
+        let testcase = TestCase {
+            error: Some(failure(&message.unwrap_or_default())),
+            ..TestCase::new(file.name.clone(), file.name.clone())
+        };
+        on_testcase(&testcase);
+        suite.testcases.push(testcase);
+    }
+
+    set_times(&mut suite);
+    if let (Some(start), Some(end)) = (file.start_time, file.end_time) {
+        suite.time = Duration::milliseconds((end - start).abs());
+    }
+    suite
+}
+
+/// Describe blocks are timed after the tests they contain.
+fn set_times(suite: &mut TestSuite) {
+    for nested in suite.testsuites.iter_mut() {
+        set_times(nested);
+    }
+    suite.time = suite
+        .testcases
+        .iter()
+        .map(|t| t.time)
+        .chain(suite.testsuites.iter().map(|s| s.time))
+        .fold(Duration::zero(), |acc, time| acc + time);
+}
+
+fn testcase(file: &str, ancestors: String, assertion: &AssertionResult) -> TestCase {
+    let classname = if ancestors.is_empty() {
+        file.to_owned()
+    } else {
+        ancestors
+    };
+    let mut testcase = TestCase::new(assertion.title.clone(), classname);
+    testcase.time = assertion
+        .duration
+        .map(float_millis)
+        .unwrap_or_else(Duration::zero);
+
+    match assertion.status.as_str() {
+        "failed" => {
+            let messages: Vec<String> = assertion
+                .failure_messages
+                .iter()
+                .map(|m| strip_ansi(m))
+                .collect();
+            testcase.failure = Some(failure(&messages.join("\n\n")));
+        }
+        "todo" => {
+            testcase.skipped = Some(TestSkipped {
+                message: Some("todo".to_owned()),
+                details: None,
+            })
+        }
+        "pending" | "skipped" | "disabled" => testcase.skipped = Some(TestSkipped::default()),
+        _ => (),
+    }
+    testcase
+}
+
+/// Failure messages start with the error, e.g. `TypeError: x is not a
+/// function`, followed by the stack trace.
+pub(super) fn failure(messages: &str) -> TestFailure {
+    let first_line = messages.trim().lines().next().unwrap_or_default();
+    let (classname, message) = match first_line.split_once(": ") {
+        Some((error, message)) if error.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+            (error.to_owned(), message)
+        }
+        _ => (String::new(), first_line),
+    };
+    TestFailure {
+        message: non_blank(message.trim().to_owned()),
+        classname,
+        stack_trace: messages.trim().to_owned(),
+    }
+}
+
+/// Drops the ANSI colour codes Jest embeds in its failure messages.
+pub(super) fn strip_ansi(s: &str) -> String {
+    let mut stripped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // skip up to the letter terminating the escape sequence
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use crate::junit::TestOutcome;
+    use pretty_assertions::assert_eq;
+
+    const JEST_JSON: &str = r##"{
+  "numTotalTests": 5,
+  "success": false,
+  "testResults": [
+    {
+      "name": "/app/src/cart.test.js",
+      "status": "failed",
+      "message": "",
+      "startTime": 1600000000000,
+      "endTime": 1600000000420,
+      "assertionResults": [
+        {
+          "ancestorTitles": ["Cart", "checkout"],
+          "title": "applies discounts",
+          "fullName": "Cart checkout applies discounts",
+          "status": "failed",
+          "duration": 12,
+          "failureMessages": ["Error: \u001b[2mexpect(\u001b[22m\u001b[31mreceived\u001b[39m\u001b[2m).\u001b[22mtoBe\u001b[2m(\u001b[22m\u001b[32mexpected\u001b[39m\u001b[2m)\u001b[22m\n\nExpected: 90\nReceived: 100\n    at Object.<anonymous> (/app/src/cart.test.js:12:27)"]
+        },
+        {
+          "ancestorTitles": ["Cart", "checkout"],
+          "title": "charges shipping",
+          "status": "passed",
+          "duration": 3,
+          "failureMessages": []
+        },
+        {
+          "ancestorTitles": ["Cart"],
+          "title": "is empty by default",
+          "status": "pending",
+          "duration": null,
+          "failureMessages": []
+        },
+        {
+          "ancestorTitles": [],
+          "title": "supports gift cards",
+          "status": "todo",
+          "failureMessages": []
+        }
+      ]
+    },
+    {
+      "name": "/app/src/broken.test.js",
+      "status": "failed",
+      "message": "  ● Test suite failed to run\n\n    SyntaxError: Unexpected token (3:4)",
+      "assertionResults": []
+    }
+  ]
+}"##;
+
+    fn read(json: &str) -> Vec<TestSuite> {
+        read_suites(json.as_bytes(), |_| ())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.value)
+            .collect()
+    }
+
+    #[test]
+    fn parse_describe_blocks() {
+        let suites = read(JEST_JSON);
+        let cart = &suites[0];
+        assert_eq!(cart.name, "/app/src/cart.test.js");
+        assert_eq!(cart.time, Duration::milliseconds(420));
+        assert_eq!(cart.testcases[0].name, "supports gift cards");
+
+        let describe = &cart.testsuites[0];
+        assert_eq!(describe.name, "Cart");
+        assert_eq!(describe.testcases[0].name, "is empty by default");
+        assert_eq!(describe.testsuites[0].name, "checkout");
+        assert_eq!(describe.testsuites[0].time, Duration::milliseconds(15));
+
+        let outcomes: Vec<(&str, TestOutcome)> = describe.testsuites[0]
+            .testcases
+            .iter()
+            .map(|t| (t.classname.as_str(), t.outcome()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("Cart › checkout", TestOutcome::Failed),
+                ("Cart › checkout", TestOutcome::Passed)
+            ]
+        );
+        assert_eq!(cart.summary().tests, 4);
+        assert_eq!(cart.summary().skipped, 2);
+    }
+
+    #[test]
+    fn parse_failure_messages() {
+        let suites = read(JEST_JSON);
+        let failure = suites[0].testsuites[0].testsuites[0].testcases[0]
+            .failure
+            .clone()
+            .unwrap();
+        assert_eq!(failure.classname, "Error");
+        assert_eq!(
+            failure.message,
+            Some("expect(received).toBe(expected)".to_owned())
+        );
+        assert!(failure
+            .stack_trace
+            .ends_with("(/app/src/cart.test.js:12:27)"));
+        assert_eq!(
+            suites[0].testcases[0]
+                .skipped
+                .as_ref()
+                .and_then(TestSkipped::reason),
+            Some("todo")
+        );
+    }
+
+    #[test]
+    fn test_file_failing_to_run() {
+        let suites = read(JEST_JSON);
+        let broken = &suites[1].testcases[0];
+        assert_eq!(broken.outcome(), TestOutcome::Errored);
+        assert_eq!(
+            broken.error.as_ref().unwrap().message,
+            Some("● Test suite failed to run".to_owned())
+        );
+    }
+
+    #[test]
+    fn parse_vitest_report() {
+        let suites = read(
+            r##"{
+  "numTotalTests": 1,
+  "testResults": [{
+    "name": "/app/src/sum.test.ts",
+    "status": "passed",
+    "message": "",
+    "assertionResults": [{
+      "ancestorTitles": ["sum"],
+      "fullName": "sum adds numbers",
+      "status": "passed",
+      "title": "adds numbers",
+      "duration": 1.5,
+      "failureMessages": [],
+      "meta": {}
+    }]
+  }]
+}"##,
+        );
+        let test = &suites[0].testsuites[0].testcases[0];
+        assert_eq!(test.time, Duration::microseconds(1500));
+        assert!(suites[0].is_successful());
+    }
+
+    #[test]
+    fn not_a_jest_report() {
+        assert!(read_suites("{}".as_bytes(), |_| ()).is_err());
+        assert!(read_suites("{\"testResults\": {}}".as_bytes(), |_| ()).is_err());
+    }
+
+    #[test]
+    fn no_test_files() {
+        assert!(read("{\"numTotalTests\": 0, \"testResults\": []}").is_empty());
+    }
+}
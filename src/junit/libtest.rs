@@ -0,0 +1,373 @@
+use super::{
+    non_blank, parser::float_seconds, SuiteWithSummary, TestCase, TestFailure, TestSkipped,
+    TestSuite,
+};
+use anyhow::{anyhow, bail, Result};
+use chrono::Duration;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader},
+};
+
+/// An event emitted by `cargo test -- -Z unstable-options --format json`, or
+/// by `cargo nextest run --message-format libtest-json`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Event {
+    Suite(SuiteEvent),
+    Test(TestEvent),
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct SuiteEvent {
+    event: String,
+    exec_time: Option<f64>,
+    nextest: Option<NextestBinary>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TestEvent {
+    event: String,
+    name: String,
+    exec_time: Option<f64>,
+    stdout: Option<String>,
+    message: Option<String>,
+}
+
+/// The test binary a suite was run from, only reported by nextest.
+#[derive(Debug, Deserialize)]
+struct NextestBinary {
+    #[serde(rename = "crate")]
+    krate: String,
+    test_binary: String,
+    kind: String,
+}
+
+impl NextestBinary {
+    /// Library tests are named after their crate alone, as nextest does.
+    fn binary_id(&self) -> String {
+        if self.kind == "lib" {
+            self.krate.clone()
+        } else {
+            format!("{}::{}", self.krate, self.test_binary)
+        }
+    }
+}
+
+/// Reads a libtest JSON event stream, mapping each test binary onto a suite.
+/// As plain libtest output does not name the binaries, their suites are named
+/// after the report file instead.
+pub(super) fn read_suites<R, F>(
+    input: R,
+    name: &str,
+    mut on_testcase: F,
+) -> Result<Vec<SuiteWithSummary>>
+where
+    R: io::Read,
+    F: FnMut(&TestCase),
+{
+    let mut suites: Vec<TestSuite> = Vec::new();
+    let mut current: Option<RunningSuite> = None;
+
+    for (n, line) in BufReader::new(input).lines().enumerate() {
+        let line = line?;
+        if !line.trim_start().starts_with('{') {
+            if let Some(running) = current.as_mut() {
+                running.output.push_str(&line);
+                running.output.push('\n');
+            }
+            continue;
+        }
+        let event: Event = serde_json::from_str(&line)
+            .map_err(|e| anyhow!("Cannot parse libtest event at line {}: {}", n + 1, e))?;
+
+        match event {
+            Event::Suite(suite) if suite.event == "started" => {
+                if let Some(running) = current.take() {
+                    suites.push(running.finish(None, &mut on_testcase));
+                }
+                let suite_name = match (&suite.nextest, suites.len()) {
+                    (Some(binary), _) => binary.binary_id(),
+                    (None, 0) => name.to_owned(),
+                    (None, n) => format!("{} #{}", name, n + 1),
+                };
+                current = Some(RunningSuite::new(suite_name));
+            }
+            Event::Suite(suite) => {
+                let running = current
+                    .take()
+                    .ok_or_else(|| anyhow!("Suite finished at line {} was never started", n + 1))?;
+                suites.push(running.finish(suite.exec_time, &mut on_testcase));
+            }
+            Event::Test(test) => {
+                let running = current
+                    .as_mut()
+                    .ok_or_else(|| anyhow!("Test at line {} is outside of any suite", n + 1))?;
+                if let Some(testcase) = running.test_event(test) {
+                    on_testcase(&testcase);
+                    running.suite.testcases.push(testcase);
+                }
+            }
+            Event::Other => (),
+        }
+    }
+
+    // the stream ended before the suite did, e.g. a test aborted the process
+    if let Some(running) = current.take() {
+        suites.push(running.finish(None, &mut on_testcase));
+    }
+    if suites.is_empty() {
+        bail!("No libtest suite events found");
+    }
+    Ok(suites.into_iter().map(|s| s.with_summary()).collect())
+}
+
+struct RunningSuite {
+    suite: TestSuite,
+    /// Tests which have started but not yet reported a result, along with
+    /// the order they started in
+    running: HashMap<String, usize>,
+    started: usize,
+    output: String,
+}
+
+impl RunningSuite {
+    fn new(name: String) -> Self {
+        RunningSuite {
+            suite: TestSuite::new(name),
+            running: HashMap::new(),
+            started: 0,
+            output: String::new(),
+        }
+    }
+
+    fn test_event(&mut self, test: TestEvent) -> Option<TestCase> {
+        if test.event == "started" {
+            self.running.insert(test.name, self.started);
+            self.started += 1;
+            return None;
+        }
+        // e.g. `timeout`, reported when a test runs for over 60 seconds
+        if !["ok", "failed", "ignored", "allowed_fail"].contains(&test.event.as_str()) {
+            return None;
+        }
+        self.running.remove(&test.name);
+
+        let mut testcase = testcase(&test.name);
+        testcase.time = test
+            .exec_time
+            .map(float_seconds)
+            .unwrap_or_else(Duration::zero);
+        match test.event.as_str() {
+            "failed" => {
+                let stdout = test.stdout.unwrap_or_default();
+                testcase.failure = Some(TestFailure {
+                    message: test.message.or_else(|| panic_message(&stdout)),
+                    classname: "panic".to_owned(),
+                    stack_trace: stdout.trim().to_owned(),
+                });
+                testcase.system_out = non_blank(stdout);
+            }
+            "ignored" => {
+                testcase.skipped = Some(TestSkipped {
+                    message: test.message,
+                    details: None,
+                })
+            }
+            _ => testcase.system_out = test.stdout.and_then(non_blank),
+        }
+        Some(testcase)
+    }
+
+    /// Tests still running when the suite ends never reported a result and
+    /// are accounted for as errors.
+    fn finish<F: FnMut(&TestCase)>(
+        mut self,
+        exec_time: Option<f64>,
+        on_testcase: &mut F,
+    ) -> TestSuite {
+        let mut running: Vec<(String, usize)> =
+            std::mem::take(&mut self.running).into_iter().collect();
+        running.sort_by_key(|(_, started)| *started);
+        for (name, _) in running {
+            let testcase = TestCase {
+                error: Some(TestFailure {
+                    message: Some("Test did not report a result".to_owned()),
+                    classname: String::new(),
+                    stack_trace: String::new(),
+                }),
+                ..testcase(&name)
+            };
+            on_testcase(&testcase);
+            self.suite.testcases.push(testcase);
+        }
+        self.suite.time = exec_time.map(float_seconds).unwrap_or_else(|| {
+            self.suite
+                .testcases
+                .iter()
+                .fold(Duration::zero(), |acc, t| acc + t.time)
+        });
+        self.suite.system_out = non_blank(self.output);
+        self.suite
+    }
+}
+
+/// Nextest prefixes test names with the id of their binary, e.g.
+/// `my-crate::integration$tests::it_works`.
+fn testcase(name: &str) -> TestCase {
+    let name = name.split_once('$').map_or(name, |(_, name)| name);
+    let classname = name.rsplit_once("::").map_or("", |(module, _)| module);
+    TestCase::new(name.to_owned(), classname.to_owned())
+}
+
+/// Extracts the panic message from the captured output of a failed test,
+/// either following `panicked at <location>:` on the next line, or inline as
+/// in `panicked at 'message', <location>` for older toolchains.
+fn panic_message(stdout: &str) -> Option<String> {
+    let mut lines = stdout.lines();
+    let panicked = lines.find_map(|line| line.split_once("panicked at ").map(|(_, at)| at))?;
+    if panicked.ends_with(':') {
+        lines.next().map(|line| line.trim().to_owned())
+    } else {
+        let inline = panicked.strip_prefix('\'')?;
+        inline.rfind("', ").map(|end| inline[..end].to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use crate::junit::{HasOutcome, TestOutcome};
+    use pretty_assertions::assert_eq;
+
+    const CARGO_TEST_JSON: &str = r##"{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "parser::tests::parses_numbers" }
+{ "type": "test", "event": "started", "name": "parser::tests::parses_strings" }
+{ "type": "test", "event": "started", "name": "slow" }
+{ "type": "test", "name": "parser::tests::parses_numbers", "event": "ok", "exec_time": 0.0015 }
+{ "type": "test", "name": "parser::tests::parses_strings", "event": "failed", "exec_time": 0.002, "stdout": "thread 'parser::tests::parses_strings' panicked at src/parser.rs:42:9:\nassertion `left == right` failed\n  left: 1\n right: 2\nnote: run with `RUST_BACKTRACE=1` environment variable to display a backtrace\n" }
+{ "type": "test", "name": "slow", "event": "ignored", "message": "takes minutes" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "ignored": 1, "measured": 0, "filtered_out": 0, "exec_time": 0.25 }
+{ "type": "suite", "event": "started", "test_count": 1 }
+{ "type": "test", "event": "started", "name": "it_works" }
+{ "type": "test", "name": "it_works", "event": "ok" }
+{ "type": "suite", "event": "ok", "passed": 1, "failed": 0, "ignored": 0, "measured": 0, "filtered_out": 0, "exec_time": 0.1 }
+"##;
+
+    fn read(json: &str) -> Vec<TestSuite> {
+        read_suites(json.as_bytes(), "cargo-test", |_| ())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.value)
+            .collect()
+    }
+
+    #[test]
+    fn parse_cargo_test_events() {
+        let suites = read(CARGO_TEST_JSON);
+        let names: Vec<&str> = suites.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["cargo-test", "cargo-test #2"]);
+        assert_eq!(suites[0].time, Duration::milliseconds(250));
+
+        let tests: Vec<(&str, &str, TestOutcome)> = suites[0]
+            .testcases
+            .iter()
+            .map(|t| (t.classname.as_str(), t.name.as_str(), t.outcome()))
+            .collect();
+        assert_eq!(
+            tests,
+            vec![
+                (
+                    "parser::tests",
+                    "parser::tests::parses_numbers",
+                    TestOutcome::Passed
+                ),
+                (
+                    "parser::tests",
+                    "parser::tests::parses_strings",
+                    TestOutcome::Failed
+                ),
+                ("", "slow", TestOutcome::Skipped),
+            ]
+        );
+        assert_eq!(suites[0].testcases[0].time, Duration::microseconds(1500));
+        assert_eq!(
+            suites[0].testcases[2].skipped,
+            Some(TestSkipped {
+                message: Some("takes minutes".to_owned()),
+                details: None
+            })
+        );
+    }
+
+    #[test]
+    fn parse_failure_output() {
+        let suites = read(CARGO_TEST_JSON);
+        let failure = suites[0].testcases[1].failure.as_ref().unwrap();
+        assert_eq!(
+            failure.message,
+            Some("assertion `left == right` failed".to_owned())
+        );
+        assert!(failure.stack_trace.contains("left: 1"));
+    }
+
+    #[test]
+    fn parse_legacy_panic_message() {
+        assert_eq!(
+            panic_message("thread 'a' panicked at 'index out of bounds', src/lib.rs:3:5\n"),
+            Some("index out of bounds".to_owned())
+        );
+    }
+
+    #[test]
+    fn parse_nextest_events() {
+        let suites = read(
+            r##"{"type":"suite","event":"started","test_count":1,"nextest":{"crate":"cikit","test_binary":"cikit","kind":"lib"}}
+{"type":"test","event":"started","name":"cikit$junit::tests::parse_testsuite"}
+{"type":"test","event":"ok","name":"cikit$junit::tests::parse_testsuite","exec_time":0.01}
+{"type":"suite","event":"ok","passed":1,"failed":0,"ignored":0,"measured":0,"filtered_out":0,"exec_time":0.01,"nextest":{"crate":"cikit","test_binary":"cikit","kind":"lib"}}
+{"type":"suite","event":"started","test_count":1,"nextest":{"crate":"cikit","test_binary":"cli","kind":"test"}}
+{"type":"test","event":"started","name":"cikit::cli$renders_text"}
+{"type":"test","event":"ok","name":"cikit::cli$renders_text","exec_time":0.02}
+{"type":"suite","event":"ok","passed":1,"failed":0,"ignored":0,"measured":0,"filtered_out":0,"exec_time":0.02,"nextest":{"crate":"cikit","test_binary":"cli","kind":"test"}}
+"##,
+        );
+        let tests: Vec<(&str, &str)> = suites
+            .iter()
+            .flat_map(|s| {
+                s.testcases
+                    .iter()
+                    .map(move |t| (s.name.as_str(), t.name.as_str()))
+            })
+            .collect();
+        assert_eq!(
+            tests,
+            vec![
+                ("cikit", "junit::tests::parse_testsuite"),
+                ("cikit::cli", "renders_text")
+            ]
+        );
+    }
+
+    #[test]
+    fn unfinished_tests_are_errors() {
+        let suites = read(
+            r##"{ "type": "suite", "event": "started", "test_count": 2 }
+{ "type": "test", "event": "started", "name": "aborts" }
+"##,
+        );
+        assert_eq!(suites[0].testcases[0].outcome(), TestOutcome::Errored);
+        assert!(!suites[0].is_successful());
+    }
+
+    #[test]
+    fn not_a_libtest_stream() {
+        assert!(read_suites("<testsuite/>".as_bytes(), "x", |_| ()).is_err());
+        assert!(read_suites("{\"type\": \"test\"".as_bytes(), "x", |_| ()).is_err());
+    }
+}
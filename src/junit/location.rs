@@ -0,0 +1,103 @@
+use super::{FailedTestCase, SourceLocation};
+use std::path::Path;
+
+impl FailedTestCase {
+    /// The location reported for the test if any, or else the first frame
+    /// of its stack trace pointing to a file of the workspace. Files are
+    /// given relative to the workspace, and left out when outside of it.
+    pub fn source_location(&self, workspace: &Path) -> Option<SourceLocation> {
+        match &self.location {
+            Some(location) => {
+                workspace_path(&location.file, workspace).map(|file| SourceLocation {
+                    file,
+                    line: location.line,
+                })
+            }
+            None => self
+                .failure
+                .stack_trace
+                .lines()
+                .filter_map(stack_frame)
+                .find_map(|(file, line)| {
+                    workspace_path(&file, workspace).map(|file| SourceLocation {
+                        file,
+                        line: Some(line),
+                    })
+                }),
+        }
+    }
+}
+
+/// Finds a `path:line` reference in a line of a stack trace, as written by
+/// most languages, or a `File "path", line N` one, as written by Python.
+fn stack_frame(line: &str) -> Option<(String, u32)> {
+    let trimmed = line.trim();
+    if let Some(rest) = trimmed.strip_prefix("File \"") {
+        let end = rest.find('"')?;
+        let line = rest[end..]
+            .trim_start_matches(&['"', ','][..])
+            .trim_start()
+            .strip_prefix("line ")?;
+        let digits: String = line.chars().take_while(char::is_ascii_digit).collect();
+        return Some((rest[..end].to_owned(), digits.parse().ok()?));
+    }
+    trimmed
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        .find_map(|token| {
+            let token = token.trim_start_matches("file://");
+            let mut parts = token.split(':');
+            let file = parts.next()?;
+            let line = parts.next()?.parse().ok()?;
+            let has_extension = matches!(Path::new(file).extension(), Some(e) if !e.is_empty());
+            if has_extension {
+                Some((file.to_owned(), line))
+            } else {
+                None
+            }
+        })
+}
+
+/// Code is only browsable within the repository, given relative to it.
+/// Dependencies are left out even when installed within the workspace.
+fn workspace_path(file: &str, workspace: &Path) -> Option<String> {
+    let path = Path::new(file);
+    let relative = if path.is_absolute() {
+        path.strip_prefix(workspace).ok()?.to_owned()
+    } else if workspace.join(path).is_file() {
+        path.to_owned()
+    } else {
+        return None;
+    };
+    if relative
+        .components()
+        .any(|c| c.as_os_str() == "node_modules")
+    {
+        return None;
+    }
+    Some(relative.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn find_stack_frames() {
+        assert_eq!(
+            stack_frame("    at Object.<anonymous> (src/cart.test.js:12:5)"),
+            Some(("src/cart.test.js".to_owned(), 12))
+        );
+        assert_eq!(
+            stack_frame("  File \"tests/test_cart.py\", line 42, in test_totals"),
+            Some(("tests/test_cart.py".to_owned(), 42))
+        );
+        assert_eq!(
+            stack_frame("    cart_test.go:31: expected 90, got 100"),
+            Some(("cart_test.go".to_owned(), 31))
+        );
+        assert_eq!(stack_frame("see https://example.com:8080/docs"), None);
+    }
+}
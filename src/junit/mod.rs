@@ -1,13 +1,18 @@
 use crate::{config::Config, github::GithubEvent};
-use anyhow::Result;
-use chrono::Duration;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use serdes::*;
-use std::{env, io, ops::AddAssign, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    env, io,
+    ops::AddAssign,
+    path::{Component, PathBuf},
+};
 
-use self::fs::TestSuiteReader;
+use self::{fs::TestSuiteReader, parser::SuiteParser};
 
-#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
 pub struct SummaryWith<T>
 where
     T: Serialize + PartialEq + Clone,
@@ -18,55 +23,95 @@ where
     pub value: T,
 }
 
-impl<T> SummaryWith<T>
+impl<T> HasOutcome for SummaryWith<T>
 where
     T: Serialize + PartialEq + Clone,
 {
-    pub fn is_successful(&self) -> bool {
-        self.summary.errors == 0 && self.summary.failures == 0
+    fn outcome(&self) -> TestOutcome {
+        self.summary.outcome()
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TestSuite {
     pub name: String,
-    #[serde(deserialize_with = "f32_to_duration")]
     #[serde(skip_serializing)]
     pub time: Duration,
-    #[serde(rename = "testcase", default)]
+    #[serde(rename = "testcase")]
     pub testcases: Vec<TestCase>,
+    pub system_out: Option<String>,
+    pub system_err: Option<String>,
+    pub metadata: SuiteMetadata,
+    #[serde(serialize_with = "suites_with_summary")]
+    pub testsuites: Vec<TestSuite>,
 }
 
 impl TestSuite {
+    /// An empty suite, which readers fill as they go.
+    pub fn new(name: String) -> Self {
+        TestSuite {
+            name,
+            time: Duration::zero(),
+            testcases: Vec::new(),
+            system_out: None,
+            system_err: None,
+            metadata: SuiteMetadata::default(),
+            testsuites: Vec::new(),
+        }
+    }
+
+    /// Summarises this suite along with all the nested ones.
     pub fn summary(&self) -> Summary {
-        let mut tests = 0;
-        let mut failures = 0;
-        let mut errors = 0; //TODO: remove
-        let mut skipped = 0;
+        let mut summary = self.own_summary();
+        let mut acc = &mut summary;
+        for suite in &self.testsuites {
+            acc += &suite.summary();
+        }
+        summary.time = self.time;
+        summary
+    }
 
+    /// Summarises the test cases directly contained in this suite.
+    fn own_summary(&self) -> Summary {
+        let mut summary = Summary::zero();
+        let mut acc = &mut summary;
         for test in &self.testcases {
-            tests += 1;
-            if test.skipped.is_some() {
-                skipped += 1;
-                continue;
-            }
-            if test.failure.is_some() {
-                failures += 1;
-                continue;
-            }
-            if test.error.is_some() {
-                errors += 1;
-                continue;
-            }
+            acc += &test.summary();
         }
+        summary.time = self.time;
+        summary
+    }
 
-        Summary {
-            time: self.time.clone(),
-            tests,
-            failures,
-            errors,
-            skipped,
+    /// Collects the files attached to the test cases of this suite and the
+    /// nested ones.
+    pub fn all_attachments(&self) -> Vec<&Attachment> {
+        let mut attachments: Vec<&Attachment> = self
+            .testcases
+            .iter()
+            .flat_map(|t| t.attachments.iter())
+            .collect();
+        for suite in &self.testsuites {
+            attachments.extend(suite.all_attachments());
+        }
+        attachments
+    }
+
+    /// Collects the skipped test cases of this suite and the nested ones.
+    pub fn all_skipped(&self) -> Vec<SkippedTest<'_>> {
+        let mut skipped: Vec<SkippedTest> = self
+            .testcases
+            .iter()
+            .filter(|t| t.skipped.is_some())
+            .map(|testcase| SkippedTest {
+                suite: &self.name,
+                testcase,
+            })
+            .collect();
+        for suite in &self.testsuites {
+            skipped.extend(suite.all_skipped());
         }
+        skipped
     }
 
     pub fn with_summary(self) -> SuiteWithSummary {
@@ -77,8 +122,46 @@ impl TestSuite {
         }
     }
 
+    /// Turns the suite hierarchy into a flat list, dropping the suites that
+    /// act only as containers for the nested ones.
+    pub fn flatten(mut self) -> Vec<TestSuite> {
+        let nested = std::mem::take(&mut self.testsuites);
+        let mut suites = Vec::new();
+
+        if !self.testcases.is_empty() || nested.is_empty() {
+            suites.push(self);
+        }
+        for suite in nested {
+            suites.extend(suite.flatten());
+        }
+        suites
+    }
+
+    pub fn sort_suites(&mut self, sorting: &ReportSorting) {
+        let ReportSorting::Time(order) = sorting;
+        self.testsuites.sort_by(|a, b| {
+            if *order == SortingOrder::Asc {
+                a.time.cmp(&b.time)
+            } else {
+                b.time.cmp(&a.time)
+            }
+        });
+        for suite in &mut self.testsuites {
+            suite.sort_suites(sorting);
+        }
+    }
+
+    /// Collects the failures of this suite and of all the nested ones.
+    pub fn all_failed(&self) -> Vec<FailedSuiteWithSummary> {
+        let mut failed: Vec<FailedSuiteWithSummary> = self.as_failed().into_iter().collect();
+        for suite in &self.testsuites {
+            failed.extend(suite.all_failed());
+        }
+        failed
+    }
+
     pub fn as_failed(&self) -> Option<FailedSuiteWithSummary> {
-        let summary = self.summary();
+        let summary = self.own_summary();
         let mut failed_testcases: Vec<FailedTestCase> = Vec::new();
 
         for t in &self.testcases {
@@ -92,8 +175,10 @@ impl TestSuite {
         } else {
             let value = FailedTestSuite {
                 name: self.name.clone(),
-                time: self.time.clone(),
-                failed_testcases: failed_testcases,
+                time: self.time,
+                failed_testcases,
+                system_out: self.system_out.clone(),
+                system_err: self.system_err.clone(),
             };
             Some(SummaryWith { summary, value })
         }
@@ -102,73 +187,227 @@ impl TestSuite {
 
 pub type SuiteWithSummary = SummaryWith<TestSuite>;
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+/// Describes where and when a suite was run, as reported by the optional
+/// `<testsuite>` attributes and its `<properties>` element.
+#[derive(Debug, PartialEq, Clone, Default, Deserialize, Serialize)]
+pub struct SuiteMetadata {
+    pub hostname: Option<String>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub package: Option<String>,
+    pub id: Option<String>,
+    pub file: Option<String>,
+    pub properties: Vec<Property>,
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct Property {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TestCase {
     pub name: String,
     pub classname: String,
-    #[serde(
-        deserialize_with = "f32_to_duration",
-        serialize_with = "duration_to_millis"
-    )]
+    #[serde(serialize_with = "duration_to_millis")]
     pub time: Duration,
     pub failure: Option<TestFailure>,
     pub error: Option<TestFailure>,
-    #[serde(serialize_with = "testskipped_to_boolean")]
-    skipped: Option<TestSkipped>,
+    pub skipped: Option<TestSkipped>,
+    pub system_out: Option<String>,
+    pub system_err: Option<String>,
+    /// Failed runs reported by Surefire when re-running failing tests
+    pub attempts: Vec<TestAttempt>,
+    /// Files captured while running the test, e.g. screenshots
+    pub attachments: Vec<Attachment>,
+    /// Where the test is defined, when the report tells
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<SourceLocation>,
 }
 impl TestCase {
-    //TODO: remove
-    pub fn is_skipped(&self) -> bool {
-        self.skipped.is_some()
+    /// A passed test case, taking no time, until readers tell otherwise.
+    pub fn new(name: String, classname: String) -> Self {
+        TestCase {
+            name,
+            classname,
+            time: Duration::zero(),
+            failure: None,
+            error: None,
+            skipped: None,
+            system_out: None,
+            system_err: None,
+            attempts: Vec::new(),
+            attachments: Vec::new(),
+            location: None,
+        }
     }
 
-    //TODO: remove
-    pub fn is_successful(&self) -> bool {
-        self.failure.is_none() && self.error.is_none()
+    /// Accounts for this test case alone. Its time is left out, as this is
+    /// already included in the duration of the enclosing suite.
+    pub fn summary(&self) -> Summary {
+        let mut summary = Summary::zero();
+        summary.tests = 1;
+        match self.outcome() {
+            TestOutcome::Passed => (),
+            TestOutcome::Failed => summary.failures = 1,
+            TestOutcome::Errored => summary.errors = 1,
+            TestOutcome::Skipped => summary.skipped = 1,
+            TestOutcome::Flaky => summary.flaky = 1,
+        }
+        summary
+    }
+
+    /// A test is flaky when it eventually passed after one or more failed runs.
+    pub fn is_flaky(&self) -> bool {
+        self.outcome() == TestOutcome::Flaky
     }
 
     fn as_failed(&self) -> Option<FailedTestCase> {
-        match self {
-            TestCase {
-                name,
-                classname,
-                time,
-                failure,
-                error,
-                skipped: _,
-            } => failure
-                .as_ref()
-                .or_else(|| error.as_ref())
-                .map(|failure| FailedTestCase {
-                    name: name.clone(),
-                    classname: classname.clone(),
-                    time: time.clone(),
-                    failure: failure.clone(),
-                }),
-        }
+        let TestCase {
+            name,
+            classname,
+            time,
+            failure,
+            error,
+            skipped: _,
+            system_out,
+            system_err,
+            attempts,
+            attachments,
+            location,
+        } = self;
+        failure
+            .as_ref()
+            .or(error.as_ref())
+            .map(|failure| FailedTestCase {
+                name: name.clone(),
+                classname: classname.clone(),
+                time: *time,
+                failure: failure.clone(),
+                system_out: system_out.clone(),
+                system_err: system_err.clone(),
+                attempts: attempts.clone(),
+                attachments: attachments.clone(),
+                location: location.clone(),
+            })
     }
+}
 
-    //TODO: remove this
-    pub fn outcome(&self) -> TestOutcome {
-        match (self.is_skipped(), &self.failure) {
-            (true, _) => TestOutcome::Skipped,
-            (_, Some(_)) => TestOutcome::Failure,
-            (_, None) => TestOutcome::Success,
+impl HasOutcome for TestCase {
+    fn outcome(&self) -> TestOutcome {
+        if self.skipped.is_some() {
+            TestOutcome::Skipped
+        } else if self.failure.is_some() {
+            TestOutcome::Failed
+        } else if self.error.is_some() {
+            TestOutcome::Errored
+        } else if self.attempts.iter().any(|a| a.kind.is_flaky()) {
+            TestOutcome::Flaky
+        } else {
+            TestOutcome::Passed
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AttemptKind {
+    FlakyFailure,
+    FlakyError,
+    RerunFailure,
+    RerunError,
+}
+
+impl AttemptKind {
+    pub fn is_flaky(&self) -> bool {
+        *self == AttemptKind::FlakyFailure || *self == AttemptKind::FlakyError
+    }
+}
+
+/// A failed run of a test case other than the one reported through its
+/// `<failure>` or `<error>` element.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestAttempt {
+    pub kind: AttemptKind,
+    pub failure: TestFailure,
+    pub system_out: Option<String>,
+    pub system_err: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FailedTestCase {
     pub name: String,
     pub classname: String,
     #[serde(serialize_with = "duration_to_millis")]
     pub time: Duration,
     pub failure: TestFailure, //TODO: use an enum here
+    pub system_out: Option<String>,
+    pub system_err: Option<String>,
+    pub attempts: Vec<TestAttempt>,
+    pub attachments: Vec<Attachment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<SourceLocation>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
-pub struct TestSkipped {}
+/// The source file, and line within it, defining a test case, relative to
+/// the project directory or absolute.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: Option<u32>,
+}
+
+/// A file produced by a test run, referenced by its path on the machine the
+/// tests ran on.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub name: String,
+    pub content_type: Option<String>,
+    pub path: PathBuf,
+}
+
+impl Attachment {
+    /// Where the file is copied to within a report directory, mirroring its
+    /// original path so that attachments with the same name do not clash.
+    pub fn report_path(&self) -> PathBuf {
+        let mut report_path = PathBuf::from("attachments");
+        for component in self.path.components() {
+            if let Component::Normal(part) = component {
+                report_path.push(part);
+            }
+        }
+        report_path
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestSkipped {
+    pub message: Option<String>,
+    pub details: Option<String>,
+}
+
+impl TestSkipped {
+    /// The `message` attribute if present, otherwise the body of `<skipped>`.
+    pub fn reason(&self) -> Option<&str> {
+        self.message
+            .as_deref()
+            .or(self.details.as_deref())
+            .map(str::trim)
+    }
+}
+
+/// A skipped test case along with the name of the suite enclosing it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedTest<'a> {
+    pub suite: &'a str,
+    pub testcase: &'a TestCase,
+}
 
 #[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
 pub struct Summary {
@@ -181,12 +420,11 @@ pub struct Summary {
     pub failures: usize,
     pub errors: usize,
     pub skipped: usize,
+    #[serde(default)]
+    pub flaky: usize,
 }
 
 impl Summary {
-    pub fn is_successful(&self) -> bool {
-        self.failures == 0 && self.errors == 0
-    }
     fn zero() -> Self {
         Summary {
             time: Duration::zero(),
@@ -194,6 +432,7 @@ impl Summary {
             failures: 0,
             errors: 0,
             skipped: 0,
+            flaky: 0,
         }
     }
 }
@@ -205,39 +444,101 @@ impl AddAssign<&Summary> for &mut Summary {
         self.errors += rhs.errors;
         self.failures += rhs.failures;
         self.skipped += rhs.skipped;
+        self.flaky += rhs.flaky;
     }
 }
 
+impl HasOutcome for Summary {
+    /// Failures take precedence over errors, and both over flaky tests. A
+    /// summary is deemed skipped only when all of its tests were.
+    fn outcome(&self) -> TestOutcome {
+        if self.failures > 0 {
+            TestOutcome::Failed
+        } else if self.errors > 0 {
+            TestOutcome::Errored
+        } else if self.flaky > 0 {
+            TestOutcome::Flaky
+        } else if self.tests > 0 && self.skipped == self.tests {
+            TestOutcome::Skipped
+        } else {
+            TestOutcome::Passed
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub enum TestOutcome {
-    Success,
-    Failure,
+    Passed,
+    Failed,
+    Errored,
     Skipped,
+    Flaky,
+}
+
+impl TestOutcome {
+    pub fn is_successful(&self) -> bool {
+        *self != TestOutcome::Failed && *self != TestOutcome::Errored
+    }
 }
 
 pub trait HasOutcome {
     fn outcome(&self) -> TestOutcome;
+
+    fn is_successful(&self) -> bool {
+        self.outcome().is_successful()
+    }
 }
 
+impl HasOutcome for TestSuite {
+    fn outcome(&self) -> TestOutcome {
+        self.summary().outcome()
+    }
+}
+
+/// Reads all the report files matching the configured pattern. Unless
+/// `strict` mode is enabled, files that cannot be parsed are returned as
+/// unreadable reports rather than failing the whole run.
 pub fn read_testsuites(
     project_dir: Option<PathBuf>,
     config: &Config,
-) -> anyhow::Result<(Vec<SuiteWithSummary>, Summary)> {
-    let current_dir = env::current_dir()?;
-    let project_dir = project_dir.unwrap_or_else(|| current_dir);
+) -> anyhow::Result<(Vec<SuiteWithSummary>, Vec<UnreadableReport>, Summary)> {
+    let project_dir = match project_dir {
+        Some(project_dir) => project_dir,
+        None => env::current_dir()?,
+    };
     let display_progress = atty::is(atty::Stream::Stdout);
     let mut summary = Summary::zero();
 
     let testsuite_reader = TestSuiteReader::from_basedir(
         project_dir,
-        &config.junit.report_dir_pattern,
+        &config.junit.all_inputs(),
         &mut summary,
         display_progress,
     )?;
-    let test_suites: Vec<SuiteWithSummary> = testsuite_reader.all_suites();
-    Ok((test_suites, summary))
+    let (mut test_suites, unreadable_reports) = testsuite_reader.all_suites();
+    if config.junit.strict && !unreadable_reports.is_empty() {
+        let errors: Vec<String> = unreadable_reports
+            .iter()
+            .map(|r| format!("{}: {}", r.path.display(), r.error))
+            .collect();
+        bail!(
+            "{} report files could not be read:\n{}",
+            unreadable_reports.len(),
+            errors.join("\n")
+        );
+    }
+    if config.junit.flatten_suites {
+        test_suites = test_suites
+            .into_iter()
+            .flat_map(|s| s.value.flatten())
+            .map(|s| s.with_summary())
+            .collect();
+    }
+    Ok((test_suites, unreadable_reports, summary))
 }
 
-pub fn sort_testsuites(suites: &mut Vec<SuiteWithSummary>, sorting: &ReportSorting) {
+pub fn sort_testsuites(suites: &mut [SuiteWithSummary], sorting: &ReportSorting) {
     let ReportSorting::Time(order) = sorting;
     suites.sort_by(|a, b| {
         if *order == SortingOrder::Asc {
@@ -246,6 +547,18 @@ pub fn sort_testsuites(suites: &mut Vec<SuiteWithSummary>, sorting: &ReportSorti
             b.summary.time.cmp(&a.summary.time)
         }
     });
+    for suite in suites.iter_mut() {
+        suite.value.sort_suites(sorting);
+    }
+}
+
+/// A report file which could not be parsed. Each of these is counted as an
+/// error in the report summary.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnreadableReport {
+    pub path: PathBuf,
+    pub error: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -253,6 +566,7 @@ pub fn sort_testsuites(suites: &mut Vec<SuiteWithSummary>, sorting: &ReportSorti
 pub struct FullReport {
     pub all_suites: Vec<SuiteWithSummary>,
     pub failed: Vec<FailedSuiteWithSummary>,
+    pub unreadable_reports: Vec<UnreadableReport>,
     pub summary: Summary,
     pub github_event: Option<GithubEvent>,
 }
@@ -260,17 +574,19 @@ pub struct FullReport {
 impl FullReport {
     pub fn new(
         all_suites: Vec<SuiteWithSummary>,
+        unreadable_reports: Vec<UnreadableReport>,
         summary: Summary,
         github_event: Option<GithubEvent>,
     ) -> FullReport {
         let failed: Vec<SummaryWith<FailedTestSuite>> = all_suites
             .iter()
-            .filter_map(|s| s.value.as_failed())
+            .flat_map(|s| s.value.all_failed())
             .collect();
 
         FullReport {
             all_suites,
             failed,
+            unreadable_reports,
             summary,
             github_event,
         }
@@ -280,18 +596,52 @@ impl FullReport {
         sort_testsuites(&mut self.all_suites, sorting);
     }
 
-    pub fn is_successful(&self) -> bool {
-        self.failed.len() == 0
+    /// The files captured while running the tests, whatever their outcome.
+    pub fn attachments(&self) -> impl Iterator<Item = &Attachment> {
+        self.all_suites
+            .iter()
+            .flat_map(|s| s.value.all_attachments())
+    }
+
+    /// When the earliest suite started. Suites without a timestamp are
+    /// assumed to have just finished running, one after the other.
+    pub fn started_at(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        self.all_suites
+            .iter()
+            .filter_map(|s| s.value.metadata.timestamp)
+            .min()
+            .unwrap_or_else(|| now - self.summary.time)
+    }
+
+    /// Groups all the skipped tests by their skip reason, tests skipped
+    /// without giving one coming first.
+    pub fn skipped_by_reason(&self) -> BTreeMap<Option<&str>, Vec<SkippedTest<'_>>> {
+        let mut by_reason: BTreeMap<Option<&str>, Vec<SkippedTest>> = BTreeMap::new();
+        for skipped in self.all_suites.iter().flat_map(|s| s.value.all_skipped()) {
+            let reason = skipped
+                .testcase
+                .skipped
+                .as_ref()
+                .and_then(TestSkipped::reason)
+                .filter(|r| !r.is_empty());
+            by_reason.entry(reason).or_default().push(skipped);
+        }
+        by_reason
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+impl HasOutcome for FullReport {
+    fn outcome(&self) -> TestOutcome {
+        self.summary.outcome()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TestFailure {
     pub message: Option<String>,
     #[serde(rename = "type")]
     pub classname: String,
-    #[serde(rename(deserialize = "$value"))]
     pub stack_trace: String,
 }
 
@@ -302,31 +652,64 @@ pub struct FailedTestSuite {
     #[serde(skip_serializing)]
     pub time: Duration,
     pub failed_testcases: Vec<FailedTestCase>,
+    pub system_out: Option<String>,
+    pub system_err: Option<String>,
 }
 
 pub type FailedSuiteWithSummary = SummaryWith<FailedTestSuite>;
 
-#[derive(Debug, PartialEq, Clone, Deserialize)]
-struct TestSuites {
-    #[serde(rename = "testsuite", default)]
-    pub testsuites: Vec<TestSuite>,
+/// The formats report files can be read from.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Junit,
+    Tap,
+    Libtest,
+    Go,
+    #[serde(alias = "vitest")]
+    Jest,
+    Playwright,
+    Cucumber,
+    Trx,
+    Nunit,
+    Xunit,
+    Ctrf,
+    Allure,
+    Subunit,
 }
 
-fn read_suites<R: io::Read>(mut input: R) -> Result<Vec<SuiteWithSummary>> {
-    let mut buf = String::new();
+/// How report files are laid out under the directories matching an input.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportLayout {
+    /// Each matching file is a report
+    #[default]
+    Files,
+    /// Matching directories are `bazel-testlogs` directories, whose reports
+    /// are grouped by test target
+    Bazel,
+}
 
-    input.read_to_string(&mut buf)?;
-    if let Ok(suite) = serde_xml_rs::from_str::<TestSuite>(&buf) {
-        Ok(vec![suite.with_summary()])
+fn non_blank(s: String) -> Option<String> {
+    if s.trim().is_empty() {
+        None
     } else {
-        let TestSuites { testsuites } = serde_xml_rs::from_str(&buf)?;
-        Ok(testsuites
-            .into_iter()
-            .map(|suite| suite.with_summary())
-            .collect())
+        Some(s)
     }
 }
 
+pub(crate) fn read_suites<R, F>(input: R, on_testcase: F) -> Result<Vec<SuiteWithSummary>>
+where
+    R: io::Read,
+    F: FnMut(&TestCase),
+{
+    let suites = SuiteParser::new(input, on_testcase).parse()?;
+    Ok(suites
+        .into_iter()
+        .map(|suite| suite.with_summary())
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     extern crate pretty_assertions;
@@ -334,8 +717,9 @@ mod tests {
 
     use super::*;
 
+    use crate::config::ReportInput;
+    use chrono::TimeZone;
     use pretty_assertions::assert_eq;
-    use serde_xml_rs::from_reader;
     use std::{env, path::Path};
     use uuid::Uuid;
 
@@ -377,14 +761,68 @@ com.example
 
                                                </testcase><testcase classname="com.example.LiveTopicCounterTest" name="TopicCounter should count a partitioned topic" time="0.461">
                                                  <failure message="100 did not equal 101" type="org.scalatest.exceptions.TestFailedException">stack-trace...</failure>
+                                                 <system-out><![CDATA[connecting to localhost:9092]]></system-out>
                                                </testcase>
                      <system-out><![CDATA[]]></system-out>
                      <system-err><![CDATA[]]></system-err>
          </testsuite>
          "##;
 
+    const METADATA_TESTSUITE_XML: &str = r##"
+<testsuite hostname="ci-runner-3" id="0" package="com.example" file="src/test/scala/com/example/LiveTopicCounterTest.scala" name="com.example.LiveTopicCounterTest" tests="1" errors="0" failures="0" skipped="0" time="0.079" timestamp="2020-06-07T14:18:12+02:00">
+                     <properties>
+                       <property name="java.version" value="11.0.7"/>
+                       <property name="os.name" value="Linux"/>
+                     </properties>
+                     <testcase classname="com.example.LiveTopicCounterTest" name="LiveTopicCounter should raise an error when the supplied topic does not exist" time="0.079">
+                     </testcase>
+         </testsuite>
+         "##;
+
+    const NESTED_TESTSUITE_XML: &str = r##"
+<testsuites>
+  <testsuite name="root" tests="3" failures="1" time="0.3">
+    <testsuite name="root.a" tests="2" failures="1" time="0.2">
+      <testcase classname="root.a" name="passes" time="0.1"></testcase>
+      <testcase classname="root.a" name="fails" time="0.1">
+        <failure message="boom" type="AssertionError">stack-trace...</failure>
+      </testcase>
+    </testsuite>
+    <testsuite name="root.b" tests="1" failures="0" time="0.1">
+      <testsuite name="root.b.c" tests="1" failures="0" time="0.1">
+        <testcase classname="root.b.c" name="skips" time="0.0"><skipped/></testcase>
+      </testsuite>
+    </testsuite>
+  </testsuite>
+</testsuites>
+         "##;
+
+    const SUREFIRE_RERUN_TESTSUITE_XML: &str = r##"
+<testsuite name="com.example.KafkaConsumerIT" tests="2" errors="0" failures="1" skipped="0" flakes="1" time="4.2">
+  <testcase name="consumesRecords" classname="com.example.KafkaConsumerIT" time="1.1">
+    <flakyFailure message="timed out after 1000ms" type="java.util.concurrent.TimeoutException">
+      <stackTrace>java.util.concurrent.TimeoutException: timed out after 1000ms</stackTrace>
+      <system-out>polling...</system-out>
+    </flakyFailure>
+  </testcase>
+  <testcase name="commitsOffsets" classname="com.example.KafkaConsumerIT" time="3.1">
+    <failure message="expected 10 but was 9" type="java.lang.AssertionError">java.lang.AssertionError: expected 10 but was 9</failure>
+    <rerunFailure message="expected 10 but was 8" type="java.lang.AssertionError">
+      <stackTrace>java.lang.AssertionError: expected 10 but was 8</stackTrace>
+    </rerunFailure>
+    <rerunFailure message="expected 10 but was 7" type="java.lang.AssertionError">
+      <stackTrace>java.lang.AssertionError: expected 10 but was 7</stackTrace>
+    </rerunFailure>
+  </testcase>
+</testsuite>
+         "##;
+
+    fn parse_suite(xml: &str) -> TestSuite {
+        read_suites(xml.as_bytes(), |_| ()).unwrap().remove(0).value
+    }
+
     fn read_failed_testsuite<R: io::Read>(input: R) -> Option<SummaryWith<FailedTestSuite>> {
-        read_suites(input)
+        read_suites(input, |_| ())
             .unwrap()
             .first()
             .and_then(|ws| ws.value.clone().as_failed())
@@ -392,20 +830,25 @@ com.example
 
     #[test]
     fn parse_testsuite() {
-        let summary: TestSuite = from_reader(SUCCESS_TESTSUITE_XML.as_bytes()).unwrap();
+        let summary = parse_suite(SUCCESS_TESTSUITE_XML);
         let expected = TestSuite {
             name: "com.example.LiveTopicCounterTest".to_owned(),
-            time: Duration::nanoseconds(137000064) + Duration::seconds(2), //2.137,
+            time: Duration::milliseconds(2137),
             testcases: vec![
                 TestCase {
                 name:
                     "LiveTopicCounter should raise an error when the supplied topic does not exist"
                         .to_owned(),
                 classname: "com.example.LiveTopicCounterTest".to_owned(),
-                time: Duration::zero() + Duration::milliseconds(079),
+                time: Duration::zero() + Duration::milliseconds(79),
                 failure: None,
                 error: None,
                 skipped: None,
+                system_out: None,
+                system_err: None,
+                attempts: vec![],
+                attachments: vec![],
+                location: None,
             },
                 TestCase {
                 name:
@@ -414,18 +857,273 @@ com.example
                 time: Duration::zero() + Duration::milliseconds(1),
                 failure: None,
                 error: None,
-                skipped: Some(TestSkipped{}),
+                skipped: Some(TestSkipped::default()),
+                system_out: None,
+                system_err: None,
+                attempts: vec![],
+                attachments: vec![],
+                location: None,
             },
 
             ],
+            system_out: None,
+            system_err: None,
+            metadata: SuiteMetadata {
+                hostname: Some("lenstop".to_owned()),
+                timestamp: Some(Utc.ymd(2020, 6, 7).and_hms(14, 18, 12)),
+                ..SuiteMetadata::default()
+            },
+            testsuites: vec![],
         };
         assert_eq!(summary, expected);
     }
 
+    #[test]
+    fn parse_testsuite_metadata() {
+        let suite = parse_suite(METADATA_TESTSUITE_XML);
+        let expected = SuiteMetadata {
+            hostname: Some("ci-runner-3".to_owned()),
+            timestamp: Some(Utc.ymd(2020, 6, 7).and_hms(12, 18, 12)),
+            package: Some("com.example".to_owned()),
+            id: Some("0".to_owned()),
+            file: Some("src/test/scala/com/example/LiveTopicCounterTest.scala".to_owned()),
+            properties: vec![
+                Property {
+                    name: "java.version".to_owned(),
+                    value: Some("11.0.7".to_owned()),
+                },
+                Property {
+                    name: "os.name".to_owned(),
+                    value: Some("Linux".to_owned()),
+                },
+            ],
+        };
+        assert_eq!(suite.metadata, expected);
+    }
+
+    #[test]
+    fn ignore_unparseable_timestamps() {
+        let xml = SUCCESS_TESTSUITE_XML.replace("2020-06-07T14:18:12", "yesterday");
+        let suite = parse_suite(&xml);
+        assert_eq!(suite.metadata.timestamp, None);
+        assert_eq!(suite.testcases.len(), 2);
+    }
+
+    #[test]
+    fn parse_times_with_commas() {
+        assert_eq!(
+            parser::seconds("1,234.5").unwrap(),
+            Duration::milliseconds(1_234_500)
+        );
+        assert_eq!(parser::seconds("0,5").unwrap(), Duration::milliseconds(500));
+        assert_eq!(
+            parser::seconds("2.137").unwrap(),
+            Duration::milliseconds(2137)
+        );
+        assert_eq!(
+            parser::seconds("1,234,567").unwrap(),
+            Duration::seconds(1_234_567)
+        );
+        assert!(parser::seconds("1,2,3").is_err());
+    }
+
+    #[test]
+    fn parse_nested_testsuites() {
+        let suites = read_suites(NESTED_TESTSUITE_XML.as_bytes(), |_| ()).unwrap();
+        assert_eq!(suites.len(), 1);
+
+        let root = &suites[0];
+        assert_eq!(root.summary.tests, 3);
+        assert_eq!(root.summary.failures, 1);
+        assert_eq!(root.summary.skipped, 1);
+        assert_eq!(root.value.testsuites.len(), 2);
+        assert_eq!(root.value.testsuites[1].testsuites[0].name, "root.b.c");
+
+        let failed = root.value.all_failed();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].value.name, "root.a");
+    }
+
+    #[test]
+    fn flatten_nested_testsuites() {
+        let mut suites = read_suites(NESTED_TESTSUITE_XML.as_bytes(), |_| ()).unwrap();
+        let names: Vec<String> = suites
+            .remove(0)
+            .value
+            .flatten()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, vec!["root.a", "root.b.c"]);
+    }
+
+    #[test]
+    fn parse_testsuite_notifies_testcases() {
+        let mut summary = Summary::zero();
+        let mut acc = &mut summary;
+        read_suites(FAILED_TESTSUITE_XML.as_bytes(), |testcase| {
+            acc += &testcase.summary();
+        })
+        .unwrap();
+
+        assert_eq!(summary.tests, 5);
+        assert_eq!(summary.failures, 1);
+    }
+
+    #[test]
+    fn parse_unexpected_root_element() {
+        let result = read_suites("<html><body/></html>".as_bytes(), |_| ());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_surefire_reruns() {
+        let suite = parse_suite(SUREFIRE_RERUN_TESTSUITE_XML);
+        let summary = suite.summary();
+        assert_eq!(summary.tests, 2);
+        assert_eq!(summary.failures, 1);
+        assert_eq!(summary.flaky, 1);
+
+        let flaky = &suite.testcases[0];
+        assert!(flaky.is_flaky());
+        assert_eq!(
+            flaky.attempts,
+            vec![TestAttempt {
+                kind: AttemptKind::FlakyFailure,
+                failure: TestFailure {
+                    message: Some("timed out after 1000ms".to_owned()),
+                    classname: "java.util.concurrent.TimeoutException".to_owned(),
+                    stack_trace: "java.util.concurrent.TimeoutException: timed out after 1000ms"
+                        .to_owned(),
+                },
+                system_out: Some("polling...".to_owned()),
+                system_err: None,
+            }]
+        );
+
+        let failed = &suite.testcases[1];
+        assert!(!failed.is_flaky());
+        assert_eq!(failed.attempts.len(), 2);
+        assert_eq!(failed.attempts[1].kind, AttemptKind::RerunFailure);
+    }
+
+    #[test]
+    fn testcase_outcomes() {
+        let suite = parse_suite(
+            r##"
+<testsuite name="outcomes" time="0.4">
+  <testcase classname="outcomes" name="passed" time="0.1"></testcase>
+  <testcase classname="outcomes" name="failed" time="0.1"><failure type="AssertionError">...</failure></testcase>
+  <testcase classname="outcomes" name="errored" time="0.1"><error type="NullPointerException">...</error></testcase>
+  <testcase classname="outcomes" name="skipped" time="0.1"><skipped/></testcase>
+</testsuite>
+         "##,
+        );
+        let outcomes: Vec<TestOutcome> = suite.testcases.iter().map(|t| t.outcome()).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                TestOutcome::Passed,
+                TestOutcome::Failed,
+                TestOutcome::Errored,
+                TestOutcome::Skipped
+            ]
+        );
+        assert_eq!(suite.outcome(), TestOutcome::Failed);
+        assert!(!suite.with_summary().is_successful());
+    }
+
+    #[test]
+    fn parse_skip_reasons() {
+        let suite = parse_suite(
+            r##"
+<testsuite name="skips" time="0.3">
+  <testcase classname="skips" name="with message" time="0.1"><skipped message="flaky on CI, see #42"/></testcase>
+  <testcase classname="skips" name="with body" time="0.1"><skipped><![CDATA[
+    disabled until the new API ships
+  ]]></skipped></testcase>
+  <testcase classname="skips" name="without reason" time="0.1"><skipped/></testcase>
+</testsuite>
+         "##,
+        );
+        let reasons: Vec<Option<&str>> = suite
+            .testcases
+            .iter()
+            .map(|t| t.skipped.as_ref().and_then(TestSkipped::reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                Some("flaky on CI, see #42"),
+                Some("disabled until the new API ships"),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn group_skipped_by_reason() {
+        let suites = read_suites(
+            r##"
+<testsuite name="outer" time="0.3">
+  <testcase classname="outer" name="a" time="0.1"><skipped message="slow"/></testcase>
+  <testcase classname="outer" name="b" time="0.1"></testcase>
+  <testsuite name="inner" time="0.2">
+    <testcase classname="inner" name="c" time="0.1"><skipped message="slow"/></testcase>
+    <testcase classname="inner" name="d" time="0.1"><skipped/></testcase>
+  </testsuite>
+</testsuite>
+         "##
+            .as_bytes(),
+            |_| (),
+        )
+        .unwrap();
+        let report = FullReport::new(suites, vec![], Summary::zero(), None);
+        let grouped: Vec<(Option<&str>, Vec<String>)> = report
+            .skipped_by_reason()
+            .into_iter()
+            .map(|(reason, tests)| {
+                let tests = tests
+                    .iter()
+                    .map(|t| format!("{}/{}", t.suite, t.testcase.name))
+                    .collect();
+                (reason, tests)
+            })
+            .collect();
+        assert_eq!(
+            grouped,
+            vec![
+                (None, vec!["inner/d".to_owned()]),
+                (
+                    Some("slow"),
+                    vec!["outer/a".to_owned(), "inner/c".to_owned()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn summary_outcome() {
+        let mut summary = Summary::zero();
+        summary.tests = 2;
+        summary.skipped = 2;
+        assert_eq!(summary.outcome(), TestOutcome::Skipped);
+
+        summary.skipped = 1;
+        summary.flaky = 1;
+        assert_eq!(summary.outcome(), TestOutcome::Flaky);
+        assert!(summary.is_successful());
+
+        summary.flaky = 0;
+        summary.errors = 1;
+        assert_eq!(summary.outcome(), TestOutcome::Errored);
+        assert!(!summary.is_successful());
+    }
+
     #[test]
     fn parse_testsuite_wrapped() {
-        let suites1 = read_suites(SUCCESS_TESTSUITE_XML.as_bytes()).unwrap();
-        let suites2 = read_suites(SUCCESS_TESTSUITE_WRAPPED.as_bytes()).unwrap();
+        let suites1 = read_suites(SUCCESS_TESTSUITE_XML.as_bytes(), |_| ()).unwrap();
+        let suites2 = read_suites(SUCCESS_TESTSUITE_WRAPPED.as_bytes(), |_| ()).unwrap();
         assert!(suites1.len() == 1);
         assert_eq!(suites1, suites2);
     }
@@ -441,7 +1139,7 @@ com.example
                     "LiveTopicCounter should raise an error when the supplied topic does not exist"
                         .to_owned(),
                 classname: "com.example.LiveTopicCounterTest".to_owned(),
-                time: Duration::zero() + Duration::milliseconds(079),
+                time: Duration::zero() + Duration::milliseconds(79),
                 error: None,
             failure: Some(TestFailure {
                 message: Some("100 did not equal 101".to_owned()),
@@ -450,6 +1148,11 @@ com.example
             }),
 
                 skipped: None,
+                system_out: None,
+                system_err: None,
+                attempts: vec![],
+                attachments: vec![],
+                location: None,
             },
                 TestCase {
                 name:
@@ -458,18 +1161,50 @@ com.example
                 time: Duration::zero() + Duration::milliseconds(1),
                 failure: None,
                 error: None,
-                skipped: Some(TestSkipped{}),
+                skipped: Some(TestSkipped {
+                    message: Some("not implemented yet".to_owned()),
+                    details: None,
+                }),
+                system_out: None,
+                system_err: None,
+                attempts: vec![],
+                attachments: vec![],
+                location: None,
             },
 
             ],
+            system_out: Some("suite output".to_owned()),
+            system_err: None,
+            metadata: SuiteMetadata {
+                hostname: Some("lenstop".to_owned()),
+                timestamp: Some(Utc.ymd(2020, 6, 7).and_hms(14, 18, 12)),
+                properties: vec![Property {
+                    name: "java.version".to_owned(),
+                    value: Some("11.0.7".to_owned()),
+                }],
+                ..SuiteMetadata::default()
+            },
+            testsuites: vec![],
         };
         let expected = serde_json::json!({
           "tests":2,
           "errors":0,
           "failures":1,
           "skipped":1,
+          "flaky":0,
           "time":250,
           "name":"com.example.LiveTopicCounterTest",
+          "systemOut":"suite output",
+          "systemErr":null,
+          "metadata": {
+            "hostname": "lenstop",
+            "timestamp": "2020-06-07T14:18:12Z",
+            "package": null,
+            "id": null,
+            "file": null,
+            "properties": [{"name": "java.version", "value": "11.0.7"}]
+          },
+          "testsuites": [],
           "testcase":[{
             "classname":"com.example.LiveTopicCounterTest",
             "failure":{
@@ -479,13 +1214,21 @@ com.example
             },
             "error": null,
             "name":"LiveTopicCounter should raise an error when the supplied topic does not exist",
-            "skipped":false,
+            "skipped":null,
+            "systemOut":null,
+            "systemErr":null,
+            "attempts":[],
+            "attachments":[],
             "time":79},
             {"classname":"com.example.LiveTopicCounterTest",
              "failure":null,
              "error":null,
              "name":"LiveTopicCounter should skip this test",
-             "skipped":true,
+             "skipped":{"message":"not implemented yet","details":null},
+             "systemOut":null,
+             "systemErr":null,
+             "attempts":[],
+             "attachments":[],
              "time":1
             }]
         });
@@ -495,7 +1238,7 @@ com.example
 
     #[test]
     fn can_parse_failed_testsuite() {
-        let suite: TestSuite = from_reader(FAILED_TESTSUITE_XML.as_bytes()).unwrap();
+        let suite = parse_suite(FAILED_TESTSUITE_XML);
         let failed = FailedTestCase {
             name: "TopicCounter should count a partitioned topic".to_owned(),
             classname: "com.example.LiveTopicCounterTest".to_owned(),
@@ -505,6 +1248,11 @@ com.example
                 classname: "org.scalatest.exceptions.TestFailedException".to_owned(),
                 stack_trace: "stack-trace...".to_owned(),
             },
+            system_out: Some("connecting to localhost:9092".to_owned()),
+            system_err: None,
+            attempts: vec![],
+            attachments: vec![],
+            location: None,
         };
         let expected = SummaryWith {
             summary: Summary {
@@ -512,12 +1260,15 @@ com.example
                 failures: 1,
                 errors: 0,
                 skipped: 0,
-                time: Duration::nanoseconds(137000064) + Duration::seconds(2), //2.137,
+                flaky: 0,
+                time: Duration::milliseconds(2137),
             },
             value: FailedTestSuite {
                 name: "com.example.LiveTopicCounterTest".to_owned(),
-                time: Duration::nanoseconds(137000064) + Duration::seconds(2), //2.137,
+                time: Duration::milliseconds(2137),
                 failed_testcases: vec![failed],
+                system_out: None,
+                system_err: None,
             },
         };
         let failed = suite.as_failed().unwrap();
@@ -537,7 +1288,7 @@ com.example
     }
     #[test]
     fn failed_testsuite() {
-        let report_dir_pattern = "**/testreports/*";
+        let inputs = [ReportInput::new("**/testreports/*", None)];
         let mut dir = env::temp_dir();
         let mut failed_suites: Vec<FailedTestSuite> = Vec::new();
 
@@ -547,11 +1298,10 @@ com.example
         create_report_dir(base_dir, "testreports", 3, 3, 7).expect("Couldn't setup test data");
         let mut summary = Summary::zero();
 
-        let reader =
-            TestSuiteReader::from_basedir(base_dir, report_dir_pattern, &mut summary, false)
-                .expect("Couldn't initialise the testsuite reader");
+        let reader = TestSuiteReader::from_basedir(base_dir, &inputs, &mut summary, false)
+            .expect("Couldn't initialise the testsuite reader");
 
-        for test_suite in reader.all_suites() {
+        for test_suite in reader.all_suites().0 {
             if let Some(with_summary) = test_suite.value.as_failed() {
                 failed_suites.push(with_summary.value);
             }
@@ -559,6 +1309,156 @@ com.example
         assert_eq!(failed_suites.len(), 3);
     }
 
+    #[test]
+    fn unreadable_reports() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("cikit/testrun-{}", Uuid::new_v4()));
+        let base_dir = dir.as_path();
+
+        create_report_dir(base_dir, "testreports", 1, 0, 2).expect("Couldn't setup test data");
+        let truncated = &SUCCESS_TESTSUITE_XML[..SUCCESS_TESTSUITE_XML.len() / 2];
+        std::fs::write(base_dir.join("0/testreports/truncated.xml"), truncated)
+            .expect("Couldn't setup test data");
+        let mut summary = Summary::zero();
+
+        let inputs = [ReportInput::new("**/testreports/*", None)];
+        let reader = TestSuiteReader::from_basedir(base_dir, &inputs, &mut summary, false)
+            .expect("Couldn't initialise the testsuite reader");
+        let (suites, unreadable) = reader.all_suites();
+
+        assert_eq!(suites.len(), 2);
+        assert_eq!(unreadable.len(), 1);
+        assert!(unreadable[0].path.ends_with("truncated.xml"));
+        assert!(unreadable[0]
+            .error
+            .contains("Couldn't parse JUnit XML report"));
+        assert_eq!(summary.tests, 4);
+        assert_eq!(summary.errors, 1);
+        assert_eq!(summary.outcome(), TestOutcome::Errored);
+    }
+
+    #[test]
+    fn sniff_trx_reports() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("cikit/testrun-{}", Uuid::new_v4()));
+        let base_dir = dir.as_path();
+
+        create_report_dir(base_dir, "testreports", 1, 0, 1).expect("Couldn't setup test data");
+        let trx = r##"<TestRun name="run" xmlns="http://microsoft.com/schemas/VisualStudio/TeamTest/2010">
+  <Results>
+    <UnitTestResult testId="t1" testName="Passes" duration="00:00:00.1" outcome="Passed" />
+  </Results>
+</TestRun>"##;
+        for name in &["0/testreports/run.trx", "0/testreports/run-trx.xml"] {
+            std::fs::write(base_dir.join(name), trx).expect("Couldn't setup test data");
+        }
+        // in none of the supported formats, so skipped rather than unreadable
+        std::fs::write(base_dir.join("0/testreports/package.json"), "{}")
+            .expect("Couldn't setup test data");
+        let mut summary = Summary::zero();
+
+        let inputs = [
+            ReportInput::new("**/testreports/*", None),
+            ReportInput::new("**/testreports/*.trx", Some(ReportFormat::Trx)),
+        ];
+        let reader = TestSuiteReader::from_basedir(base_dir, &inputs, &mut summary, false)
+            .expect("Couldn't initialise the testsuite reader");
+        let (suites, unreadable) = reader.all_suites();
+
+        assert_eq!(suites.len(), 3);
+        assert!(unreadable.is_empty());
+        assert_eq!(summary.tests, 4);
+    }
+
+    #[test]
+    fn detect_json_and_tap_reports() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("cikit/testrun-{}", Uuid::new_v4()));
+        let base_dir = dir.as_path();
+
+        let reports_dir = base_dir.join("testreports");
+        std::fs::create_dir_all(&reports_dir).expect("Couldn't setup test data");
+        let go = r##"{"Action":"run","Package":"example.com/shop","Test":"TestAdd"}
+{"Action":"pass","Package":"example.com/shop","Test":"TestAdd","Elapsed":0.01}
+{"Action":"pass","Package":"example.com/shop","Elapsed":0.02}
+"##;
+        std::fs::write(reports_dir.join("go-test.json"), go).expect("Couldn't setup test data");
+        let tap = "TAP version 13\n1..2\nok 1 - adds\nnot ok 2 - removes\n";
+        std::fs::write(reports_dir.join("cart.tap"), tap).expect("Couldn't setup test data");
+        std::fs::write(reports_dir.join("build.log"), "Compiling cikit\n")
+            .expect("Couldn't setup test data");
+        let mut summary = Summary::zero();
+
+        let inputs = [ReportInput::new("testreports/*", None)];
+        let reader = TestSuiteReader::from_basedir(base_dir, &inputs, &mut summary, false)
+            .expect("Couldn't initialise the testsuite reader");
+        let (suites, unreadable) = reader.all_suites();
+
+        assert_eq!(suites.len(), 2);
+        assert!(unreadable.is_empty());
+        assert_eq!(summary.tests, 3);
+        assert_eq!(summary.failures, 1);
+    }
+
+    #[test]
+    fn detect_allure_results_dirs() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("cikit/testrun-{}", Uuid::new_v4()));
+        let base_dir = dir.as_path();
+
+        create_report_dir(base_dir, "testreports", 1, 0, 1).expect("Couldn't setup test data");
+        let results_dir = base_dir.join("0/testreports/allure-results");
+        std::fs::create_dir_all(&results_dir).expect("Couldn't setup test data");
+        let result = r##"{"uuid": "a1", "name": "adds items", "status": "passed"}"##;
+        std::fs::write(results_dir.join("a1-result.json"), result)
+            .expect("Couldn't setup test data");
+        std::fs::write(results_dir.join("a1-attachment.txt"), "adding items")
+            .expect("Couldn't setup test data");
+        let mut summary = Summary::zero();
+
+        let inputs = [ReportInput::new("**/testreports/**/*", None)];
+        let reader = TestSuiteReader::from_basedir(base_dir, &inputs, &mut summary, false)
+            .expect("Couldn't initialise the testsuite reader");
+        let (suites, unreadable) = reader.all_suites();
+
+        assert_eq!(suites.len(), 2);
+        assert!(unreadable.is_empty());
+        assert_eq!(summary.tests, 3);
+    }
+
+    #[test]
+    fn read_bazel_targets() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("cikit/testrun-{}", Uuid::new_v4()));
+        let base_dir = dir.as_path();
+
+        for shard in 1..=2 {
+            let shard_dir =
+                base_dir.join(format!("bazel-testlogs/app/cart_test/shard_{}_of_2", shard));
+            std::fs::create_dir_all(&shard_dir).expect("Couldn't setup test data");
+            std::fs::write(shard_dir.join("test.xml"), SUCCESS_TESTSUITE_WRAPPED)
+                .expect("Couldn't setup test data");
+        }
+        let mut summary = Summary::zero();
+
+        let inputs = [
+            ReportInput::new("**/*.xml", None),
+            ReportInput {
+                layout: ReportLayout::Bazel,
+                ..ReportInput::new("bazel-testlogs", None)
+            },
+        ];
+        let reader = TestSuiteReader::from_basedir(base_dir, &inputs, &mut summary, false)
+            .expect("Couldn't initialise the testsuite reader");
+        let (suites, unreadable) = reader.all_suites();
+
+        assert!(unreadable.is_empty());
+        assert_eq!(suites.len(), 1);
+        assert_eq!(suites[0].value.name, "//app:cart_test");
+        assert_eq!(summary.tests, 2);
+        assert_eq!(summary.skipped, 1);
+    }
+
     fn create_report_dir(
         base_dir: &Path,
         report_dirname: &str,
@@ -573,18 +1473,18 @@ com.example
             reports_path.push(n.to_string())
         }
         reports_path.push(report_dirname);
-        std::fs::create_dir_all(reports_path.to_owned())?;
+        std::fs::create_dir_all(&reports_path)?;
 
         //create successfull report files
         for n in 0..successful {
-            let mut report_path = PathBuf::from(reports_path.to_owned());
+            let mut report_path = reports_path.to_owned();
             report_path.push(format!("{}.xml", n));
             std::fs::write(report_path, SUCCESS_TESTSUITE_XML)?;
         }
 
         //create failed report files
         for n in successful..(failed + successful) {
-            let mut report_path = PathBuf::from(reports_path.to_owned());
+            let mut report_path = reports_path.to_owned();
             report_path.push(format!("{}.xml", n));
             std::fs::write(report_path, FAILED_TESTSUITE_XML)?;
         }
@@ -596,7 +1496,25 @@ com.example
 pub mod display;
 mod fs;
 
+pub(crate) mod allure;
+mod bazel;
 mod cli;
+mod ctrf;
+mod cucumber;
+#[cfg(test)]
+pub(crate) mod fixtures;
+mod formats;
+mod gotest;
+mod jest;
+mod libtest;
+mod location;
+mod nunit;
+mod parser;
+mod playwright;
 mod serdes;
+mod subunit;
+mod tap;
+mod trx;
+mod xunit;
 pub type ReportSorting = cli::ReportSorting;
 pub type SortingOrder = cli::SortingOrder;
@@ -0,0 +1,380 @@
+use super::{
+    non_blank,
+    parser::{attr, element_text, seconds, skip_element, xml_events},
+    Property, SuiteMetadata, SuiteWithSummary, TestCase, TestFailure, TestSkipped, TestSuite,
+};
+use anyhow::{anyhow, bail, Result};
+use chrono::Duration;
+use std::io::{self, BufReader};
+use xml::{
+    attribute::OwnedAttribute,
+    reader::{EventReader, XmlEvent},
+};
+
+/// A pull based NUnit 3 XML parser, mapping each `<test-suite>` (assembly,
+/// namespace, fixture or parameterized method) onto a suite nested within
+/// the enclosing one.
+struct NunitParser<R: io::Read, F: FnMut(&TestCase)> {
+    events: EventReader<BufReader<R>>,
+    on_testcase: F,
+}
+
+/// Reads an NUnit 3 `<test-run>` result file.
+pub(super) fn read_suites<R, F>(input: R, on_testcase: F) -> Result<Vec<SuiteWithSummary>>
+where
+    R: io::Read,
+    F: FnMut(&TestCase),
+{
+    let suites = NunitParser {
+        events: xml_events(input),
+        on_testcase,
+    }
+    .parse()?;
+    Ok(suites.into_iter().map(|s| s.with_summary()).collect())
+}
+
+impl<R: io::Read, F: FnMut(&TestCase)> NunitParser<R, F> {
+    fn parse(mut self) -> Result<Vec<TestSuite>> {
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement { name, .. } => {
+                    if name.local_name == "test-run" {
+                        break;
+                    }
+                    bail!("Unexpected root element <{}>", name.local_name);
+                }
+                XmlEvent::EndDocument => bail!("No <test-run> element found"),
+                _ => continue,
+            }
+        }
+
+        let mut suites = Vec::new();
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
+                    if name.local_name == "test-suite" {
+                        suites.push(self.test_suite(&attributes)?);
+                    } else {
+                        skip_element(&mut self.events)?;
+                    }
+                }
+                XmlEvent::EndElement { .. } => return Ok(suites),
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+    }
+
+    fn test_suite(&mut self, attributes: &[OwnedAttribute]) -> Result<TestSuite> {
+        let is_assembly = attr(attributes, "type").as_deref() == Some("Assembly");
+        let name = if is_assembly {
+            attr(attributes, "name")
+        } else {
+            attr(attributes, "fullname").or_else(|| attr(attributes, "name"))
+        };
+        let name = name.ok_or_else(|| anyhow!("Missing attribute `name` in <test-suite>"))?;
+        let mut suite = TestSuite {
+            time: duration_attr(attributes)?,
+            metadata: SuiteMetadata {
+                id: attr(attributes, "id"),
+                file: attr(attributes, "fullname").filter(|_| is_assembly),
+                ..SuiteMetadata::default()
+            },
+            ..TestSuite::new(name)
+        };
+
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement {
+                    name,
+                    attributes: child,
+                    ..
+                } => match name.local_name.as_str() {
+                    "test-case" => {
+                        let testcase = self.test_case(&child, &suite.name)?;
+                        (self.on_testcase)(&testcase);
+                        suite.testcases.push(testcase);
+                    }
+                    "test-suite" => suite.testsuites.push(self.test_suite(&child)?),
+                    "properties" => suite.metadata.properties = self.properties()?,
+                    "output" => suite.system_out = non_blank(element_text(&mut self.events)?),
+                    // failures of the tests within are already reported by
+                    // them, while those of a one time tear down are not
+                    "failure" => {
+                        let failure = self.failure()?;
+                        if attr(attributes, "site").as_deref() == Some("TearDown") {
+                            let testcase = TestCase {
+                                error: Some(failure),
+                                ..TestCase::new("OneTimeTearDown".to_owned(), suite.name.clone())
+                            };
+                            (self.on_testcase)(&testcase);
+                            suite.testcases.push(testcase);
+                        }
+                    }
+                    _ => skip_element(&mut self.events)?,
+                },
+                XmlEvent::EndElement { .. } => return Ok(suite),
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+    }
+
+    fn test_case(&mut self, attributes: &[OwnedAttribute], suite: &str) -> Result<TestCase> {
+        let name = attr(attributes, "name")
+            .ok_or_else(|| anyhow!("Missing attribute `name` in <test-case>"))?;
+        let classname = attr(attributes, "classname").unwrap_or_else(|| suite.to_owned());
+        let mut testcase = TestCase {
+            time: duration_attr(attributes)?,
+            ..TestCase::new(name, classname)
+        };
+        let mut failure: Option<TestFailure> = None;
+        let mut reason: Option<String> = None;
+
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement { name, .. } => match name.local_name.as_str() {
+                    "failure" => failure = Some(self.failure()?),
+                    "reason" => reason = self.failure()?.message,
+                    "output" => testcase.system_out = non_blank(element_text(&mut self.events)?),
+                    _ => skip_element(&mut self.events)?,
+                },
+                XmlEvent::EndElement { .. } => break,
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+
+        let label = attr(attributes, "label").unwrap_or_default();
+        match attr(attributes, "result").unwrap_or_default().as_str() {
+            "Failed" => {
+                let failure = failure.unwrap_or_else(|| TestFailure {
+                    message: non_blank(label.clone()),
+                    classname: String::new(),
+                    stack_trace: String::new(),
+                });
+                if label == "Error" || label == "Invalid" || label == "Cancelled" {
+                    testcase.error = Some(failure);
+                } else {
+                    testcase.failure = Some(failure);
+                }
+            }
+            "Skipped" | "Inconclusive" => {
+                testcase.skipped = Some(TestSkipped {
+                    message: reason,
+                    details: None,
+                })
+            }
+            _ => (),
+        }
+        Ok(testcase)
+    }
+
+    /// Reads either a `<failure>` or a `<reason>` element, both holding a
+    /// `<message>` and the former a `<stack-trace>` as well.
+    fn failure(&mut self) -> Result<TestFailure> {
+        let mut failure = TestFailure {
+            message: None,
+            classname: String::new(),
+            stack_trace: String::new(),
+        };
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement { name, .. } => match name.local_name.as_str() {
+                    "message" => {
+                        failure.message =
+                            non_blank(element_text(&mut self.events)?).map(|m| m.trim().to_owned())
+                    }
+                    "stack-trace" => {
+                        failure.stack_trace = element_text(&mut self.events)?.trim().to_owned()
+                    }
+                    _ => skip_element(&mut self.events)?,
+                },
+                XmlEvent::EndElement { .. } => return Ok(failure),
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+    }
+
+    fn properties(&mut self) -> Result<Vec<Property>> {
+        let mut properties = Vec::new();
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
+                    if name.local_name == "property" {
+                        properties.push(Property {
+                            name: attr(&attributes, "name").unwrap_or_default(),
+                            value: attr(&attributes, "value"),
+                        });
+                    }
+                    skip_element(&mut self.events)?;
+                }
+                XmlEvent::EndElement { .. } => return Ok(properties),
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+    }
+}
+
+/// NUnit reports durations in seconds through a `duration` attribute.
+fn duration_attr(attributes: &[OwnedAttribute]) -> Result<Duration> {
+    match attr(attributes, "duration") {
+        None => Ok(Duration::zero()),
+        Some(s) => seconds(&s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use crate::junit::{HasOutcome, TestOutcome};
+    use pretty_assertions::assert_eq;
+
+    const NUNIT_XML: &str = r##"<?xml version="1.0" encoding="utf-8" standalone="no"?>
+<test-run id="0" runstate="Runnable" testcasecount="5" result="Failed" total="5" passed="2" failed="2" skipped="1" duration="0.250" engine-version="3.12.0">
+  <command-line><![CDATA[nunit3-console Shop.Tests.dll]]></command-line>
+  <test-suite type="Assembly" id="0-1005" name="Shop.Tests.dll" fullname="/src/bin/Shop.Tests.dll" runstate="Runnable" testcasecount="5" result="Failed" site="Child" duration="0.250">
+    <environment framework-version="3.12.0.0" os-version="Linux" />
+    <properties>
+      <property name="_PID" value="4242" />
+    </properties>
+    <failure>
+      <message><![CDATA[One or more child tests had errors]]></message>
+    </failure>
+    <test-suite type="TestFixture" id="0-1000" name="CartTests" fullname="Shop.Tests.CartTests" classname="Shop.Tests.CartTests" runstate="Runnable" result="Failed" site="Child" duration="0.200">
+      <test-case id="0-1001" name="AddsItems" fullname="Shop.Tests.CartTests.AddsItems" methodname="AddsItems" classname="Shop.Tests.CartTests" runstate="Runnable" result="Passed" duration="0.012" asserts="1">
+        <output><![CDATA[adding 2 items]]></output>
+      </test-case>
+      <test-case id="0-1002" name="AppliesDiscounts" fullname="Shop.Tests.CartTests.AppliesDiscounts" methodname="AppliesDiscounts" classname="Shop.Tests.CartTests" runstate="Runnable" result="Failed" duration="0.150" asserts="1">
+        <failure>
+          <message><![CDATA[  Expected: 90
+  But was:  100
+]]></message>
+          <stack-trace><![CDATA[at Shop.Tests.CartTests.AppliesDiscounts() in /src/CartTests.cs:line 21
+]]></stack-trace>
+        </failure>
+        <assertions>
+          <assertion result="Failed" />
+        </assertions>
+      </test-case>
+      <test-case id="0-1003" name="ShipsAbroad" fullname="Shop.Tests.CartTests.ShipsAbroad" methodname="ShipsAbroad" classname="Shop.Tests.CartTests" runstate="Ignored" result="Skipped" label="Ignored" duration="0">
+        <properties>
+          <property name="_SKIPREASON" value="Shipping is disabled" />
+        </properties>
+        <reason>
+          <message><![CDATA[Shipping is disabled]]></message>
+        </reason>
+      </test-case>
+      <test-suite type="ParameterizedMethod" id="0-1006" name="Totals" fullname="Shop.Tests.CartTests.Totals" classname="Shop.Tests.CartTests" runstate="Runnable" result="Failed" duration="0.030">
+        <test-case id="0-1007" name="Totals(1)" fullname="Shop.Tests.CartTests.Totals(1)" classname="Shop.Tests.CartTests" result="Passed" duration="0.010" />
+        <test-case id="0-1008" name="Totals(2)" fullname="Shop.Tests.CartTests.Totals(2)" classname="Shop.Tests.CartTests" result="Failed" label="Error" duration="0.020">
+          <failure>
+            <message><![CDATA[System.NullReferenceException : Object reference not set to an instance of an object.]]></message>
+            <stack-trace><![CDATA[at Shop.Cart.Total() in /src/Cart.cs:line 8]]></stack-trace>
+          </failure>
+        </test-case>
+      </test-suite>
+    </test-suite>
+  </test-suite>
+</test-run>"##;
+
+    fn read(xml: &str) -> Vec<TestSuite> {
+        read_suites(xml.as_bytes(), |_| ())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.value)
+            .collect()
+    }
+
+    #[test]
+    fn parse_nested_suites() {
+        let suites = read(NUNIT_XML);
+        let assembly = &suites[0];
+        assert_eq!(assembly.name, "Shop.Tests.dll");
+        assert_eq!(
+            assembly.metadata.file,
+            Some("/src/bin/Shop.Tests.dll".to_owned())
+        );
+        assert_eq!(assembly.metadata.properties[0].name, "_PID");
+        assert!(assembly.testcases.is_empty());
+
+        let fixture = &assembly.testsuites[0];
+        assert_eq!(fixture.name, "Shop.Tests.CartTests");
+        assert_eq!(fixture.time, Duration::milliseconds(200));
+        assert_eq!(fixture.testsuites[0].name, "Shop.Tests.CartTests.Totals");
+        assert_eq!(assembly.summary().tests, 5);
+        assert_eq!(
+            fixture.testcases[0].system_out,
+            Some("adding 2 items".to_owned())
+        );
+    }
+
+    #[test]
+    fn parse_results() {
+        let suites = read(NUNIT_XML);
+        let fixture = &suites[0].testsuites[0];
+        let outcomes: Vec<(&str, TestOutcome)> = fixture
+            .testcases
+            .iter()
+            .chain(fixture.testsuites[0].testcases.iter())
+            .map(|t| (t.name.as_str(), t.outcome()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("AddsItems", TestOutcome::Passed),
+                ("AppliesDiscounts", TestOutcome::Failed),
+                ("ShipsAbroad", TestOutcome::Skipped),
+                ("Totals(1)", TestOutcome::Passed),
+                ("Totals(2)", TestOutcome::Errored),
+            ]
+        );
+
+        let failure = fixture.testcases[1].failure.as_ref().unwrap();
+        assert_eq!(
+            failure.message,
+            Some("Expected: 90\n  But was:  100".to_owned())
+        );
+        assert_eq!(
+            failure.stack_trace,
+            "at Shop.Tests.CartTests.AppliesDiscounts() in /src/CartTests.cs:line 21"
+        );
+        assert_eq!(
+            fixture.testcases[2]
+                .skipped
+                .as_ref()
+                .and_then(TestSkipped::reason),
+            Some("Shipping is disabled")
+        );
+    }
+
+    #[test]
+    fn one_time_tear_down_failure() {
+        let suites = read(
+            r##"<test-run>
+  <test-suite type="TestFixture" name="CartTests" fullname="Shop.Tests.CartTests" result="Failed" site="TearDown" duration="0.1">
+    <failure>
+      <message><![CDATA[TearDown : System.IO.IOException : disk full]]></message>
+    </failure>
+    <test-case name="AddsItems" classname="Shop.Tests.CartTests" result="Passed" duration="0.1" />
+  </test-suite>
+</test-run>"##,
+        );
+        let tear_down = &suites[0].testcases[0];
+        assert_eq!(tear_down.name, "OneTimeTearDown");
+        assert_eq!(tear_down.outcome(), TestOutcome::Errored);
+    }
+
+    #[test]
+    fn not_an_nunit_report() {
+        assert!(read_suites("<testsuites/>".as_bytes(), |_| ()).is_err());
+    }
+}
@@ -0,0 +1,378 @@
+use super::{
+    non_blank, AttemptKind, Property, SourceLocation, SuiteMetadata, TestAttempt, TestCase,
+    TestFailure, TestSkipped, TestSuite,
+};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use log::warn;
+use std::io::{self, BufReader};
+use xml::{
+    attribute::OwnedAttribute,
+    reader::{EventReader, ParserConfig, XmlEvent},
+};
+
+/// A pull based JUnit XML parser.
+///
+/// Rather than buffering the whole document, the parser walks the XML events
+/// once, building suites as their closing tag is reached and handing each
+/// parsed test case over to the supplied callback.
+pub(super) struct SuiteParser<R: io::Read, F: FnMut(&TestCase)> {
+    events: EventReader<BufReader<R>>,
+    on_testcase: F,
+}
+
+impl<R: io::Read, F: FnMut(&TestCase)> SuiteParser<R, F> {
+    pub fn new(input: R, on_testcase: F) -> Self {
+        let events = xml_events(input);
+
+        SuiteParser {
+            events,
+            on_testcase,
+        }
+    }
+
+    /// Detects the document root (either a single `<testsuite>` or a
+    /// `<testsuites>` wrapper) and parses all the suites found within it.
+    pub fn parse(mut self) -> Result<Vec<TestSuite>> {
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
+                    return match name.local_name.as_str() {
+                        "testsuite" => Ok(vec![self.testsuite(&attributes)?]),
+                        "testsuites" => self.testsuites(),
+                        other => Err(anyhow!("Unexpected root element <{}>", other)),
+                    }
+                }
+                XmlEvent::EndDocument => bail!("No <testsuite> element found"),
+                _ => continue,
+            }
+        }
+    }
+
+    fn testsuites(&mut self) -> Result<Vec<TestSuite>> {
+        let mut suites = Vec::new();
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
+                    if name.local_name == "testsuite" {
+                        suites.push(self.testsuite(&attributes)?);
+                    } else {
+                        self.skip()?;
+                    }
+                }
+                XmlEvent::EndElement { .. } => return Ok(suites),
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+    }
+
+    fn testsuite(&mut self, attributes: &[OwnedAttribute]) -> Result<TestSuite> {
+        let name = required_attr(attributes, "name", "testsuite")?;
+        let mut suite = TestSuite {
+            time: duration_attr(attributes)?,
+            metadata: SuiteMetadata {
+                hostname: attr(attributes, "hostname"),
+                timestamp: timestamp_attr(attributes),
+                package: attr(attributes, "package"),
+                id: attr(attributes, "id"),
+                file: attr(attributes, "file"),
+                properties: Vec::new(),
+            },
+            ..TestSuite::new(name)
+        };
+
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => match name.local_name.as_str() {
+                    "testcase" => {
+                        let testcase = self.testcase(&attributes)?;
+                        (self.on_testcase)(&testcase);
+                        suite.testcases.push(testcase);
+                    }
+                    "testsuite" => suite.testsuites.push(self.testsuite(&attributes)?),
+                    "properties" => suite.metadata.properties = self.properties()?,
+                    "system-out" => suite.system_out = non_blank(self.text()?),
+                    "system-err" => suite.system_err = non_blank(self.text()?),
+                    _ => self.skip()?,
+                },
+                XmlEvent::EndElement { .. } => return Ok(suite),
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+    }
+
+    fn testcase(&mut self, attributes: &[OwnedAttribute]) -> Result<TestCase> {
+        let name = required_attr(attributes, "name", "testcase")?;
+        let classname = attr(attributes, "classname").unwrap_or_default();
+        let mut testcase = TestCase {
+            time: duration_attr(attributes)?,
+            location: location_attrs(attributes),
+            ..TestCase::new(name, classname)
+        };
+
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => match name.local_name.as_str() {
+                    "failure" => testcase.failure = Some(self.failure(&attributes)?),
+                    "error" => testcase.error = Some(self.failure(&attributes)?),
+                    "flakyFailure" => testcase
+                        .attempts
+                        .push(self.attempt(AttemptKind::FlakyFailure, &attributes)?),
+                    "flakyError" => testcase
+                        .attempts
+                        .push(self.attempt(AttemptKind::FlakyError, &attributes)?),
+                    "rerunFailure" => testcase
+                        .attempts
+                        .push(self.attempt(AttemptKind::RerunFailure, &attributes)?),
+                    "rerunError" => testcase
+                        .attempts
+                        .push(self.attempt(AttemptKind::RerunError, &attributes)?),
+                    "skipped" => {
+                        testcase.skipped = Some(TestSkipped {
+                            message: attr(&attributes, "message").and_then(non_blank),
+                            details: non_blank(self.text()?).map(|s| s.trim().to_owned()),
+                        })
+                    }
+                    "system-out" => testcase.system_out = non_blank(self.text()?),
+                    "system-err" => testcase.system_err = non_blank(self.text()?),
+                    _ => self.skip()?,
+                },
+                XmlEvent::EndElement { .. } => return Ok(testcase),
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+    }
+
+    fn failure(&mut self, attributes: &[OwnedAttribute]) -> Result<TestFailure> {
+        Ok(TestFailure {
+            message: attr(attributes, "message"),
+            classname: attr(attributes, "type").unwrap_or_default(),
+            stack_trace: self.text()?.trim().to_owned(),
+        })
+    }
+
+    /// Surefire nests the stack trace of a re-run within a `<stackTrace>`
+    /// element, next to the output captured during that run.
+    fn attempt(&mut self, kind: AttemptKind, attributes: &[OwnedAttribute]) -> Result<TestAttempt> {
+        let mut text = String::new();
+        let mut attempt = TestAttempt {
+            kind,
+            failure: TestFailure {
+                message: attr(attributes, "message"),
+                classname: attr(attributes, "type").unwrap_or_default(),
+                stack_trace: String::new(),
+            },
+            system_out: None,
+            system_err: None,
+        };
+
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement { name, .. } => match name.local_name.as_str() {
+                    "stackTrace" => attempt.failure.stack_trace = self.text()?.trim().to_owned(),
+                    "system-out" => attempt.system_out = non_blank(self.text()?),
+                    "system-err" => attempt.system_err = non_blank(self.text()?),
+                    _ => self.skip()?,
+                },
+                XmlEvent::Characters(s) => text.push_str(&s),
+                XmlEvent::EndElement { .. } => {
+                    if attempt.failure.stack_trace.is_empty() {
+                        attempt.failure.stack_trace = text.trim().to_owned();
+                    }
+                    return Ok(attempt);
+                }
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+    }
+
+    fn properties(&mut self) -> Result<Vec<Property>> {
+        let mut properties = Vec::new();
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
+                    if name.local_name == "property" {
+                        let property = Property {
+                            name: required_attr(&attributes, "name", "property")?,
+                            value: attr(&attributes, "value"),
+                        };
+                        let body = self.text()?;
+                        properties.push(Property {
+                            value: property.value.or_else(|| non_blank(body)),
+                            ..property
+                        });
+                    } else {
+                        self.skip()?;
+                    }
+                }
+                XmlEvent::EndElement { .. } => return Ok(properties),
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+    }
+
+    fn text(&mut self) -> Result<String> {
+        element_text(&mut self.events)
+    }
+
+    fn skip(&mut self) -> Result<()> {
+        skip_element(&mut self.events)
+    }
+}
+
+/// Reads an XML document, with CDATA sections and whitespace reported as
+/// plain characters.
+pub(super) fn xml_events<R: io::Read>(input: R) -> EventReader<BufReader<R>> {
+    ParserConfig::new()
+        .cdata_to_characters(true)
+        .whitespace_to_characters(true)
+        .ignore_comments(true)
+        .create_reader(BufReader::new(input))
+}
+
+/// The local name of the first element of a document, e.g. `testsuites`.
+pub(super) fn root_element<R: io::Read>(input: R) -> Result<String> {
+    let mut events = xml_events(input);
+    loop {
+        match events.next()? {
+            XmlEvent::StartElement { name, .. } => return Ok(name.local_name),
+            XmlEvent::EndDocument => bail!("No root element found"),
+            _ => continue,
+        }
+    }
+}
+
+/// Collects the text content of the current element, up to its closing tag.
+pub(super) fn element_text<R: io::Read>(events: &mut EventReader<R>) -> Result<String> {
+    let mut text = String::new();
+    loop {
+        match events.next()? {
+            XmlEvent::Characters(s) => text.push_str(&s),
+            XmlEvent::StartElement { .. } => skip_element(events)?,
+            XmlEvent::EndElement { .. } => return Ok(text),
+            XmlEvent::EndDocument => bail!("Unexpected end of document"),
+            _ => continue,
+        }
+    }
+}
+
+/// Discards the current element along with all its children.
+pub(super) fn skip_element<R: io::Read>(events: &mut EventReader<R>) -> Result<()> {
+    let mut depth = 1;
+    while depth > 0 {
+        match events.next()? {
+            XmlEvent::StartElement { .. } => depth += 1,
+            XmlEvent::EndElement { .. } => depth -= 1,
+            XmlEvent::EndDocument => bail!("Unexpected end of document"),
+            _ => continue,
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn attr(attributes: &[OwnedAttribute], name: &str) -> Option<String> {
+    attributes
+        .iter()
+        .find(|a| a.name.local_name == name)
+        .map(|a| a.value.clone())
+}
+
+/// The `file` and `line` attributes written by pytest and some other tools.
+/// Line numbers which cannot be parsed are ignored.
+fn location_attrs(attributes: &[OwnedAttribute]) -> Option<SourceLocation> {
+    attr(attributes, "file").map(|file| SourceLocation {
+        file,
+        line: attr(attributes, "line").and_then(|l| l.trim().parse().ok()),
+    })
+}
+
+pub(super) fn required_attr(
+    attributes: &[OwnedAttribute],
+    name: &str,
+    element: &str,
+) -> Result<String> {
+    attr(attributes, name).ok_or_else(|| anyhow!("Missing attribute `{}` in <{}>", name, element))
+}
+
+/// Parses the `time` attribute (in seconds) with microsecond precision,
+/// defaulting to zero when absent.
+fn duration_attr(attributes: &[OwnedAttribute]) -> Result<Duration> {
+    match attr(attributes, "time") {
+        None => Ok(Duration::zero()),
+        Some(s) => seconds(&s),
+    }
+}
+
+/// Parses a number of seconds, e.g. `2.137`, with microsecond precision.
+pub(super) fn seconds(s: &str) -> Result<Duration> {
+    let secs: f64 = decimal_point(s)
+        .parse()
+        .map_err(|_| anyhow!("Cannot parse duration {}", s))?;
+    Ok(float_seconds(secs))
+}
+
+/// Some reporters group thousands with commas (e.g. `1,234.5`), while others
+/// format durations in locales using a decimal comma (e.g. `0,5`). A single
+/// comma without a decimal point is taken for the latter.
+fn decimal_point(s: &str) -> String {
+    let commas = s.matches(',').count();
+    let integer = s.split('.').next().unwrap_or_default();
+    let grouped =
+        (s.contains('.') || commas > 1) && integer.split(',').skip(1).all(|group| group.len() == 3);
+    if grouped {
+        s.replace(',', "")
+    } else if commas == 1 {
+        s.replace(',', ".")
+    } else {
+        s.to_owned()
+    }
+}
+
+/// For the readers of JSON reports, where durations are numbers.
+pub(super) fn float_seconds(secs: f64) -> Duration {
+    Duration::microseconds((secs.abs() * 1_000_000.0).round() as i64)
+}
+
+/// For the JSON and TAP reports measuring durations in milliseconds.
+pub(super) fn float_millis(millis: f64) -> Duration {
+    Duration::microseconds((millis.abs() * 1000.0).round() as i64)
+}
+
+/// JUnit timestamps are usually ISO 8601 local times without an offset (e.g.
+/// `2020-06-07T14:18:12`): these are assumed to be UTC.
+/// The timestamp is optional, so one which cannot be parsed is ignored
+/// rather than failing the whole report.
+fn timestamp_attr(attributes: &[OwnedAttribute]) -> Option<DateTime<Utc>> {
+    let s = attr(attributes, "timestamp")?;
+    let timestamp = DateTime::parse_from_rfc3339(&s)
+        .map(|dt| dt.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(&s, "%Y-%m-%dT%H:%M:%S%.f")
+                .map(|dt| DateTime::from_utc(dt, Utc))
+        });
+    match timestamp {
+        Ok(timestamp) => Some(timestamp),
+        Err(_) => {
+            warn!(
+                "Ignoring the testsuite timestamp {}, which cannot be parsed",
+                s
+            );
+            None
+        }
+    }
+}
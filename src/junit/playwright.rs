@@ -0,0 +1,512 @@
+use super::{
+    jest::{failure, strip_ansi},
+    non_blank,
+    parser::float_millis,
+    Attachment, AttemptKind, SuiteWithSummary, TestAttempt, TestCase, TestFailure, TestSkipped,
+    TestSuite,
+};
+use anyhow::Result;
+use chrono::Duration;
+use serde::Deserialize;
+use std::{
+    io::{self, BufReader},
+    path::PathBuf,
+};
+
+/// The document written by Playwright's `json` reporter.
+#[derive(Debug, Deserialize)]
+struct PlaywrightReport {
+    suites: Vec<Suite>,
+    #[serde(default)]
+    errors: Vec<TestError>,
+}
+
+/// Either a test file or a `describe` block within it.
+#[derive(Debug, Deserialize)]
+struct Suite {
+    title: String,
+    file: Option<String>,
+    #[serde(default)]
+    specs: Vec<Spec>,
+    #[serde(default)]
+    suites: Vec<Suite>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Spec {
+    title: String,
+    file: Option<String>,
+    #[serde(default)]
+    tests: Vec<Test>,
+}
+
+/// A spec run within a given project (e.g. a browser).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Test {
+    #[serde(default)]
+    project_name: String,
+    status: String,
+    #[serde(default)]
+    annotations: Vec<Annotation>,
+    #[serde(default)]
+    results: Vec<TestResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Annotation {
+    #[serde(rename = "type")]
+    kind: String,
+    description: Option<String>,
+}
+
+/// A single run of a test, retries included.
+#[derive(Debug, Deserialize)]
+struct TestResult {
+    status: String,
+    #[serde(default)]
+    duration: f64,
+    error: Option<TestError>,
+    #[serde(default)]
+    stdout: Vec<Output>,
+    #[serde(default)]
+    stderr: Vec<Output>,
+    #[serde(default)]
+    attachments: Vec<ResultAttachment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TestError {
+    message: Option<String>,
+    stack: Option<String>,
+}
+
+/// Binary output is reported as a base64 `buffer` instead, and left out.
+#[derive(Debug, Deserialize)]
+struct Output {
+    text: Option<String>,
+}
+
+/// Attachments may also be inlined as a `body`, in which case there is no
+/// file to link to.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultAttachment {
+    name: String,
+    content_type: Option<String>,
+    path: Option<PathBuf>,
+}
+
+/// Reads a Playwright JSON report, mapping each test file and `describe` block
+/// onto a suite and each spec onto a test case per project it ran in.
+/// Retries are used to tell flaky tests, which eventually passed, apart from
+/// failed ones.
+pub(super) fn read_suites<R, F>(input: R, mut on_testcase: F) -> Result<Vec<SuiteWithSummary>>
+where
+    R: io::Read,
+    F: FnMut(&TestCase),
+{
+    let report: PlaywrightReport = serde_json::from_reader(BufReader::new(input))?;
+
+    let mut suites: Vec<TestSuite> = report
+        .suites
+        .iter()
+        .map(|s| suite(s, "", &mut on_testcase))
+        .collect();
+
+    // errors raised outside of any test, e.g. in the global setup
+    if !report.errors.is_empty() {
+        let mut global = TestSuite::new("Playwright".to_owned());
+        for (n, error) in report.errors.iter().enumerate() {
+            let testcase = TestCase {
+                error: Some(test_failure(error)),
+                ..TestCase::new(format!("global error {}", n + 1), String::new())
+            };
+            on_testcase(&testcase);
+            global.testcases.push(testcase);
+        }
+        suites.push(global);
+    }
+    Ok(suites.into_iter().map(|s| s.with_summary()).collect())
+}
+
+fn suite<F: FnMut(&TestCase)>(suite: &Suite, file: &str, on_testcase: &mut F) -> TestSuite {
+    let file = suite.file.as_deref().unwrap_or(file);
+    let mut test_suite = TestSuite::new(suite.title.clone());
+
+    for spec in &suite.specs {
+        for test in &spec.tests {
+            let testcase = spec_testcase(spec, test, spec.file.as_deref().unwrap_or(file));
+            on_testcase(&testcase);
+            test_suite.testcases.push(testcase);
+        }
+    }
+    for nested in &suite.suites {
+        test_suite
+            .testsuites
+            .push(self::suite(nested, file, on_testcase));
+    }
+
+    test_suite.time = test_suite
+        .testcases
+        .iter()
+        .map(|t| t.time)
+        .chain(test_suite.testsuites.iter().map(|s| s.time))
+        .fold(Duration::zero(), |acc, time| acc + time);
+    test_suite
+}
+
+fn spec_testcase(spec: &Spec, test: &Test, file: &str) -> TestCase {
+    let name = if test.project_name.is_empty() {
+        spec.title.clone()
+    } else {
+        format!("[{}] {}", test.project_name, spec.title)
+    };
+    let mut testcase = TestCase::new(name, file.to_owned());
+
+    if test.status == "skipped" {
+        let reason = test
+            .annotations
+            .iter()
+            .find(|a| a.kind == "skip" || a.kind == "fixme")
+            .and_then(|a| a.description.clone());
+        testcase.skipped = Some(TestSkipped {
+            message: reason,
+            details: None,
+        });
+    }
+    // tests which never ran may have no result at all
+    let (last, retries) = match test.results.split_last() {
+        Some(results) => results,
+        None => return testcase,
+    };
+    testcase.time = float_millis(last.duration);
+    testcase.system_out = output(&last.stdout);
+    testcase.system_err = output(&last.stderr);
+    testcase.attachments = test
+        .results
+        .iter()
+        .filter(|r| r.status != "passed" && r.status != "skipped")
+        .flat_map(|r| r.attachments.iter())
+        .filter_map(|a| {
+            a.path.as_ref().map(|path| Attachment {
+                name: a.name.clone(),
+                content_type: a.content_type.clone(),
+                path: path.clone(),
+            })
+        })
+        .collect();
+
+    match test.status.as_str() {
+        "flaky" => testcase.attempts = attempts(retries, AttemptKind::FlakyFailure),
+        "unexpected" => {
+            testcase.attempts = attempts(retries, AttemptKind::RerunFailure);
+            let failure = result_failure(last);
+            if last.status == "interrupted" {
+                testcase.error = Some(failure);
+            } else {
+                testcase.failure = Some(failure);
+            }
+        }
+        _ => (),
+    }
+    testcase
+}
+
+/// The failed runs preceding the last one.
+fn attempts(retries: &[TestResult], kind: AttemptKind) -> Vec<TestAttempt> {
+    retries
+        .iter()
+        .filter(|r| r.status != "passed" && r.status != "skipped")
+        .map(|r| TestAttempt {
+            kind,
+            failure: result_failure(r),
+            system_out: output(&r.stdout),
+            system_err: output(&r.stderr),
+        })
+        .collect()
+}
+
+/// Timed out runs come without an error.
+fn result_failure(result: &TestResult) -> TestFailure {
+    match &result.error {
+        Some(error) => test_failure(error),
+        None => TestFailure {
+            message: Some(format!("Test {}", result.status)),
+            classname: String::new(),
+            stack_trace: String::new(),
+        },
+    }
+}
+
+fn test_failure(error: &TestError) -> TestFailure {
+    let stack = error.stack.as_ref().or(error.message.as_ref());
+    let mut failure = failure(&strip_ansi(stack.map(String::as_str).unwrap_or_default()));
+    // the stack trace repeats the message, which may however span many lines
+    if let Some(message) = &error.message {
+        failure.message = strip_ansi(message)
+            .lines()
+            .next()
+            .map(|line| line.trim_start_matches("Error: ").trim().to_owned())
+            .and_then(non_blank);
+    }
+    failure
+}
+
+fn output(chunks: &[Output]) -> Option<String> {
+    non_blank(
+        chunks
+            .iter()
+            .filter_map(|chunk| chunk.text.as_deref())
+            .collect::<String>(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use crate::junit::{HasOutcome, TestOutcome};
+    use pretty_assertions::assert_eq;
+
+    const PLAYWRIGHT_JSON: &str = r##"{
+  "config": {},
+  "suites": [
+    {
+      "title": "login.spec.ts",
+      "file": "login.spec.ts",
+      "specs": [
+        {
+          "title": "shows the form",
+          "file": "login.spec.ts",
+          "line": 3,
+          "tests": [
+            {
+              "projectName": "chromium",
+              "expectedStatus": "passed",
+              "status": "expected",
+              "annotations": [],
+              "results": [{
+                "retry": 0,
+                "status": "passed",
+                "duration": 812,
+                "stdout": [],
+                "stderr": [],
+                "attachments": []
+              }]
+            },
+            {
+              "projectName": "firefox",
+              "expectedStatus": "passed",
+              "status": "flaky",
+              "annotations": [],
+              "results": [
+                {
+                  "retry": 0,
+                  "status": "timedOut",
+                  "duration": 30000,
+                  "stdout": [{"text": "navigating\n"}],
+                  "stderr": [],
+                  "attachments": [
+                    {"name": "screenshot", "contentType": "image/png", "path": "/app/test-results/login-firefox/test-failed-1.png"}
+                  ]
+                },
+                {
+                  "retry": 1,
+                  "status": "passed",
+                  "duration": 950,
+                  "stdout": [],
+                  "stderr": [],
+                  "attachments": []
+                }
+              ]
+            }
+          ]
+        }
+      ],
+      "suites": [
+        {
+          "title": "with SSO",
+          "file": "login.spec.ts",
+          "specs": [
+            {
+              "title": "redirects to the provider",
+              "file": "login.spec.ts",
+              "line": 12,
+              "tests": [{
+                "projectName": "chromium",
+                "expectedStatus": "passed",
+                "status": "unexpected",
+                "annotations": [],
+                "results": [
+                  {
+                    "retry": 0,
+                    "status": "failed",
+                    "duration": 120,
+                    "error": {"message": "Error: first attempt"},
+                    "attachments": []
+                  },
+                  {
+                    "retry": 1,
+                    "status": "failed",
+                    "duration": 100,
+                    "error": {
+                      "message": "\u001b[31mError: expect(page).toHaveURL(expected)\u001b[39m\n\nExpected: /sso/",
+                      "stack": "Error: expect(page).toHaveURL(expected)\n\nExpected: /sso/\n    at /app/login.spec.ts:14:7"
+                    },
+                    "stdout": [],
+                    "stderr": [{"text": "warning\n"}],
+                    "attachments": [
+                      {"name": "screenshot", "contentType": "image/png", "path": "/app/test-results/login-sso/test-failed-1.png"},
+                      {"name": "trace", "contentType": "application/zip", "path": "/app/test-results/login-sso/trace.zip"},
+                      {"name": "note", "contentType": "text/plain", "body": "aGVsbG8="}
+                    ]
+                  }
+                ]
+              }]
+            },
+            {
+              "title": "remembers the user",
+              "file": "login.spec.ts",
+              "line": 20,
+              "tests": [{
+                "projectName": "chromium",
+                "expectedStatus": "skipped",
+                "status": "skipped",
+                "annotations": [{"type": "skip", "description": "SSO is disabled"}],
+                "results": []
+              }]
+            }
+          ]
+        }
+      ]
+    }
+  ],
+  "errors": []
+}"##;
+
+    fn read(json: &str) -> Vec<TestSuite> {
+        read_suites(json.as_bytes(), |_| ())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.value)
+            .collect()
+    }
+
+    #[test]
+    fn parse_specs_per_project() {
+        let suites = read(PLAYWRIGHT_JSON);
+        let file = &suites[0];
+        assert_eq!(file.name, "login.spec.ts");
+        assert_eq!(file.testsuites[0].name, "with SSO");
+
+        let outcomes: Vec<(&str, TestOutcome)> = file
+            .testcases
+            .iter()
+            .chain(file.testsuites[0].testcases.iter())
+            .map(|t| (t.name.as_str(), t.outcome()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("[chromium] shows the form", TestOutcome::Passed),
+                ("[firefox] shows the form", TestOutcome::Flaky),
+                ("[chromium] redirects to the provider", TestOutcome::Failed),
+                ("[chromium] remembers the user", TestOutcome::Skipped),
+            ]
+        );
+        assert_eq!(file.testcases[1].classname, "login.spec.ts");
+        assert_eq!(file.testcases[1].time, Duration::milliseconds(950));
+        assert_eq!(file.time, Duration::milliseconds(812 + 950 + 100));
+        assert_eq!(
+            file.testsuites[0].testcases[1]
+                .skipped
+                .as_ref()
+                .and_then(TestSkipped::reason),
+            Some("SSO is disabled")
+        );
+    }
+
+    #[test]
+    fn parse_retries() {
+        let suites = read(PLAYWRIGHT_JSON);
+        let flaky = &suites[0].testcases[1];
+        assert_eq!(flaky.attempts.len(), 1);
+        assert_eq!(flaky.attempts[0].kind, AttemptKind::FlakyFailure);
+        assert_eq!(
+            flaky.attempts[0].failure.message,
+            Some("Test timedOut".to_owned())
+        );
+        assert_eq!(
+            flaky.attempts[0].system_out,
+            Some("navigating\n".to_owned())
+        );
+
+        let failed = &suites[0].testsuites[0].testcases[0];
+        let failure = failed.failure.as_ref().unwrap();
+        assert_eq!(
+            failure.message,
+            Some("expect(page).toHaveURL(expected)".to_owned())
+        );
+        assert_eq!(failure.classname, "Error");
+        assert!(failure.stack_trace.ends_with("at /app/login.spec.ts:14:7"));
+        assert_eq!(failed.system_err, Some("warning\n".to_owned()));
+        assert_eq!(failed.attempts.len(), 1);
+        assert_eq!(failed.attempts[0].kind, AttemptKind::RerunFailure);
+        assert_eq!(
+            failed.attempts[0].failure.message,
+            Some("first attempt".to_owned())
+        );
+    }
+
+    #[test]
+    fn keep_attachment_paths() {
+        let suites = read(PLAYWRIGHT_JSON);
+        let flaky = &suites[0].testcases[1];
+        assert_eq!(
+            flaky.attachments,
+            vec![Attachment {
+                name: "screenshot".to_owned(),
+                content_type: Some("image/png".to_owned()),
+                path: PathBuf::from("/app/test-results/login-firefox/test-failed-1.png"),
+            }]
+        );
+
+        let failed = &suites[0].testsuites[0].testcases[0];
+        let names: Vec<&str> = failed.attachments.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["screenshot", "trace"]);
+        assert_eq!(
+            failed.attachments[1].report_path(),
+            PathBuf::from("attachments/app/test-results/login-sso/trace.zip")
+        );
+        assert!(suites[0].testcases[0].attachments.is_empty());
+    }
+
+    #[test]
+    fn global_errors() {
+        let suites = read(
+            r##"{
+  "suites": [],
+  "errors": [{"message": "Error: globalSetup failed", "stack": "Error: globalSetup failed\n    at setup.ts:3:9"}]
+}"##,
+        );
+        let error = &suites[0].testcases[0];
+        assert_eq!(error.outcome(), TestOutcome::Errored);
+        assert_eq!(
+            error.error.as_ref().unwrap().message,
+            Some("globalSetup failed".to_owned())
+        );
+    }
+
+    #[test]
+    fn not_a_playwright_report() {
+        assert!(read_suites("{}".as_bytes(), |_| ()).is_err());
+        assert!(read_suites("{\"suites\": {}}".as_bytes(), |_| ()).is_err());
+    }
+
+    #[test]
+    fn no_test_files() {
+        assert!(read("{\"config\": {}, \"suites\": [], \"errors\": []}").is_empty());
+    }
+}
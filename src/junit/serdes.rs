@@ -1,4 +1,4 @@
-use super::TestSkipped;
+use super::{SummaryWith, TestSuite};
 use chrono::Duration;
 use serde::{Deserialize, Deserializer, Serializer};
 
@@ -21,12 +21,16 @@ where
 {
     s.serialize_i64(duration.num_milliseconds())
 }
-pub(super) fn testskipped_to_boolean<S>(
-    skipped: &Option<TestSkipped>,
+
+pub(super) fn suites_with_summary<S>(
+    suites: &[TestSuite],
     s: S,
 ) -> std::result::Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    s.serialize_bool(skipped.is_some())
+    s.collect_seq(suites.iter().map(|suite| SummaryWith {
+        summary: suite.summary(),
+        value: suite,
+    }))
 }
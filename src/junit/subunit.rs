@@ -0,0 +1,627 @@
+use super::{non_blank, SuiteWithSummary, TestCase, TestFailure, TestSkipped, TestSuite};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use log::debug;
+use std::{
+    collections::HashMap,
+    io::{self, BufReader, ErrorKind, Read},
+};
+
+const SIGNATURE: u8 = 0xb3;
+const VERSION: u16 = 0x2;
+/// Packets are at most 4MiB long, CRC included.
+const MAX_PACKET_LENGTH: usize = 4 * 1024 * 1024;
+
+const FLAG_TEST_ID: u16 = 0x0800;
+const FLAG_ROUTE_CODE: u16 = 0x0400;
+const FLAG_TIMESTAMP: u16 = 0x0200;
+const FLAG_TAGS: u16 = 0x0080;
+const FLAG_FILE_CONTENT: u16 = 0x0040;
+const FLAG_MIME_TYPE: u16 = 0x0020;
+const STATUS_MASK: u16 = 0x0007;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    Undefined,
+    /// The test was listed, rather than run
+    Exists,
+    InProgress,
+    Success,
+    UnexpectedSuccess,
+    Skip,
+    Fail,
+    ExpectedFailure,
+}
+
+impl Status {
+    fn from_flags(flags: u16) -> Self {
+        match flags & STATUS_MASK {
+            1 => Status::Exists,
+            2 => Status::InProgress,
+            3 => Status::Success,
+            4 => Status::UnexpectedSuccess,
+            5 => Status::Skip,
+            6 => Status::Fail,
+            7 => Status::ExpectedFailure,
+            _ => Status::Undefined,
+        }
+    }
+
+    fn is_final(self) -> bool {
+        !matches!(
+            self,
+            Status::Undefined | Status::Exists | Status::InProgress
+        )
+    }
+}
+
+/// A chunk of a file attached to a test, such as its traceback or output.
+#[derive(Debug, PartialEq)]
+struct FileChunk {
+    name: String,
+    content: Vec<u8>,
+}
+
+/// A subunit v2 packet, which either reports a change in the status of a
+/// test, or carries some of the content of a file attached to it. Tags,
+/// MIME types and route codes are left out.
+#[derive(Debug, PartialEq)]
+struct Packet {
+    status: Status,
+    test_id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    file: Option<FileChunk>,
+}
+
+/// Splits a subunit v2 stream into packets. Bytes found between packets are
+/// output the stream was interleaved with, and are skipped.
+struct Decoder<R> {
+    input: R,
+    offset: usize,
+}
+
+impl<R: Read> Decoder<R> {
+    fn new(input: R) -> Self {
+        Decoder { input, offset: 0 }
+    }
+
+    fn next_packet(&mut self) -> Result<Option<Packet>> {
+        let mut skipped = 0;
+        loop {
+            match self.read_bytes(1) {
+                Ok(byte) if byte[0] == SIGNATURE => break,
+                Ok(_) => skipped += 1,
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        if skipped > 0 {
+            debug!("Skipped {} bytes of non subunit content", skipped);
+        }
+        let start = self.offset - 1;
+
+        let mut header = vec![SIGNATURE];
+        header.extend(self.read_bytes(2)?);
+        let flags = u16::from_be_bytes([header[1], header[2]]);
+        if flags >> 12 != VERSION {
+            bail!(
+                "Unsupported subunit version {} at offset {}",
+                flags >> 12,
+                start
+            );
+        }
+        let first = self.read_bytes(1)?;
+        let length_bytes = [first.clone(), self.read_bytes(number_length(first[0]) - 1)?].concat();
+        let length = Fields::new(&length_bytes).number()?;
+        header.extend(&length_bytes);
+        if length > MAX_PACKET_LENGTH || length < header.len() + 4 {
+            bail!("Invalid packet length {} at offset {}", length, start);
+        }
+
+        let rest = self.read_bytes(length - header.len())?;
+        let (body, crc) = rest.split_at(rest.len() - 4);
+        let expected = u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]]);
+        let actual = crc32(&[&header[..], body].concat());
+        if actual != expected {
+            bail!(
+                "Packet CRC mismatch at offset {}: expected {:08x}, got {:08x}",
+                start,
+                expected,
+                actual
+            );
+        }
+        Packet::parse(flags, body)
+            .map(Some)
+            .map_err(|e| anyhow!("Invalid packet at offset {}: {}", start, e))
+    }
+
+    fn read_bytes(&mut self, length: usize) -> io::Result<Vec<u8>> {
+        let mut bytes = vec![0; length];
+        self.input.read_exact(&mut bytes)?;
+        self.offset += length;
+        Ok(bytes)
+    }
+}
+
+impl Packet {
+    /// Optional fields come in a fixed order, each being present when its
+    /// flag is set.
+    fn parse(flags: u16, body: &[u8]) -> Result<Self> {
+        let mut fields = Fields::new(body);
+        let has = |flag: u16| flags & flag != 0;
+
+        let timestamp = if has(FLAG_TIMESTAMP) {
+            let seconds = fields.u32()?;
+            let nanoseconds = fields.number()?;
+            Some(Utc.timestamp(i64::from(seconds), nanoseconds as u32))
+        } else {
+            None
+        };
+        let test_id = if has(FLAG_TEST_ID) {
+            Some(fields.string()?)
+        } else {
+            None
+        };
+        if has(FLAG_TAGS) {
+            for _ in 0..fields.number()? {
+                fields.string()?;
+            }
+        }
+        if has(FLAG_MIME_TYPE) {
+            fields.string()?;
+        }
+        let file = if has(FLAG_FILE_CONTENT) {
+            let name = fields.string()?;
+            let length = fields.number()?;
+            let content = fields.bytes(length)?.to_vec();
+            Some(FileChunk { name, content })
+        } else {
+            None
+        };
+        if has(FLAG_ROUTE_CODE) {
+            fields.string()?;
+        }
+
+        Ok(Packet {
+            status: Status::from_flags(flags),
+            test_id,
+            timestamp,
+            file,
+        })
+    }
+}
+
+/// How many bytes a number takes, as told by the two highest bits of its
+/// first byte.
+fn number_length(first: u8) -> usize {
+    1 + (first >> 6) as usize
+}
+
+/// Reads the fields of a packet.
+struct Fields<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Fields { bytes }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        if length > self.bytes.len() {
+            bail!("Packet is shorter than its fields");
+        }
+        let (bytes, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// A big endian number of up to 30 bits, prefixed by its length.
+    fn number(&mut self) -> Result<usize> {
+        let first = *self
+            .bytes
+            .first()
+            .ok_or_else(|| anyhow!("Missing number"))?;
+        let bytes = self.bytes(number_length(first))?;
+        Ok(bytes.iter().enumerate().fold(0, |acc, (i, byte)| {
+            let byte = if i == 0 { byte & 0x3f } else { *byte };
+            (acc << 8) | byte as usize
+        }))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let length = self.number()?;
+        Ok(String::from_utf8(self.bytes(length)?.to_vec())?)
+    }
+}
+
+/// The CRC32 used by zlib, which checks the integrity of each packet.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// What is known of a test, as its packets are read.
+#[derive(Debug)]
+struct TestRun {
+    id: String,
+    status: Status,
+    started: Option<DateTime<Utc>>,
+    stopped: Option<DateTime<Utc>>,
+    files: Vec<FileChunk>,
+}
+
+impl TestRun {
+    fn file(&self, name: &str) -> Option<String> {
+        self.files
+            .iter()
+            .find(|f| f.name == name)
+            .and_then(|f| non_blank(String::from_utf8_lossy(&f.content).into_owned()))
+    }
+}
+
+/// Reads a subunit v2 stream, as stored by stestr. Tests are grouped into
+/// suites after the class they belong to, which is told from their id, e.g.
+/// `package.module.Class.test_method`.
+pub(super) fn read_suites<R, F>(input: R, mut on_testcase: F) -> Result<Vec<SuiteWithSummary>>
+where
+    R: io::Read,
+    F: FnMut(&TestCase),
+{
+    let mut decoder = Decoder::new(BufReader::new(input));
+    let mut runs: Vec<TestRun> = Vec::new();
+    let mut run_positions: HashMap<String, usize> = HashMap::new();
+    while let Some(packet) = decoder.next_packet()? {
+        let id = match packet.test_id {
+            Some(id) => id,
+            // output of the test runner itself
+            None => continue,
+        };
+        let position = match run_positions.get(&id) {
+            Some(position) => *position,
+            None => {
+                run_positions.insert(id.clone(), runs.len());
+                runs.push(TestRun {
+                    id,
+                    status: Status::Undefined,
+                    started: None,
+                    stopped: None,
+                    files: Vec::new(),
+                });
+                runs.len() - 1
+            }
+        };
+        let run = &mut runs[position];
+        match packet.status {
+            Status::InProgress => run.started = run.started.or(packet.timestamp),
+            status if status.is_final() => {
+                run.status = status;
+                run.stopped = packet.timestamp;
+            }
+            Status::Exists if run.status == Status::Undefined => run.status = Status::Exists,
+            _ => (),
+        }
+        if let Some(chunk) = packet.file {
+            match run.files.iter_mut().find(|f| f.name == chunk.name) {
+                Some(file) => file.content.extend(chunk.content),
+                None => run.files.push(chunk),
+            }
+        }
+    }
+
+    let mut suites: Vec<TestSuite> = Vec::new();
+    let mut suite_positions: HashMap<String, usize> = HashMap::new();
+    for run in runs {
+        // listed without being run
+        if run.status == Status::Exists
+            || (run.status == Status::Undefined && run.started.is_none())
+        {
+            continue;
+        }
+        let (classname, name) = split_id(&run.id);
+        let position = match suite_positions.get(classname) {
+            Some(position) => *position,
+            None => {
+                suite_positions.insert(classname.to_owned(), suites.len());
+                suites.push(TestSuite::new(classname.to_owned()));
+                suites.len() - 1
+            }
+        };
+        let suite = &mut suites[position];
+        if let Some(started) = run.started {
+            suite.metadata.timestamp =
+                Some(suite.metadata.timestamp.map_or(started, |t| t.min(started)));
+        }
+        let testcase = testcase(&run, classname, name);
+        on_testcase(&testcase);
+        suite.time = suite.time + testcase.time;
+        suite.testcases.push(testcase);
+    }
+    if suites.is_empty() {
+        bail!("No subunit test results found");
+    }
+
+    Ok(suites.into_iter().map(|s| s.with_summary()).collect())
+}
+
+/// Splits a test id into its class and method, leaving out the attributes
+/// which may follow it, e.g. `test_method[id-1234,smoke]`.
+fn split_id(id: &str) -> (&str, &str) {
+    let without_attributes = match id.find('[') {
+        Some(position) => &id[..position],
+        None => id,
+    };
+    match without_attributes.rfind('.') {
+        Some(position) => (&id[..position], &id[position + 1..]),
+        None => ("", id),
+    }
+}
+
+fn testcase(run: &TestRun, classname: &str, name: &str) -> TestCase {
+    let time = match (run.started, run.stopped) {
+        (Some(started), Some(stopped)) if stopped > started => stopped - started,
+        _ => Duration::zero(),
+    };
+    let mut testcase = TestCase {
+        time,
+        system_out: run.file("stdout"),
+        system_err: run.file("stderr"),
+        ..TestCase::new(name.to_owned(), classname.to_owned())
+    };
+
+    // other text attachments, such as logs, are appended to the output
+    let others: Vec<String> = run
+        .files
+        .iter()
+        .filter(|f| !["stdout", "stderr", "traceback", "reason"].contains(&f.name.as_str()))
+        .filter_map(|f| {
+            let content = String::from_utf8(f.content.clone()).ok()?;
+            non_blank(content).map(|content| format!("{}:\n{}", f.name, content))
+        })
+        .collect();
+    if !others.is_empty() {
+        let output = testcase
+            .system_out
+            .into_iter()
+            .chain(others)
+            .collect::<Vec<_>>();
+        testcase.system_out = Some(output.join("\n"));
+    }
+
+    match run.status {
+        Status::Success | Status::ExpectedFailure => (),
+        Status::Fail => testcase.failure = Some(failure(run.file("traceback"))),
+        Status::UnexpectedSuccess => {
+            testcase.failure = Some(TestFailure {
+                message: Some("Unexpected success".to_owned()),
+                classname: String::new(),
+                stack_trace: String::new(),
+            })
+        }
+        Status::Skip => {
+            testcase.skipped = Some(TestSkipped {
+                message: run.file("reason"),
+                details: None,
+            })
+        }
+        // the stream ended while the test was running
+        _ => {
+            testcase.error = Some(TestFailure {
+                message: Some("The test did not finish".to_owned()),
+                classname: String::new(),
+                stack_trace: run.file("traceback").unwrap_or_default(),
+            })
+        }
+    }
+    testcase
+}
+
+/// The last line of a Python traceback names the exception, e.g.
+/// `AssertionError: 1 != 2`.
+fn failure(traceback: Option<String>) -> TestFailure {
+    let traceback = traceback.unwrap_or_default();
+    let last_line = traceback
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    let classname = match last_line.find(':') {
+        Some(position) if !last_line[..position].contains(' ') => &last_line[..position],
+        _ => "",
+    };
+    TestFailure {
+        message: non_blank(last_line.to_owned()),
+        classname: classname.to_owned(),
+        stack_trace: traceback.trim().to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use crate::junit::{HasOutcome, TestOutcome};
+    use pretty_assertions::assert_eq;
+
+    /// An enumeration packet for test `foo`, as written by python-subunit.
+    const ENUMERATION: &[u8] = b"\xb3\x29\x01\x0c\x03foo\x08\x55\x5f\x1b";
+
+    fn number(value: usize) -> Vec<u8> {
+        if value < 1 << 6 {
+            vec![value as u8]
+        } else if value < 1 << 14 {
+            ((value as u16) | 0x4000).to_be_bytes().to_vec()
+        } else {
+            ((value as u32) | 0x0080_0000).to_be_bytes()[1..].to_vec()
+        }
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        [number(value.len()), value.as_bytes().to_vec()].concat()
+    }
+
+    /// Encodes a packet the way python-subunit does.
+    fn packet(test_id: &str, status: u16, seconds: u32, file: Option<(&str, &str)>) -> Vec<u8> {
+        // runnable
+        let mut flags = (VERSION << 12) | FLAG_TEST_ID | 0x0100 | FLAG_TIMESTAMP | status;
+        let mut body = seconds.to_be_bytes().to_vec();
+        body.extend(number(0));
+        body.extend(string(test_id));
+        if let Some((name, content)) = file {
+            flags |= FLAG_FILE_CONTENT | FLAG_MIME_TYPE;
+            body.extend(string("text/plain; charset=utf8"));
+            body.extend(string(name));
+            body.extend(number(content.len()));
+            body.extend(content.as_bytes());
+        }
+        let base_length = 3 + body.len() + 4;
+        let length = if base_length <= 62 {
+            number(base_length + 1)
+        } else {
+            number(base_length + 2)
+        };
+        let mut packet = vec![SIGNATURE];
+        packet.extend(&flags.to_be_bytes());
+        packet.extend(length);
+        packet.extend(body);
+        let crc = crc32(&packet);
+        packet.extend(&crc.to_be_bytes());
+        packet
+    }
+
+    fn stream() -> Vec<u8> {
+        let traceback = "Traceback (most recent call last):\n  File \"shop/tests/test_cart.py\", line 12, in test_discount\n    self.assertEqual(90, total)\nAssertionError: 90 != 100\n";
+        [
+            packet("shop.tests.test_cart.CartTest.test_add", 2, 100, None),
+            packet(
+                "shop.tests.test_cart.CartTest.test_add",
+                0,
+                100,
+                Some(("stdout", "adding 2 items")),
+            ),
+            packet("shop.tests.test_cart.CartTest.test_add", 3, 102, None),
+            b"interleaved output\n".to_vec(),
+            packet(
+                "shop.tests.test_cart.CartTest.test_discount[id-1,smoke]",
+                2,
+                102,
+                None,
+            ),
+            packet(
+                "shop.tests.test_cart.CartTest.test_discount[id-1,smoke]",
+                6,
+                103,
+                Some(("traceback", traceback)),
+            ),
+            packet("shop.tests.test_ship.ShipTest.test_abroad", 2, 103, None),
+            packet(
+                "shop.tests.test_ship.ShipTest.test_abroad",
+                5,
+                103,
+                Some(("reason", "shipping is disabled")),
+            ),
+            packet("shop.tests.test_ship.ShipTest.test_home", 2, 103, None),
+            ENUMERATION.to_vec(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn decode_packets() {
+        let packet = Decoder::new(ENUMERATION).next_packet().unwrap().unwrap();
+        assert_eq!(
+            packet,
+            Packet {
+                status: Status::Exists,
+                test_id: Some("foo".to_owned()),
+                timestamp: None,
+                file: None,
+            }
+        );
+        assert_eq!(Fields::new(&number(16000)).number().unwrap(), 16000);
+        assert_eq!(Fields::new(&[0x40, 0x40]).number().unwrap(), 64);
+    }
+
+    #[test]
+    fn check_crc() {
+        let mut corrupted = ENUMERATION.to_vec();
+        corrupted[5] = b'g';
+        let error = Decoder::new(&corrupted[..]).next_packet().unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Packet CRC mismatch at offset 0"));
+    }
+
+    #[test]
+    fn read_test_results() {
+        let suites: Vec<TestSuite> = read_suites(&stream()[..], |_| ())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.value)
+            .collect();
+        let names: Vec<&str> = suites.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "shop.tests.test_cart.CartTest",
+                "shop.tests.test_ship.ShipTest"
+            ]
+        );
+        assert_eq!(suites[0].time, Duration::seconds(3));
+        assert_eq!(suites[0].metadata.timestamp, Some(Utc.timestamp(100, 0)));
+
+        let outcomes: Vec<(&str, TestOutcome)> = suites
+            .iter()
+            .flat_map(|s| s.testcases.iter())
+            .map(|t| (t.name.as_str(), t.outcome()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("test_add", TestOutcome::Passed),
+                ("test_discount[id-1,smoke]", TestOutcome::Failed),
+                ("test_abroad", TestOutcome::Skipped),
+                ("test_home", TestOutcome::Errored),
+            ]
+        );
+        assert_eq!(
+            suites[0].testcases[0].system_out,
+            Some("adding 2 items".to_owned())
+        );
+
+        let failure = suites[0].testcases[1].failure.as_ref().unwrap();
+        assert_eq!(
+            failure.message,
+            Some("AssertionError: 90 != 100".to_owned())
+        );
+        assert_eq!(failure.classname, "AssertionError");
+        assert!(failure.stack_trace.starts_with("Traceback"));
+        assert_eq!(
+            suites[1].testcases[0]
+                .skipped
+                .as_ref()
+                .and_then(TestSkipped::reason),
+            Some("shipping is disabled")
+        );
+    }
+
+    #[test]
+    fn not_a_subunit_stream() {
+        assert!(read_suites("ok 1 - adds\n".as_bytes(), |_| ()).is_err());
+        assert!(read_suites(ENUMERATION, |_| ()).is_err());
+    }
+}
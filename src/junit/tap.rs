@@ -0,0 +1,561 @@
+use super::{
+    non_blank, parser::float_millis, HasOutcome, SuiteWithSummary, TestCase, TestFailure,
+    TestSkipped, TestSuite,
+};
+use anyhow::{bail, Result};
+use chrono::Duration;
+use std::io::{self, BufRead, BufReader};
+
+/// Subtests are indented by four spaces relative to their parent.
+const SUBTEST_INDENT: usize = 4;
+
+/// Reads a TAP (versions 13 and 14) stream as a single suite named after the
+/// file it was read from. Subtests are mapped onto nested suites.
+pub(super) fn read_suites<R, F>(
+    input: R,
+    name: &str,
+    on_testcase: F,
+) -> Result<Vec<SuiteWithSummary>>
+where
+    R: io::Read,
+    F: FnMut(&TestCase),
+{
+    let lines = BufReader::new(input)
+        .lines()
+        .collect::<io::Result<Vec<String>>>()?;
+    let mut parser = TapParser {
+        lines: &lines,
+        position: 0,
+        bailed_out: false,
+        on_testcase,
+    };
+    let suite = parser.suite(name.to_owned(), 0)?;
+    if suite.testcases.is_empty() && suite.testsuites.is_empty() {
+        bail!("No TAP plan or test lines found");
+    }
+    Ok(vec![suite.with_summary()])
+}
+
+struct TapParser<'a, F: FnMut(&TestCase)> {
+    lines: &'a [String],
+    position: usize,
+    bailed_out: bool,
+    on_testcase: F,
+}
+
+impl<'a, F: FnMut(&TestCase)> TapParser<'a, F> {
+    /// Parses the lines at the given indentation level, up to the end of the
+    /// stream or to the first line belonging to an enclosing level.
+    fn suite(&mut self, name: String, indent: usize) -> Result<TestSuite> {
+        let mut suite = TestSuite::new(name);
+        let mut output = String::new();
+        let mut plan: Option<usize> = None;
+        let mut test_points: usize = 0;
+        let mut subtest_name: Option<String> = None;
+        let mut subtest: Option<TestSuite> = None;
+
+        while let Some(line) = self.lines.get(self.position) {
+            let depth = indentation(line);
+            let content = line.trim();
+            if self.bailed_out || (depth < indent && !content.is_empty()) {
+                break;
+            }
+            if content.is_empty() {
+                self.position += 1;
+                continue;
+            }
+            if depth >= indent + SUBTEST_INDENT && starts_subtest(content) {
+                if let Some(name) = content.strip_prefix("# Subtest:") {
+                    subtest_name = non_blank(name.trim().to_owned());
+                    self.position += 1;
+                    continue;
+                }
+                let name = subtest_name
+                    .take()
+                    .unwrap_or_else(|| format!("subtest {}", test_points + 1));
+                subtest = Some(self.suite(name, depth)?);
+                continue;
+            }
+            self.position += 1;
+
+            if let Some(point) = TestPoint::parse(content) {
+                test_points += 1;
+                let diagnostics = self.yaml_block(indent);
+                match subtest.take() {
+                    Some(nested) => suite.testsuites.push(self.close_subtest(nested, point)),
+                    None => {
+                        let testcase = point.into_testcase(&suite.name, test_points, &diagnostics);
+                        (self.on_testcase)(&testcase);
+                        suite.testcases.push(testcase);
+                    }
+                }
+            } else if let Some((planned, skip_reason)) = parse_plan(content) {
+                plan = Some(planned);
+                if planned == 0 {
+                    // `1..0` skips the whole stream
+                    let testcase = TestCase {
+                        skipped: Some(TestSkipped {
+                            message: skip_reason,
+                            details: None,
+                        }),
+                        ..TestCase::new(suite.name.clone(), suite.name.clone())
+                    };
+                    (self.on_testcase)(&testcase);
+                    suite.testcases.push(testcase);
+                }
+            } else if let Some(reason) = content.strip_prefix("Bail out!") {
+                self.bailed_out = true;
+                let testcase = TestCase {
+                    error: Some(TestFailure {
+                        message: non_blank(reason.trim().to_owned()),
+                        classname: "Bail out!".to_owned(),
+                        stack_trace: String::new(),
+                    }),
+                    ..TestCase::new("Bail out!".to_owned(), suite.name.clone())
+                };
+                (self.on_testcase)(&testcase);
+                suite.testcases.push(testcase);
+            } else if let Some(name) = content.strip_prefix("# Subtest:") {
+                subtest_name = non_blank(name.trim().to_owned());
+            } else if !content.starts_with("TAP version") && !content.starts_with("pragma ") {
+                output.push_str(content);
+                output.push('\n');
+            }
+        }
+
+        // a subtest without its closing test point, e.g. a truncated stream
+        if let Some(nested) = subtest {
+            suite.testsuites.push(nested);
+        }
+        if let Some(planned) = plan.filter(|p| *p > 0 && *p != test_points && !self.bailed_out) {
+            let testcase = TestCase {
+                error: Some(TestFailure {
+                    message: Some(format!(
+                        "Planned {} tests, but {} were run",
+                        planned, test_points
+                    )),
+                    classname: "plan".to_owned(),
+                    stack_trace: String::new(),
+                }),
+                ..TestCase::new("plan".to_owned(), suite.name.clone())
+            };
+            (self.on_testcase)(&testcase);
+            suite.testcases.push(testcase);
+        }
+
+        suite.time = suite
+            .testcases
+            .iter()
+            .map(|t| t.time)
+            .chain(suite.testsuites.iter().map(|s| s.time))
+            .fold(Duration::zero(), |acc, time| acc + time);
+        suite.system_out = non_blank(output);
+        Ok(suite)
+    }
+
+    /// The test point following a subtest names it and reports its outcome,
+    /// which may be a failure even when all the tests within it passed (e.g.
+    /// when its plan was not fulfilled).
+    fn close_subtest(&mut self, mut nested: TestSuite, point: TestPoint) -> TestSuite {
+        if let Some(description) = point.description {
+            nested.name = description;
+        }
+        if !point.ok && point.directive.is_none() && nested.is_successful() {
+            let testcase = TestCase {
+                failure: Some(TestFailure {
+                    message: Some("Subtest failed".to_owned()),
+                    classname: String::new(),
+                    stack_trace: String::new(),
+                }),
+                ..TestCase::new(nested.name.clone(), nested.name.clone())
+            };
+            (self.on_testcase)(&testcase);
+            nested.testcases.push(testcase);
+        }
+        nested
+    }
+
+    /// Collects the YAML diagnostics block (delimited by `---` and `...`)
+    /// following a test point, if any. A block missing its closing `...`
+    /// ends with the first line back at the indentation of the test point.
+    fn yaml_block(&mut self, indent: usize) -> Vec<String> {
+        let mut yaml = Vec::new();
+        match self.lines.get(self.position) {
+            Some(line) if indentation(line) > indent && line.trim() == "---" => (),
+            _ => return yaml,
+        }
+        let block_indent = indentation(&self.lines[self.position]);
+        self.position += 1;
+        while let Some(line) = self.lines.get(self.position) {
+            if !line.trim().is_empty() && indentation(line) <= indent {
+                break;
+            }
+            self.position += 1;
+            if line.trim() == "..." {
+                break;
+            }
+            yaml.push(line.chars().skip(block_indent).collect());
+        }
+        yaml
+    }
+}
+
+/// Indented lines only open a subtest when they could start one, any other
+/// being the output of the test before them.
+fn starts_subtest(content: &str) -> bool {
+    content.starts_with("# Subtest:")
+        || TestPoint::parse(content).is_some()
+        || parse_plan(content).is_some()
+}
+
+#[derive(Debug, PartialEq)]
+enum Directive {
+    Skip(Option<String>),
+    Todo(Option<String>),
+}
+
+/// A `ok`/`not ok` line.
+#[derive(Debug, PartialEq)]
+struct TestPoint {
+    ok: bool,
+    number: Option<usize>,
+    description: Option<String>,
+    directive: Option<Directive>,
+}
+
+impl TestPoint {
+    fn parse(line: &str) -> Option<TestPoint> {
+        let (ok, rest) = if let Some(rest) = line.strip_prefix("not ok") {
+            (false, rest)
+        } else if let Some(rest) = line.strip_prefix("ok") {
+            (true, rest)
+        } else {
+            return None;
+        };
+        if !rest.is_empty() && !rest.starts_with(' ') {
+            return None;
+        }
+
+        let (rest, comment) = split_comment(rest.trim_start());
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        let number = rest[..digits].parse().ok();
+        let description = rest[digits..].trim_start();
+        let description = description
+            .strip_prefix("- ")
+            .unwrap_or(description)
+            .trim()
+            .replace("\\#", "#")
+            .replace("\\\\", "\\");
+
+        Some(TestPoint {
+            ok,
+            number,
+            description: non_blank(description),
+            directive: comment.and_then(parse_directive),
+        })
+    }
+
+    fn into_testcase(self, classname: &str, position: usize, diagnostics: &[String]) -> TestCase {
+        let number = self.number.unwrap_or(position);
+        let name = self
+            .description
+            .unwrap_or_else(|| format!("test {}", number));
+        let time = yaml_scalar(diagnostics, "duration_ms")
+            .and_then(|ms| ms.parse::<f64>().ok())
+            .map(float_millis)
+            .unwrap_or_else(Duration::zero);
+        let mut testcase = TestCase {
+            time,
+            ..TestCase::new(name, classname.to_owned())
+        };
+
+        match (self.ok, self.directive) {
+            (_, Some(Directive::Skip(reason))) => {
+                testcase.skipped = Some(TestSkipped {
+                    message: reason,
+                    details: None,
+                })
+            }
+            // a failing TODO test is an expected failure
+            (false, Some(Directive::Todo(reason))) => {
+                testcase.skipped = Some(TestSkipped {
+                    message: Some(
+                        reason
+                            .map(|r| format!("TODO {}", r))
+                            .unwrap_or_else(|| "TODO".to_owned()),
+                    ),
+                    details: None,
+                })
+            }
+            (true, _) => (),
+            (false, None) => {
+                testcase.failure = Some(TestFailure {
+                    message: yaml_scalar(diagnostics, "message"),
+                    classname: yaml_scalar(diagnostics, "severity").unwrap_or_default(),
+                    stack_trace: diagnostics.join("\n").trim().to_owned(),
+                })
+            }
+        }
+        testcase
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.chars().take_while(|c| *c == ' ').count()
+}
+
+/// Splits a test point at its first unescaped `#`.
+fn split_comment(s: &str) -> (&str, Option<&str>) {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '\\' => escaped = !escaped,
+            '#' if !escaped => return (&s[..i], Some(s[i + 1..].trim())),
+            _ => escaped = false,
+        }
+    }
+    (s, None)
+}
+
+fn parse_directive(comment: &str) -> Option<Directive> {
+    let word_len = comment
+        .chars()
+        .take_while(char::is_ascii_alphabetic)
+        .count();
+    let word = comment[..word_len].to_ascii_lowercase();
+    let reason = non_blank(
+        comment[word_len..]
+            .trim_start_matches(':')
+            .trim()
+            .to_owned(),
+    );
+    if word.starts_with("skip") {
+        Some(Directive::Skip(reason))
+    } else if word == "todo" {
+        Some(Directive::Todo(reason))
+    } else {
+        None
+    }
+}
+
+/// Parses a `1..N` plan, along with the reason for skipping given by `1..0`.
+fn parse_plan(line: &str) -> Option<(usize, Option<String>)> {
+    let (plan, comment) = split_comment(line);
+    let planned = plan.trim().strip_prefix("1..")?.parse().ok()?;
+    let reason = match comment.and_then(parse_directive) {
+        Some(Directive::Skip(reason)) => reason,
+        _ => None,
+    };
+    Some((planned, reason))
+}
+
+/// Looks up a top level key of a YAML diagnostics block. Only plain, quoted
+/// and block scalars are supported, which covers what TAP producers emit.
+fn yaml_scalar(yaml: &[String], key: &str) -> Option<String> {
+    let prefix = format!("{}:", key);
+    let position = yaml.iter().position(|l| l.starts_with(&prefix))?;
+    let value = yaml[position][prefix.len()..].trim();
+
+    if value.starts_with('|') || value.starts_with('>') {
+        let lines: Vec<&str> = yaml[position + 1..]
+            .iter()
+            .take_while(|l| l.is_empty() || l.starts_with(' '))
+            .map(|l| l.trim())
+            .collect();
+        let separator = if value.starts_with('|') { "\n" } else { " " };
+        non_blank(lines.join(separator).trim().to_owned())
+    } else {
+        let unquoted = ['"', '\'']
+            .iter()
+            .find(|q| value.len() > 1 && value.starts_with(**q) && value.ends_with(**q))
+            .map(|_| &value[1..value.len() - 1])
+            .unwrap_or(value);
+        non_blank(unquoted.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use crate::junit::{Summary, TestOutcome};
+    use pretty_assertions::assert_eq;
+
+    const TAP_STREAM: &str = r##"TAP version 13
+1..6
+ok 1 - connects to the database
+not ok 2 - applies migrations
+  ---
+  message: "table users already exists"
+  severity: fail
+  at:
+    file: t/migrations.t
+    line: 42
+  duration_ms: 12.5
+  ...
+ok 3 - uses the replica # SKIP no replica configured
+not ok 4 - handles unicode # TODO not implemented
+ok 5 handles \# in names
+# cleaning up
+ok 6
+"##;
+
+    fn read_suite(tap: &str) -> TestSuite {
+        read_suites(tap.as_bytes(), "db.t", |_| ())
+            .unwrap()
+            .remove(0)
+            .value
+    }
+
+    #[test]
+    fn parse_test_points() {
+        let suite = read_suite(TAP_STREAM);
+        let names: Vec<&str> = suite.testcases.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "connects to the database",
+                "applies migrations",
+                "uses the replica",
+                "handles unicode",
+                "handles # in names",
+                "test 6"
+            ]
+        );
+        let outcomes: Vec<TestOutcome> = suite.testcases.iter().map(|t| t.outcome()).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                TestOutcome::Passed,
+                TestOutcome::Failed,
+                TestOutcome::Skipped,
+                TestOutcome::Skipped,
+                TestOutcome::Passed,
+                TestOutcome::Passed
+            ]
+        );
+        assert_eq!(suite.name, "db.t");
+        assert_eq!(suite.system_out, Some("# cleaning up\n".to_owned()));
+    }
+
+    #[test]
+    fn parse_yaml_diagnostics() {
+        let suite = read_suite(TAP_STREAM);
+        let failed = &suite.testcases[1];
+        assert_eq!(failed.time, Duration::microseconds(12500));
+        assert_eq!(
+            failed.failure,
+            Some(TestFailure {
+                message: Some("table users already exists".to_owned()),
+                classname: "fail".to_owned(),
+                stack_trace: "message: \"table users already exists\"\nseverity: fail\nat:\n  file: t/migrations.t\n  line: 42\nduration_ms: 12.5".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_directives() {
+        let suite = read_suite(TAP_STREAM);
+        let reasons: Vec<Option<&str>> = suite
+            .testcases
+            .iter()
+            .map(|t| t.skipped.as_ref().and_then(TestSkipped::reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                None,
+                None,
+                Some("no replica configured"),
+                Some("TODO not implemented"),
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_subtests() {
+        let suite = read_suite(
+            r##"TAP version 14
+1..2
+# Subtest: parser
+    1..2
+    ok 1 - parses numbers
+    not ok 2 - parses strings
+ok 1 - parser
+    ok 1 - nested without header
+    1..1
+ok 2 - lexer
+"##,
+        );
+        assert!(suite.testcases.is_empty());
+        let nested: Vec<(&str, usize)> = suite
+            .testsuites
+            .iter()
+            .map(|s| (s.name.as_str(), s.testcases.len()))
+            .collect();
+        assert_eq!(nested, vec![("parser", 2), ("lexer", 1)]);
+        assert_eq!(suite.summary().failures, 1);
+    }
+
+    #[test]
+    fn indented_output() {
+        let suite = read_suite("not ok 1\n    some output\nok 2 - bar\n");
+        let names: Vec<&str> = suite.testcases.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["test 1", "bar"]);
+        assert!(suite.testsuites.is_empty());
+        assert_eq!(suite.system_out, Some("some output\n".to_owned()));
+
+        let headed = read_suite("1..1\n    # Subtest: parser\n    ok 1 - parses\nok 1\n");
+        assert_eq!(headed.testsuites[0].name, "parser");
+        assert_eq!(headed.testsuites[0].testcases.len(), 1);
+    }
+
+    #[test]
+    fn unterminated_yaml_block() {
+        let suite = read_suite("not ok 1 - foo\n  ---\n  message: boom\nok 2 - bar\n");
+        let names: Vec<&str> = suite.testcases.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["foo", "bar"]);
+        assert_eq!(
+            suite.testcases[0].failure.as_ref().unwrap().message,
+            Some("boom".to_owned())
+        );
+    }
+
+    #[test]
+    fn bail_out_and_missing_tests() {
+        let bailed = read_suite("1..3\nok 1\nBail out! database is down\nok 2\n");
+        let summary: Summary = bailed.summary();
+        assert_eq!((summary.tests, summary.errors), (2, 1));
+        assert_eq!(
+            bailed.testcases[1].error.as_ref().unwrap().message,
+            Some("database is down".to_owned())
+        );
+
+        let truncated = read_suite("1..3\nok 1\n");
+        assert_eq!(
+            truncated.testcases[1].error.as_ref().unwrap().message,
+            Some("Planned 3 tests, but 1 were run".to_owned())
+        );
+    }
+
+    #[test]
+    fn skip_all_plan() {
+        let suite = read_suite("1..0 # Skipped: no database\n");
+        assert_eq!(suite.outcome(), TestOutcome::Skipped);
+        assert_eq!(
+            suite.testcases[0]
+                .skipped
+                .as_ref()
+                .and_then(TestSkipped::reason),
+            Some("no database")
+        );
+    }
+
+    #[test]
+    fn not_a_tap_stream() {
+        assert!(read_suites("<testsuite/>".as_bytes(), "x", |_| ()).is_err());
+    }
+}
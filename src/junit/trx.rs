@@ -0,0 +1,387 @@
+use super::{
+    non_blank,
+    parser::{attr, element_text, float_seconds, skip_element, xml_events},
+    SuiteMetadata, SuiteWithSummary, TestCase, TestFailure, TestSkipped, TestSuite,
+};
+use anyhow::{anyhow, bail, Result};
+use chrono::Duration;
+use log::warn;
+use std::{
+    collections::HashMap,
+    io::{self, BufReader},
+};
+use xml::{
+    attribute::OwnedAttribute,
+    reader::{EventReader, XmlEvent},
+};
+
+/// A `<UnitTestResult>`, which only refers to the test it ran by its id.
+struct UnitTestResult {
+    test_id: String,
+    testcase: TestCase,
+    hostname: Option<String>,
+}
+
+/// The class and assembly of a test, from its `<UnitTest>` definition.
+struct UnitTest {
+    classname: String,
+    storage: Option<String>,
+}
+
+/// Reads a Visual Studio TRX report, as written by MSTest or by `dotnet test
+/// --logger trx`. Test results are grouped into suites after the class
+/// defining them, as found in the `<TestDefinitions>` following the results.
+pub(super) fn read_suites<R, F>(input: R, mut on_testcase: F) -> Result<Vec<SuiteWithSummary>>
+where
+    R: io::Read,
+    F: FnMut(&TestCase),
+{
+    let mut events = xml_events(input);
+    let mut results: Vec<UnitTestResult> = Vec::new();
+    let mut definitions: HashMap<String, UnitTest> = HashMap::new();
+
+    let run_name = loop {
+        match events.next()? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => match name.local_name.as_str() {
+                "TestRun" => break attr(&attributes, "name").unwrap_or_default(),
+                other => bail!("Unexpected root element <{}>", other),
+            },
+            XmlEvent::EndDocument => bail!("No <TestRun> element found"),
+            _ => continue,
+        }
+    };
+
+    loop {
+        match events.next()? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => match name.local_name.as_str() {
+                "Results" | "TestDefinitions" => continue,
+                "UnitTestResult" => results.push(unit_test_result(&mut events, &attributes)?),
+                "UnitTest" => {
+                    let id = attr(&attributes, "id")
+                        .ok_or_else(|| anyhow!("Missing attribute `id` in <UnitTest>"))?;
+                    definitions.insert(id, unit_test(&mut events, &attributes)?);
+                }
+                _ => skip_element(&mut events)?,
+            },
+            XmlEvent::EndDocument => break,
+            _ => continue,
+        }
+    }
+
+    let mut suites: Vec<TestSuite> = Vec::new();
+    let mut suite_positions: HashMap<String, usize> = HashMap::new();
+    for result in results {
+        let definition = definitions.get(&result.test_id);
+        let name = definition
+            .map(|d| d.classname.clone())
+            .unwrap_or_else(|| run_name.clone());
+        let position = match suite_positions.get(&name) {
+            Some(position) => *position,
+            None => {
+                suite_positions.insert(name.clone(), suites.len());
+                suites.push(TestSuite {
+                    metadata: SuiteMetadata {
+                        hostname: result.hostname,
+                        file: definition.and_then(|d| d.storage.clone()),
+                        ..SuiteMetadata::default()
+                    },
+                    ..TestSuite::new(name.clone())
+                });
+                suites.len() - 1
+            }
+        };
+        let suite = &mut suites[position];
+        let testcase = TestCase {
+            classname: name,
+            ..result.testcase
+        };
+        on_testcase(&testcase);
+        suite.time = suite.time + testcase.time;
+        suite.testcases.push(testcase);
+    }
+
+    Ok(suites.into_iter().map(|s| s.with_summary()).collect())
+}
+
+fn unit_test_result<R: io::Read>(
+    events: &mut EventReader<BufReader<R>>,
+    attributes: &[OwnedAttribute],
+) -> Result<UnitTestResult> {
+    let name = attr(attributes, "testName")
+        .ok_or_else(|| anyhow!("Missing attribute `testName` in <UnitTestResult>"))?;
+    let mut testcase = TestCase {
+        time: match attr(attributes, "duration") {
+            Some(duration) => timespan(&duration),
+            None => Duration::zero(),
+        },
+        ..TestCase::new(name, String::new())
+    };
+    let mut message: Option<String> = None;
+    let mut stack_trace: Option<String> = None;
+
+    // the `<Output>` element nests both the captured output and the error
+    let mut depth = 1;
+    while depth > 0 {
+        match events.next()? {
+            XmlEvent::StartElement { name, .. } => match name.local_name.as_str() {
+                "Output" | "ErrorInfo" => depth += 1,
+                "StdOut" => testcase.system_out = non_blank(element_text(events)?),
+                "StdErr" => testcase.system_err = non_blank(element_text(events)?),
+                "Message" => message = non_blank(element_text(events)?),
+                "StackTrace" => stack_trace = non_blank(element_text(events)?),
+                // results of data driven tests, already accounted for
+                _ => skip_element(events)?,
+            },
+            XmlEvent::EndElement { .. } => depth -= 1,
+            XmlEvent::EndDocument => bail!("Unexpected end of document"),
+            _ => continue,
+        }
+    }
+
+    let outcome = attr(attributes, "outcome").unwrap_or_default();
+    let failure = || TestFailure {
+        message: message
+            .as_ref()
+            .map(|m| m.trim().to_owned())
+            .or_else(|| Some(format!("Test outcome: {}", outcome))),
+        classname: String::new(),
+        stack_trace: message
+            .iter()
+            .chain(stack_trace.iter())
+            .map(|s| s.trim())
+            .collect::<Vec<&str>>()
+            .join("\n"),
+    };
+    match outcome.as_str() {
+        "Failed" | "Timeout" => testcase.failure = Some(failure()),
+        "Error" | "Aborted" => testcase.error = Some(failure()),
+        "NotExecuted" | "NotRunnable" | "Inconclusive" | "Pending" => {
+            testcase.skipped = Some(TestSkipped {
+                message: message.as_ref().map(|m| m.trim().to_owned()),
+                details: None,
+            })
+        }
+        _ => (),
+    }
+
+    Ok(UnitTestResult {
+        test_id: attr(attributes, "testId").unwrap_or_default(),
+        testcase,
+        hostname: attr(attributes, "computerName"),
+    })
+}
+
+fn unit_test<R: io::Read>(
+    events: &mut EventReader<BufReader<R>>,
+    attributes: &[OwnedAttribute],
+) -> Result<UnitTest> {
+    let mut unit_test = UnitTest {
+        classname: String::new(),
+        storage: attr(attributes, "storage"),
+    };
+    loop {
+        match events.next()? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                if name.local_name == "TestMethod" {
+                    unit_test.classname = attr(&attributes, "className").unwrap_or_default();
+                }
+                skip_element(events)?;
+            }
+            XmlEvent::EndElement { .. } => return Ok(unit_test),
+            XmlEvent::EndDocument => bail!("Unexpected end of document"),
+            _ => continue,
+        }
+    }
+}
+
+/// Parses a .NET `TimeSpan`, e.g. `00:00:01.2345678` or `1.02:00:00` for
+/// over a day, with microsecond precision.
+/// A duration which cannot be parsed is ignored rather than failing the
+/// whole file.
+fn timespan(s: &str) -> Duration {
+    match parse_timespan(s) {
+        Some(duration) => duration,
+        None => {
+            warn!("Ignoring the test duration {}, which cannot be parsed", s);
+            Duration::zero()
+        }
+    }
+}
+
+fn parse_timespan(s: &str) -> Option<Duration> {
+    let parts: Vec<&str> = s.split(':').collect();
+    let (days, hours, minutes, seconds) = match parts.as_slice() {
+        [hours, minutes, seconds] => match hours.split_once('.') {
+            Some((days, hours)) => (days, hours, *minutes, *seconds),
+            None => ("0", *hours, *minutes, *seconds),
+        },
+        _ => return None,
+    };
+    let days: i64 = days.parse().ok()?;
+    let hours: i64 = hours.parse().ok()?;
+    let minutes: i64 = minutes.parse().ok()?;
+    let seconds: f64 = seconds.parse().ok()?;
+    Some(
+        Duration::days(days)
+            + Duration::hours(hours)
+            + Duration::minutes(minutes)
+            + float_seconds(seconds),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use crate::junit::{HasOutcome, TestOutcome};
+    use pretty_assertions::assert_eq;
+
+    const TRX: &str = r##"<?xml version="1.0" encoding="utf-8"?>
+<TestRun id="8c84fa94" name="agent@BUILD-01 2020-11-02 10:12:31" xmlns="http://microsoft.com/schemas/VisualStudio/TeamTest/2010">
+  <Times creation="2020-11-02T10:12:31.3" start="2020-11-02T10:12:29.9" finish="2020-11-02T10:12:32.1" />
+  <TestSettings name="default" id="6b2d6c35" />
+  <Results>
+    <UnitTestResult executionId="e1" testId="t1" testName="AddsItems" computerName="BUILD-01" duration="00:00:00.0123450" outcome="Passed" testType="13cdc9d9" testListId="8c84fa94">
+      <Output>
+        <StdOut>adding 2 items</StdOut>
+      </Output>
+    </UnitTestResult>
+    <UnitTestResult executionId="e2" testId="t2" testName="AppliesDiscounts" computerName="BUILD-01" duration="00:00:01.5000000" outcome="Failed" testType="13cdc9d9" testListId="8c84fa94">
+      <Output>
+        <ErrorInfo>
+          <Message>Assert.AreEqual failed. Expected:&lt;90&gt;. Actual:&lt;100&gt;.</Message>
+          <StackTrace>   at Shop.Tests.CartTests.AppliesDiscounts() in /src/CartTests.cs:line 21</StackTrace>
+        </ErrorInfo>
+      </Output>
+    </UnitTestResult>
+    <UnitTestResult executionId="e3" testId="t3" testName="ShipsAbroad" computerName="BUILD-01" duration="00:00:00" outcome="NotExecuted" testType="13cdc9d9" testListId="8c84fa94">
+      <Output>
+        <ErrorInfo>
+          <Message>Shipping is disabled</Message>
+        </ErrorInfo>
+      </Output>
+    </UnitTestResult>
+    <UnitTestResult executionId="e4" testId="t4" testName="ChecksOut" computerName="BUILD-01" duration="00:01:00.0000000" outcome="Timeout" testType="13cdc9d9" testListId="8c84fa94">
+      <InnerResults>
+        <UnitTestResult executionId="e5" testId="t4" testName="ChecksOut (1)" outcome="Timeout" />
+      </InnerResults>
+    </UnitTestResult>
+  </Results>
+  <TestDefinitions>
+    <UnitTest name="AddsItems" storage="/src/bin/shop.tests.dll" id="t1">
+      <Execution id="e1" />
+      <TestMethod codeBase="/src/bin/shop.tests.dll" adapterTypeName="executor://mstestadapter/v2" className="Shop.Tests.CartTests" name="AddsItems" />
+    </UnitTest>
+    <UnitTest name="AppliesDiscounts" storage="/src/bin/shop.tests.dll" id="t2">
+      <Execution id="e2" />
+      <TestMethod codeBase="/src/bin/shop.tests.dll" adapterTypeName="executor://mstestadapter/v2" className="Shop.Tests.CartTests" name="AppliesDiscounts" />
+    </UnitTest>
+    <UnitTest name="ShipsAbroad" storage="/src/bin/shop.tests.dll" id="t3">
+      <Execution id="e3" />
+      <TestMethod codeBase="/src/bin/shop.tests.dll" adapterTypeName="executor://mstestadapter/v2" className="Shop.Tests.ShippingTests" name="ShipsAbroad" />
+    </UnitTest>
+  </TestDefinitions>
+  <TestEntries>
+    <TestEntry testId="t1" executionId="e1" testListId="8c84fa94" />
+  </TestEntries>
+  <ResultSummary outcome="Failed">
+    <Counters total="4" executed="3" passed="1" failed="2" />
+  </ResultSummary>
+</TestRun>"##;
+
+    fn read(xml: &str) -> Vec<TestSuite> {
+        read_suites(xml.as_bytes(), |_| ())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.value)
+            .collect()
+    }
+
+    #[test]
+    fn group_results_by_class() {
+        let suites = read(TRX);
+        let names: Vec<&str> = suites.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Shop.Tests.CartTests",
+                "Shop.Tests.ShippingTests",
+                "agent@BUILD-01 2020-11-02 10:12:31"
+            ]
+        );
+        let cart = &suites[0];
+        assert_eq!(cart.time, Duration::microseconds(1_512_345));
+        assert_eq!(
+            cart.metadata.file,
+            Some("/src/bin/shop.tests.dll".to_owned())
+        );
+        assert_eq!(cart.metadata.hostname, Some("BUILD-01".to_owned()));
+        assert_eq!(cart.testcases[0].classname, "Shop.Tests.CartTests");
+        assert_eq!(
+            cart.testcases[0].system_out,
+            Some("adding 2 items".to_owned())
+        );
+    }
+
+    #[test]
+    fn parse_outcomes() {
+        let suites = read(TRX);
+        let outcomes: Vec<(&str, TestOutcome)> = suites
+            .iter()
+            .flat_map(|s| s.testcases.iter())
+            .map(|t| (t.name.as_str(), t.outcome()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("AddsItems", TestOutcome::Passed),
+                ("AppliesDiscounts", TestOutcome::Failed),
+                ("ShipsAbroad", TestOutcome::Skipped),
+                ("ChecksOut", TestOutcome::Failed),
+            ]
+        );
+
+        let failure = suites[0].testcases[1].failure.as_ref().unwrap();
+        assert_eq!(
+            failure.message,
+            Some("Assert.AreEqual failed. Expected:<90>. Actual:<100>.".to_owned())
+        );
+        assert!(failure.stack_trace.ends_with("/src/CartTests.cs:line 21"));
+        assert_eq!(
+            suites[1].testcases[0]
+                .skipped
+                .as_ref()
+                .and_then(TestSkipped::reason),
+            Some("Shipping is disabled")
+        );
+        assert_eq!(
+            suites[2].testcases[0].failure.as_ref().unwrap().message,
+            Some("Test outcome: Timeout".to_owned())
+        );
+    }
+
+    #[test]
+    fn parse_timespans() {
+        assert_eq!(
+            timespan("01:02:03.0000005"),
+            Duration::seconds(3723) + Duration::microseconds(1)
+        );
+        assert_eq!(
+            timespan("1.02:00:00"),
+            Duration::days(1) + Duration::hours(2)
+        );
+        assert_eq!(timespan("1.5"), Duration::zero());
+    }
+
+    #[test]
+    fn not_a_trx_report() {
+        assert!(read_suites("<testsuites/>".as_bytes(), |_| ()).is_err());
+    }
+}
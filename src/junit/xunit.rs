@@ -0,0 +1,375 @@
+use super::{
+    non_blank,
+    parser::{attr, element_text, seconds, skip_element, xml_events},
+    SuiteWithSummary, TestCase, TestFailure, TestSkipped, TestSuite,
+};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use log::warn;
+use std::io::{self, BufReader};
+use xml::{
+    attribute::OwnedAttribute,
+    reader::{EventReader, XmlEvent},
+};
+
+/// A pull based xUnit.net v2 XML parser, mapping each `<assembly>` onto a
+/// suite and each test `<collection>` onto a suite nested within it.
+struct XunitParser<R: io::Read, F: FnMut(&TestCase)> {
+    events: EventReader<BufReader<R>>,
+    on_testcase: F,
+}
+
+/// Reads an xUnit.net v2 result file, rooted at either `<assemblies>` or a
+/// single `<assembly>`.
+pub(super) fn read_suites<R, F>(input: R, on_testcase: F) -> Result<Vec<SuiteWithSummary>>
+where
+    R: io::Read,
+    F: FnMut(&TestCase),
+{
+    let suites = XunitParser {
+        events: xml_events(input),
+        on_testcase,
+    }
+    .parse()?;
+    Ok(suites.into_iter().map(|s| s.with_summary()).collect())
+}
+
+impl<R: io::Read, F: FnMut(&TestCase)> XunitParser<R, F> {
+    fn parse(mut self) -> Result<Vec<TestSuite>> {
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
+                    return match name.local_name.as_str() {
+                        "assembly" => Ok(vec![self.assembly(&attributes)?]),
+                        "assemblies" => self.assemblies(),
+                        other => Err(anyhow!("Unexpected root element <{}>", other)),
+                    }
+                }
+                XmlEvent::EndDocument => bail!("No <assembly> element found"),
+                _ => continue,
+            }
+        }
+    }
+
+    fn assemblies(&mut self) -> Result<Vec<TestSuite>> {
+        let mut suites = Vec::new();
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
+                    if name.local_name == "assembly" {
+                        suites.push(self.assembly(&attributes)?);
+                    } else {
+                        skip_element(&mut self.events)?;
+                    }
+                }
+                XmlEvent::EndElement { .. } => return Ok(suites),
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+    }
+
+    fn assembly(&mut self, attributes: &[OwnedAttribute]) -> Result<TestSuite> {
+        let mut suite = suite(attributes, "assembly")?;
+        suite.metadata.file = attr(attributes, "name");
+        suite.metadata.timestamp = run_timestamp(attributes);
+
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => match name.local_name.as_str() {
+                    "collection" => suite.testsuites.push(self.collection(&attributes)?),
+                    "errors" => continue,
+                    // e.g. a failing fixture or assembly cleanup
+                    "error" => {
+                        let name = attr(&attributes, "name")
+                            .or_else(|| attr(&attributes, "type"))
+                            .unwrap_or_else(|| "error".to_owned());
+                        let testcase = TestCase {
+                            error: self.failure(&attributes)?,
+                            ..TestCase::new(name, suite.name.clone())
+                        };
+                        (self.on_testcase)(&testcase);
+                        suite.testcases.push(testcase);
+                    }
+                    _ => skip_element(&mut self.events)?,
+                },
+                XmlEvent::EndElement { name } => {
+                    if name.local_name == "assembly" {
+                        return Ok(suite);
+                    }
+                }
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+    }
+
+    fn collection(&mut self, attributes: &[OwnedAttribute]) -> Result<TestSuite> {
+        let mut suite = suite(attributes, "collection")?;
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
+                    if name.local_name == "test" {
+                        let testcase = self.test(&attributes)?;
+                        (self.on_testcase)(&testcase);
+                        suite.testcases.push(testcase);
+                    } else {
+                        skip_element(&mut self.events)?;
+                    }
+                }
+                XmlEvent::EndElement { .. } => return Ok(suite),
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+    }
+
+    fn test(&mut self, attributes: &[OwnedAttribute]) -> Result<TestCase> {
+        let name = attr(attributes, "name")
+            .ok_or_else(|| anyhow!("Missing attribute `name` in <test>"))?;
+        let mut testcase = TestCase {
+            time: duration_attr(attributes)?,
+            ..TestCase::new(name, attr(attributes, "type").unwrap_or_default())
+        };
+        let mut failure: Option<TestFailure> = None;
+        let mut reason: Option<String> = None;
+
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => match name.local_name.as_str() {
+                    "failure" => failure = self.failure(&attributes)?,
+                    "reason" => {
+                        reason =
+                            non_blank(element_text(&mut self.events)?).map(|r| r.trim().to_owned())
+                    }
+                    "output" => testcase.system_out = non_blank(element_text(&mut self.events)?),
+                    _ => skip_element(&mut self.events)?,
+                },
+                XmlEvent::EndElement { .. } => break,
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+
+        match attr(attributes, "result").unwrap_or_default().as_str() {
+            "Fail" => {
+                testcase.failure = Some(failure.unwrap_or_else(|| TestFailure {
+                    message: None,
+                    classname: String::new(),
+                    stack_trace: String::new(),
+                }))
+            }
+            "Skip" | "NotRun" => {
+                testcase.skipped = Some(TestSkipped {
+                    message: reason,
+                    details: None,
+                })
+            }
+            _ => (),
+        }
+        Ok(testcase)
+    }
+
+    /// Reads the `<failure>` element of either a test or an error. An error
+    /// holds it as a child instead, in which case this returns the first
+    /// one found.
+    fn failure(&mut self, attributes: &[OwnedAttribute]) -> Result<Option<TestFailure>> {
+        let mut failure = TestFailure {
+            message: None,
+            classname: attr(attributes, "exception-type").unwrap_or_default(),
+            stack_trace: String::new(),
+        };
+        let mut nested: Option<TestFailure> = None;
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => match name.local_name.as_str() {
+                    "message" => {
+                        failure.message =
+                            non_blank(element_text(&mut self.events)?).map(|m| m.trim().to_owned())
+                    }
+                    "stack-trace" => {
+                        failure.stack_trace = element_text(&mut self.events)?.trim().to_owned()
+                    }
+                    "failure" => nested = nested.or(self.failure(&attributes)?),
+                    _ => skip_element(&mut self.events)?,
+                },
+                XmlEvent::EndElement { .. } => return Ok(nested.or(Some(failure))),
+                XmlEvent::EndDocument => bail!("Unexpected end of document"),
+                _ => continue,
+            }
+        }
+    }
+}
+
+fn suite(attributes: &[OwnedAttribute], element: &str) -> Result<TestSuite> {
+    let name = attr(attributes, "name")
+        .ok_or_else(|| anyhow!("Missing attribute `name` in <{}>", element))?;
+    Ok(TestSuite {
+        time: duration_attr(attributes)?,
+        ..TestSuite::new(name)
+    })
+}
+
+fn duration_attr(attributes: &[OwnedAttribute]) -> Result<Duration> {
+    match attr(attributes, "time") {
+        None => Ok(Duration::zero()),
+        Some(s) => seconds(&s),
+    }
+}
+
+/// Assemblies are timestamped through separate `run-date` and `run-time`
+/// attributes, in local time: these are assumed to be UTC. As the timestamp
+/// is optional, one which cannot be parsed is ignored.
+fn run_timestamp(attributes: &[OwnedAttribute]) -> Option<DateTime<Utc>> {
+    let timestamp = format!(
+        "{}T{}",
+        attr(attributes, "run-date")?,
+        attr(attributes, "run-time")?
+    );
+    match NaiveDateTime::parse_from_str(&timestamp, "%Y-%m-%dT%H:%M:%S") {
+        Ok(dt) => Some(DateTime::from_utc(dt, Utc)),
+        Err(_) => {
+            warn!(
+                "Ignoring the assembly timestamp {}, which cannot be parsed",
+                timestamp
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use crate::junit::{HasOutcome, TestOutcome};
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    const XUNIT_XML: &str = r##"<?xml version="1.0" encoding="utf-8"?>
+<assemblies timestamp="11/02/2020 10:12:31">
+  <assembly name="/src/bin/Shop.Tests.dll" run-date="2020-11-02" run-time="10:12:31" config-file="/src/bin/Shop.Tests.dll.config" test-framework="xUnit.net 2.4.1.0" environment="64-bit .NET Core" total="4" passed="1" failed="1" skipped="1" time="0.250" errors="1">
+    <errors>
+      <error type="test-class-cleanup" name="Shop.Tests.ShippingTests">
+        <failure exception-type="System.IO.IOException">
+          <message><![CDATA[disk full]]></message>
+          <stack-trace><![CDATA[at Shop.Tests.ShippingTests.Dispose()]]></stack-trace>
+        </failure>
+      </error>
+    </errors>
+    <collection total="3" passed="1" failed="1" skipped="1" name="Test collection for Shop.Tests.CartTests" time="0.200">
+      <test name="Shop.Tests.CartTests.AddsItems" type="Shop.Tests.CartTests" method="AddsItems" time="0.0120000" result="Pass">
+        <traits>
+          <trait name="Category" value="Cart" />
+        </traits>
+        <output><![CDATA[adding 2 items]]></output>
+      </test>
+      <test name="Shop.Tests.CartTests.AppliesDiscounts" type="Shop.Tests.CartTests" method="AppliesDiscounts" time="0.1500000" result="Fail">
+        <failure exception-type="Xunit.Sdk.EqualException">
+          <message><![CDATA[Assert.Equal() Failure
+Expected: 90
+Actual:   100]]></message>
+          <stack-trace><![CDATA[   at Shop.Tests.CartTests.AppliesDiscounts() in /src/CartTests.cs:line 21]]></stack-trace>
+        </failure>
+      </test>
+      <test name="Shop.Tests.CartTests.ShipsAbroad" type="Shop.Tests.CartTests" method="ShipsAbroad" time="0" result="Skip">
+        <reason><![CDATA[Shipping is disabled]]></reason>
+      </test>
+    </collection>
+  </assembly>
+</assemblies>"##;
+
+    fn read(xml: &str) -> Vec<TestSuite> {
+        read_suites(xml.as_bytes(), |_| ())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.value)
+            .collect()
+    }
+
+    #[test]
+    fn parse_assemblies() {
+        let suites = read(XUNIT_XML);
+        let assembly = &suites[0];
+        assert_eq!(assembly.name, "/src/bin/Shop.Tests.dll");
+        assert_eq!(assembly.time, Duration::milliseconds(250));
+        assert_eq!(
+            assembly.metadata.timestamp,
+            Some(Utc.ymd(2020, 11, 2).and_hms(10, 12, 31))
+        );
+        let collection = &assembly.testsuites[0];
+        assert_eq!(collection.name, "Test collection for Shop.Tests.CartTests");
+        assert_eq!(collection.testcases[0].classname, "Shop.Tests.CartTests");
+        assert_eq!(
+            collection.testcases[0].system_out,
+            Some("adding 2 items".to_owned())
+        );
+        assert_eq!(assembly.summary().tests, 4);
+    }
+
+    #[test]
+    fn parse_results() {
+        let suites = read(XUNIT_XML);
+        let outcomes: Vec<(&str, TestOutcome)> = suites[0]
+            .testcases
+            .iter()
+            .chain(suites[0].testsuites[0].testcases.iter())
+            .map(|t| (t.name.as_str(), t.outcome()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("Shop.Tests.ShippingTests", TestOutcome::Errored),
+                ("Shop.Tests.CartTests.AddsItems", TestOutcome::Passed),
+                ("Shop.Tests.CartTests.AppliesDiscounts", TestOutcome::Failed),
+                ("Shop.Tests.CartTests.ShipsAbroad", TestOutcome::Skipped),
+            ]
+        );
+
+        let error = suites[0].testcases[0].error.as_ref().unwrap();
+        assert_eq!(error.classname, "System.IO.IOException");
+        assert_eq!(error.message, Some("disk full".to_owned()));
+
+        let collection = &suites[0].testsuites[0];
+        let failure = collection.testcases[1].failure.as_ref().unwrap();
+        assert_eq!(failure.classname, "Xunit.Sdk.EqualException");
+        assert_eq!(
+            failure.message,
+            Some("Assert.Equal() Failure\nExpected: 90\nActual:   100".to_owned())
+        );
+        assert!(failure.stack_trace.ends_with("/src/CartTests.cs:line 21"));
+        assert_eq!(
+            collection.testcases[2]
+                .skipped
+                .as_ref()
+                .and_then(TestSkipped::reason),
+            Some("Shipping is disabled")
+        );
+    }
+
+    #[test]
+    fn ignore_unparseable_run_dates() {
+        let xml = XUNIT_XML.replace("run-date=\"2020-11-02\"", "run-date=\"02/11/2020\"");
+        let suites = read(&xml);
+        assert_eq!(suites[0].metadata.timestamp, None);
+        assert_eq!(suites[0].summary().tests, 4);
+    }
+
+    #[test]
+    fn not_an_xunit_report() {
+        assert!(read_suites("<testsuites/>".as_bytes(), |_| ()).is_err());
+    }
+}
@@ -0,0 +1,454 @@
+use crate::junit::{
+    AttemptKind, FullReport, Summary, TestCase, TestFailure, TestSuite, UnreadableReport,
+};
+use chrono::Duration;
+use std::{borrow::Cow, io};
+use xml::{
+    common::XmlVersion,
+    writer::{EmitterConfig, EventWriter, XmlEvent},
+};
+
+const UNREADABLE_SUITE: &str = "Unreadable reports";
+
+/// Renders the report as a single JUnit XML document, for tools which only
+/// read that format. Nested suites are listed as a flat list, and reports
+/// which could not be read as errors of their own suite.
+pub struct JunitXmlReport {
+    compact: bool,
+    sink: Box<dyn io::Write>,
+}
+impl JunitXmlReport {
+    pub fn sink_to(compact: bool, sink: Box<dyn io::Write>) -> Self {
+        JunitXmlReport { compact, sink }
+    }
+    pub fn stdout(compact: bool) -> Self {
+        JunitXmlReport::sink_to(compact, Box::new(io::stdout()))
+    }
+}
+
+impl JunitXmlReport {
+    pub fn render(&mut self, full_report: &FullReport) -> anyhow::Result<()> {
+        write_document(&mut self.sink, self.compact, full_report)?;
+        writeln!(self.sink)?;
+        Ok(())
+    }
+}
+
+fn write_document<W: io::Write>(
+    sink: W,
+    compact: bool,
+    full_report: &FullReport,
+) -> anyhow::Result<()> {
+    let mut suites: Vec<TestSuite> = full_report
+        .all_suites
+        .iter()
+        .flat_map(|s| s.value.clone().flatten())
+        .collect();
+    if !full_report.unreadable_reports.is_empty() {
+        suites.push(unreadable_suite(&full_report.unreadable_reports));
+    }
+    let summaries: Vec<Summary> = suites.iter().map(TestSuite::summary).collect();
+
+    let mut writer = EmitterConfig::new()
+        .perform_indent(!compact)
+        .create_writer(sink);
+    writer.write(XmlEvent::StartDocument {
+        version: XmlVersion::Version10,
+        encoding: Some("UTF-8"),
+        standalone: None,
+    })?;
+    // The summary already counts the unreadable reports as errors, but not as
+    // the tests of their suite. Nested suites are only counted once.
+    let mut totals = Totals::of(&full_report.summary);
+    totals.tests += full_report.unreadable_reports.len();
+    let attributes = totals.attributes();
+    let mut element = XmlEvent::start_element("testsuites");
+    for (name, value) in &attributes {
+        element = element.attr(*name, value);
+    }
+    writer.write(element)?;
+    for (suite, summary) in suites.iter().zip(&summaries) {
+        write_suite(&mut writer, suite, summary)?;
+    }
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+/// The counts shared by the `<testsuites>` and `<testsuite>` elements.
+struct Totals {
+    tests: usize,
+    failures: usize,
+    errors: usize,
+    skipped: usize,
+    time: Duration,
+}
+
+impl Totals {
+    fn of(summary: &Summary) -> Self {
+        Totals {
+            tests: summary.tests,
+            failures: summary.failures,
+            errors: summary.errors,
+            skipped: summary.skipped,
+            time: summary.time,
+        }
+    }
+
+    fn attributes(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("tests", self.tests.to_string()),
+            ("failures", self.failures.to_string()),
+            ("errors", self.errors.to_string()),
+            ("skipped", self.skipped.to_string()),
+            ("time", seconds(self.time)),
+        ]
+    }
+}
+
+fn write_suite<W: io::Write>(
+    writer: &mut EventWriter<W>,
+    suite: &TestSuite,
+    summary: &Summary,
+) -> anyhow::Result<()> {
+    let metadata = &suite.metadata;
+    let mut attributes = vec![("name", xml_text(&suite.name).into_owned())];
+    attributes.extend(Totals::of(summary).attributes());
+    if let Some(timestamp) = metadata.timestamp {
+        attributes.push((
+            "timestamp",
+            timestamp.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        ));
+    }
+    let optional = [
+        ("hostname", &metadata.hostname),
+        ("package", &metadata.package),
+        ("id", &metadata.id),
+        ("file", &metadata.file),
+    ];
+    for (name, value) in optional.iter() {
+        if let Some(value) = value {
+            attributes.push((name, xml_text(value).into_owned()));
+        }
+    }
+    let mut element = XmlEvent::start_element("testsuite");
+    for (name, value) in &attributes {
+        element = element.attr(*name, value);
+    }
+    writer.write(element)?;
+
+    if !metadata.properties.is_empty() {
+        writer.write(XmlEvent::start_element("properties"))?;
+        for property in &metadata.properties {
+            let name = xml_text(&property.name);
+            let value = property.value.as_deref().map(xml_text);
+            let mut element = XmlEvent::start_element("property").attr("name", &name);
+            if let Some(value) = &value {
+                element = element.attr("value", value);
+            }
+            writer.write(element)?;
+            writer.write(XmlEvent::end_element())?;
+        }
+        writer.write(XmlEvent::end_element())?;
+    }
+    for testcase in &suite.testcases {
+        write_testcase(writer, testcase)?;
+    }
+    write_text(writer, "system-out", suite.system_out.as_deref())?;
+    write_text(writer, "system-err", suite.system_err.as_deref())?;
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+/// Attachments are listed in the output of the test, the way the Jenkins
+/// and GitLab JUnit importers expect them: `[[ATTACHMENT|<path>]]`.
+fn write_testcase<W: io::Write>(
+    writer: &mut EventWriter<W>,
+    testcase: &TestCase,
+) -> anyhow::Result<()> {
+    let name = xml_text(&testcase.name);
+    let classname = xml_text(&testcase.classname);
+    let time = seconds(testcase.time);
+    let file = testcase.location.as_ref().map(|l| xml_text(&l.file));
+    let line = testcase
+        .location
+        .as_ref()
+        .and_then(|l| l.line)
+        .map(|l| l.to_string());
+    let mut element = XmlEvent::start_element("testcase")
+        .attr("name", &name)
+        .attr("classname", &classname)
+        .attr("time", &time);
+    if let Some(file) = &file {
+        element = element.attr("file", file);
+    }
+    if let Some(line) = &line {
+        element = element.attr("line", line);
+    }
+    writer.write(element)?;
+
+    if let Some(skipped) = &testcase.skipped {
+        let message = skipped.message.as_deref().map(xml_text);
+        let mut element = XmlEvent::start_element("skipped");
+        if let Some(message) = &message {
+            element = element.attr("message", message);
+        }
+        writer.write(element)?;
+        if let Some(details) = &skipped.details {
+            writer.write(XmlEvent::characters(&xml_text(details)))?;
+        }
+        writer.write(XmlEvent::end_element())?;
+    }
+    if let Some(failure) = &testcase.failure {
+        write_failure(writer, "failure", failure)?;
+    }
+    if let Some(error) = &testcase.error {
+        write_failure(writer, "error", error)?;
+    }
+    for attempt in &testcase.attempts {
+        let element = match attempt.kind {
+            AttemptKind::FlakyFailure => "flakyFailure",
+            AttemptKind::FlakyError => "flakyError",
+            AttemptKind::RerunFailure => "rerunFailure",
+            AttemptKind::RerunError => "rerunError",
+        };
+        let (message, classname) = failure_attributes(&attempt.failure);
+        let mut start = XmlEvent::start_element(element);
+        if let Some(message) = &message {
+            start = start.attr("message", message);
+        }
+        if let Some(classname) = &classname {
+            start = start.attr("type", classname);
+        }
+        writer.write(start)?;
+        write_text(
+            writer,
+            "stackTrace",
+            Some(attempt.failure.stack_trace.as_str()),
+        )?;
+        write_text(writer, "system-out", attempt.system_out.as_deref())?;
+        write_text(writer, "system-err", attempt.system_err.as_deref())?;
+        writer.write(XmlEvent::end_element())?;
+    }
+
+    let attachments = testcase
+        .attachments
+        .iter()
+        .map(|a| format!("[[ATTACHMENT|{}]]", a.path.display()));
+    let system_out: Vec<String> = testcase
+        .system_out
+        .iter()
+        .cloned()
+        .chain(attachments)
+        .collect();
+    write_text(writer, "system-out", Some(&system_out.join("\n")))?;
+    write_text(writer, "system-err", testcase.system_err.as_deref())?;
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+fn write_failure<W: io::Write>(
+    writer: &mut EventWriter<W>,
+    element: &str,
+    failure: &TestFailure,
+) -> anyhow::Result<()> {
+    let (message, classname) = failure_attributes(failure);
+    let mut start = XmlEvent::start_element(element);
+    if let Some(message) = &message {
+        start = start.attr("message", message);
+    }
+    if let Some(classname) = &classname {
+        start = start.attr("type", classname);
+    }
+    writer.write(start)?;
+    if !failure.stack_trace.is_empty() {
+        writer.write(XmlEvent::characters(&xml_text(&failure.stack_trace)))?;
+    }
+    writer.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+fn failure_attributes(failure: &TestFailure) -> (Option<Cow<'_, str>>, Option<Cow<'_, str>>) {
+    (
+        failure.message.as_deref().map(xml_text),
+        Some(failure.classname.as_str())
+            .filter(|c| !c.is_empty())
+            .map(xml_text),
+    )
+}
+
+/// Blank text is left out, along with its element.
+fn write_text<W: io::Write>(
+    writer: &mut EventWriter<W>,
+    element: &str,
+    text: Option<&str>,
+) -> anyhow::Result<()> {
+    if let Some(text) = text.filter(|t| !t.trim().is_empty()) {
+        writer.write(XmlEvent::start_element(element))?;
+        writer.write(XmlEvent::characters(&xml_text(text)))?;
+        writer.write(XmlEvent::end_element())?;
+    }
+    Ok(())
+}
+
+fn unreadable_suite(reports: &[UnreadableReport]) -> TestSuite {
+    TestSuite {
+        testcases: reports
+            .iter()
+            .map(|report| TestCase {
+                error: Some(TestFailure {
+                    message: Some(report.error.clone()),
+                    classname: String::new(),
+                    stack_trace: String::new(),
+                }),
+                ..TestCase::new(
+                    report.path.display().to_string(),
+                    UNREADABLE_SUITE.to_owned(),
+                )
+            })
+            .collect(),
+        ..TestSuite::new(UNREADABLE_SUITE.to_owned())
+    }
+}
+
+fn seconds(time: Duration) -> String {
+    format!("{:.3}", time.num_milliseconds() as f64 / 1000.0)
+}
+
+/// Leaves out the characters XML 1.0 does not allow, such as the escape
+/// codes colouring the output of some test runners.
+fn xml_text(text: &str) -> Cow<'_, str> {
+    let allowed = |c: char| {
+        matches!(c, '\t' | '\n' | '\r') || (c >= '\u{20}' && c != '\u{fffe}' && c != '\u{ffff}')
+    };
+    if text.chars().all(allowed) {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(text.chars().filter(|c| allowed(*c)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use crate::junit::{
+        fixtures::{self, failure, suite, testcase, unreadable},
+        Attachment, Property, SourceLocation, SuiteMetadata, TestAttempt, TestSkipped,
+    };
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    fn render(full_report: &FullReport) -> String {
+        let mut xml = Vec::new();
+        write_document(&mut xml, true, full_report).unwrap();
+        String::from_utf8(xml).unwrap()
+    }
+
+    fn full_report() -> FullReport {
+        let failed = TestCase {
+            failure: Some(TestFailure {
+                stack_trace: "AssertionError: expected <90>\n\tat CartTest.java:12".to_owned(),
+                ..failure("expected <90>")
+            }),
+            system_out: Some("\u{1b}[31mred\u{1b}[0m".to_owned()),
+            attachments: vec![Attachment {
+                name: "screenshot".to_owned(),
+                content_type: Some("image/png".to_owned()),
+                path: PathBuf::from("/app/screenshots/cart.png"),
+            }],
+            location: Some(SourceLocation {
+                file: "tests/cart_test.py".to_owned(),
+                line: Some(12),
+            }),
+            ..testcase("applies discounts")
+        };
+        let flaky = TestCase {
+            attempts: vec![TestAttempt {
+                kind: AttemptKind::FlakyFailure,
+                failure: failure("timed out"),
+                system_out: None,
+                system_err: None,
+            }],
+            ..testcase("checks out")
+        };
+        let skipped = TestCase {
+            skipped: Some(TestSkipped {
+                message: Some("shipping is disabled".to_owned()),
+                details: None,
+            }),
+            ..testcase("ships abroad")
+        };
+        let nested = suite("checkout", vec![flaky, skipped]);
+        let suite = TestSuite {
+            time: Duration::milliseconds(50),
+            system_out: Some("suite output".to_owned()),
+            metadata: SuiteMetadata {
+                hostname: Some("ci-runner".to_owned()),
+                timestamp: Some(Utc.ymd(2020, 6, 7).and_hms(14, 18, 12)),
+                properties: vec![Property {
+                    name: "java.version".to_owned(),
+                    value: Some("11".to_owned()),
+                }],
+                ..SuiteMetadata::default()
+            },
+            testsuites: vec![nested],
+            ..suite("cart", vec![failed])
+        };
+        fixtures::full_report(
+            vec![suite],
+            vec![unreadable(PathBuf::from("reports/broken.xml"))],
+        )
+    }
+
+    #[test]
+    fn render_merged_report() {
+        let xml = render(&full_report());
+        let expected = concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            r#"<testsuites tests="4" failures="1" errors="1" skipped="1" time="0.050">"#,
+            r#"<testsuite name="cart" tests="1" failures="1" errors="0" skipped="0" time="0.050" timestamp="2020-06-07T14:18:12" hostname="ci-runner">"#,
+            r#"<properties><property name="java.version" value="11" /></properties>"#,
+            r#"<testcase name="applies discounts" classname="com.example.CartTest" time="0.010" file="tests/cart_test.py" line="12">"#,
+            r#"<failure message="expected &lt;90&gt;" type="AssertionError">AssertionError: expected &lt;90>"#,
+            "\n\tat CartTest.java:12</failure>",
+            "<system-out>[31mred[0m\n[[ATTACHMENT|/app/screenshots/cart.png]]</system-out>",
+            "</testcase>",
+            "<system-out>suite output</system-out>",
+            "</testsuite>",
+            r#"<testsuite name="checkout" tests="2" failures="0" errors="0" skipped="1" time="0.020">"#,
+            r#"<testcase name="checks out" classname="com.example.CartTest" time="0.010">"#,
+            r#"<flakyFailure message="timed out" type="AssertionError"><stackTrace>AssertionError: timed out</stackTrace></flakyFailure>"#,
+            "</testcase>",
+            r#"<testcase name="ships abroad" classname="com.example.CartTest" time="0.010">"#,
+            r#"<skipped message="shipping is disabled" />"#,
+            "</testcase>",
+            "</testsuite>",
+            r#"<testsuite name="Unreadable reports" tests="1" failures="0" errors="1" skipped="0" time="0.000">"#,
+            r#"<testcase name="reports/broken.xml" classname="Unreadable reports" time="0.000">"#,
+            r#"<error message="unexpected end of file" />"#,
+            "</testcase>",
+            "</testsuite>",
+            "</testsuites>",
+        );
+        assert_eq!(xml, expected);
+    }
+
+    #[test]
+    fn read_rendered_report() {
+        let xml = render(&full_report());
+        let suites = crate::junit::read_suites(xml.as_bytes(), |_| ()).unwrap();
+        let names: Vec<&str> = suites.iter().map(|s| s.value.name.as_str()).collect();
+        assert_eq!(names, vec!["cart", "checkout", "Unreadable reports"]);
+
+        let mut cart = full_report().all_suites.remove(0).value;
+        cart.testsuites.clear();
+        cart.testcases[0].system_out =
+            Some("[31mred[0m\n[[ATTACHMENT|/app/screenshots/cart.png]]".to_owned());
+        cart.testcases[0].attachments.clear();
+        assert_eq!(suites[0].value, cart);
+        assert_eq!(suites[1].summary.flaky, 1);
+        assert_eq!(suites[2].summary.errors, 1);
+    }
+}
@@ -5,12 +5,17 @@ extern crate colored;
 extern crate glob;
 extern crate reqwest;
 extern crate serde_derive;
-extern crate serde_xml_rs;
+extern crate xml;
 
+pub mod allure;
 pub mod config;
 pub mod console;
+pub mod ctrf;
 pub mod gcs;
 pub mod github;
 pub mod html;
 pub mod junit;
+pub mod junit_xml;
+pub mod markdown;
+pub mod sarif;
 pub mod slack;
@@ -1,12 +1,14 @@
-use cikit::{config::Config, github};
-use cikit::{console::ConsoleJsonReport, github::GithubContext};
-use cikit::{console::ConsoleTextReport, gcs};
-use cikit::{junit, slack::SlackNotifier};
-
-use cikit::html::HTMLReport;
+use cikit::{config::Config, ctrf::CtrfReport, github, junit_xml::JunitXmlReport};
+use cikit::{console::ConsoleJsonReport, github::actions::ActionsEnv, github::GithubContext};
+use cikit::{console::ConsoleSkippedReport, console::ConsoleTextReport, gcs};
+use cikit::{github::actions::ActionsPublisher, junit, markdown, slack::SlackNotifier};
+
+use cikit::{
+    allure::AllureResults, html::HTMLReport, markdown::MarkdownReport, sarif::SarifReport,
+};
 use junit::{FullReport, ReportSorting, SortingOrder};
 
-use std::path::PathBuf;
+use std::{env, path::PathBuf};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -15,10 +17,38 @@ enum Format {
         #[structopt(short, long, help = "time [ASC|DESC]")]
         sort_by: Option<ReportSorting>,
     },
+    /// Lists the skipped tests grouped by skip reason
+    Skipped,
     Json {
         #[structopt(short, long, help = "do not pretty print json")]
         compact: bool,
     },
+    /// Renders the report as a Common Test Report Format (CTRF) document
+    Ctrf {
+        #[structopt(short, long, help = "do not pretty print json")]
+        compact: bool,
+    },
+    /// Renders the report as markdown, e.g. for PR descriptions
+    Markdown {
+        #[structopt(
+            long,
+            help = "maximum size of the report in bytes. Defaults to the size limit of GitHub comments"
+        )]
+        max_size: Option<usize>,
+    },
+    /// Annotates the failed tests, and sets the job summary and step outputs
+    /// of the GitHub Actions workflow running the tests
+    GithubActions,
+    /// Renders the failed tests as a SARIF log, for code scanning
+    Sarif {
+        #[structopt(short, long, help = "do not pretty print json")]
+        compact: bool,
+    },
+    /// Merges all the reports into a single JUnit XML document
+    Junit {
+        #[structopt(short, long, help = "do not pretty print xml")]
+        compact: bool,
+    },
     Html {
         #[structopt(
             short,
@@ -32,6 +62,26 @@ enum Format {
             help = "overwrite the output directory content if the directory exists"
         )]
         force: bool,
+        #[structopt(
+            long,
+            help = "also report to the GitHub Actions workflow running the tests, linking to the published report"
+        )]
+        github_actions: bool,
+    },
+    /// Writes the report as an Allure results directory
+    Allure {
+        #[structopt(
+            short,
+            long,
+            help = "output directory of the Allure results. Defaults to 'allure-results'"
+        )]
+        output_dir: Option<PathBuf>,
+        #[structopt(
+            short,
+            long,
+            help = "overwrite the output directory content if the directory exists"
+        )]
+        force: bool,
     },
 }
 
@@ -78,7 +128,12 @@ fn main() -> anyhow::Result<()> {
             format,
             github_event_file,
         } => {
-            let (test_suites, summary) = junit::read_testsuites(opt.project_dir, &config)?;
+            let project_dir = match &opt.project_dir {
+                Some(project_dir) => project_dir.clone(),
+                None => env::current_dir()?,
+            };
+            let (test_suites, unreadable_reports, summary) =
+                junit::read_testsuites(opt.project_dir, &config)?;
             let github_ctx = if let Some(github_event_file) = github_event_file {
                 Some(GithubContext::from_file(github_event_file)?)
             } else {
@@ -87,7 +142,8 @@ fn main() -> anyhow::Result<()> {
 
             let github_run_id = github_ctx.as_ref().map(|c| c.run_id.clone());
             let github_event = github_ctx.as_ref().map(|c| c.event.clone());
-            let mut full_report = FullReport::new(test_suites, summary, github_event);
+            let mut full_report =
+                FullReport::new(test_suites, unreadable_reports, summary, github_event);
 
             match format {
                 Format::Text { sort_by } => {
@@ -96,11 +152,32 @@ fn main() -> anyhow::Result<()> {
                     }
                     ConsoleTextReport::stdout().render(&full_report)
                 }
+                Format::Skipped => ConsoleSkippedReport::stdout().render(&full_report),
                 Format::Json { compact } => ConsoleJsonReport::stdout(compact).render(&full_report),
-                Format::Html { output_dir, force } => {
+                Format::Ctrf { compact } => CtrfReport::stdout(compact).render(&full_report),
+                Format::Markdown { max_size } => {
+                    let max_size = max_size.unwrap_or(markdown::GITHUB_COMMENT_MAX_SIZE);
+                    MarkdownReport::stdout(max_size).render(&full_report)
+                }
+                Format::GithubActions => {
+                    ActionsPublisher::stdout(actions_env()?).publish(&full_report, None)
+                }
+                Format::Sarif { compact } => {
+                    SarifReport::stdout(compact, project_dir).render(&full_report)
+                }
+                Format::Junit { compact } => JunitXmlReport::stdout(compact).render(&full_report),
+                Format::Allure { output_dir, force } => {
+                    let output_dir = output_dir.unwrap_or_else(|| PathBuf::from("allure-results"));
+                    AllureResults::new(output_dir, force)?.write(&full_report)
+                }
+                Format::Html {
+                    output_dir,
+                    force,
+                    github_actions,
+                } => {
                     //FIXME: avoid PathBuf, use AsRef!
                     let output_dir = output_dir.unwrap_or_else(|| PathBuf::from("report"));
-                    let report = HTMLReport::new(output_dir.clone(), force)?;
+                    let report = HTMLReport::new(output_dir.clone(), force, project_dir)?;
                     report.write(&full_report)?;
 
                     let report_url = if let Some((config, github_run_id)) =
@@ -114,6 +191,11 @@ fn main() -> anyhow::Result<()> {
                         None
                     };
 
+                    if github_actions {
+                        ActionsPublisher::stdout(actions_env()?)
+                            .publish(&full_report, report_url.as_ref())?;
+                    }
+
                     if let Some((config, github_ctx)) = config
                         .notifications
                         .github_comments
@@ -121,11 +203,7 @@ fn main() -> anyhow::Result<()> {
                     {
                         let mut comment_publisher = github::comments::CommentPublisher::new(config);
 
-                        comment_publisher.publish(
-                            &full_report,
-                            &github_ctx,
-                            report_url.as_ref(),
-                        )?;
+                        comment_publisher.publish(&full_report, github_ctx, report_url.as_ref())?;
                     }
 
                     if let Some((config, github_ctx)) = config.notifications.slack.zip(github_ctx) {
@@ -140,3 +218,8 @@ fn main() -> anyhow::Result<()> {
         }
     }
 }
+
+fn actions_env() -> anyhow::Result<ActionsEnv> {
+    ActionsEnv::from_env()
+        .ok_or_else(|| anyhow::anyhow!("Not running in a GitHub Actions workflow"))
+}
@@ -0,0 +1,399 @@
+use crate::{
+    gcs::ReportUrl,
+    junit::{display, FailedTestCase, FullReport, HasOutcome, TestCase, TestSuite},
+};
+use chrono::Duration;
+use std::{cmp::Reverse, io};
+
+/// GitHub rejects comments longer than this.
+pub const GITHUB_COMMENT_MAX_SIZE: usize = 65_536;
+/// GitHub only displays job summaries up to this size.
+pub const GITHUB_STEP_SUMMARY_MAX_SIZE: usize = 1_048_576;
+
+const SLOWEST_TESTS: usize = 10;
+const STACK_TRACE_MAX_LINES: usize = 50;
+/// Room kept for the note listing the failed tests left out.
+const OMITTED_NOTE_SIZE: usize = 64;
+
+/// Renders the report as GitHub flavoured markdown, for PR comments, job
+/// summaries or release notes. Failed tests which do not fit within
+/// `max_size` bytes are left out, the slowest tests table first.
+pub struct MarkdownReport {
+    max_size: usize,
+    sink: Box<dyn io::Write>,
+}
+impl MarkdownReport {
+    pub fn sink_to(max_size: usize, sink: Box<dyn io::Write>) -> Self {
+        MarkdownReport { max_size, sink }
+    }
+    pub fn stdout(max_size: usize) -> Self {
+        MarkdownReport::sink_to(max_size, Box::new(io::stdout()))
+    }
+}
+
+impl MarkdownReport {
+    pub fn render(&mut self, full_report: &FullReport) -> anyhow::Result<()> {
+        write!(self.sink, "{}", document(full_report, self.max_size))?;
+        Ok(())
+    }
+}
+
+pub fn document(full_report: &FullReport, max_size: usize) -> String {
+    let mut doc = headline(full_report);
+    doc.push_str(&summary_table(full_report));
+    doc.push_str(&unreadable_reports(full_report));
+    let slowest = slowest_tests(full_report);
+
+    // failed tests come first, the slowest tests only taking what is left
+    let blocks = failed_tests(full_report);
+    let mut failed = String::new();
+    let mut omitted = 0;
+    for block in &blocks {
+        let size = doc.len() + failed.len() + block.len() + OMITTED_NOTE_SIZE;
+        if omitted == 0 && size <= max_size {
+            failed.push_str(block);
+        } else {
+            omitted += 1;
+        }
+    }
+    if !blocks.is_empty() {
+        doc.push_str("#### Failed tests\n\n");
+        doc.push_str(&failed);
+        if omitted > 0 {
+            doc.push_str(&format!(
+                "_{} more failed tests are not shown._\n\n",
+                omitted
+            ));
+        }
+    }
+    if doc.len() + slowest.len() <= max_size {
+        doc.push_str(&slowest);
+    }
+    truncate(&mut doc, max_size);
+    doc
+}
+
+/// The document followed by a link to the published report, if any, the
+/// link counting towards `max_size`.
+pub fn document_with_link(
+    full_report: &FullReport,
+    max_size: usize,
+    report_url: Option<&ReportUrl>,
+) -> String {
+    let link = report_url
+        .map(|report_url| format!(":bookmark_tabs: [Test report]({})\n", report_url.0))
+        .unwrap_or_default();
+    let mut doc = document(full_report, max_size.saturating_sub(link.len()));
+    doc.push_str(&link);
+    doc
+}
+
+fn headline(full_report: &FullReport) -> String {
+    let summary = &full_report.summary;
+    let mut headline = if full_report.is_successful() {
+        "### :heavy_check_mark: Test suite passed!\n\n".to_owned()
+    } else {
+        format!(
+            "### :x: Test suite failed with _{}_ failures and _{}_ errors\n\n",
+            summary.failures, summary.errors
+        )
+    };
+    if summary.flaky > 0 {
+        headline.push_str(&format!(
+            ":warning: _{}_ flaky tests passed on re-run\n\n",
+            summary.flaky
+        ));
+    }
+    headline
+}
+
+/// Unreadable reports are counted as errors by the summary, and are counted
+/// as tests here too so that the columns add up.
+fn summary_table(full_report: &FullReport) -> String {
+    let summary = &full_report.summary;
+    let tests = summary.tests + full_report.unreadable_reports.len();
+    let passed =
+        tests.saturating_sub(summary.failures + summary.errors + summary.skipped + summary.flaky);
+    format!(
+        "| Tests | Passed | Failed | Errors | Skipped | Flaky | Duration |\n\
+         | ---: | ---: | ---: | ---: | ---: | ---: | ---: |\n\
+         | {} | {} | {} | {} | {} | {} | {} |\n\n",
+        tests,
+        passed,
+        summary.failures,
+        summary.errors,
+        summary.skipped,
+        summary.flaky,
+        duration(summary.time)
+    )
+}
+
+fn unreadable_reports(full_report: &FullReport) -> String {
+    let reports = &full_report.unreadable_reports;
+    if reports.is_empty() {
+        return String::new();
+    }
+    let mut section = format!(
+        "<details>\n<summary>:warning: <em>{}</em> report files could not be read</summary>\n\n",
+        reports.len()
+    );
+    for report in reports {
+        section.push_str(&format!(
+            "- `{}`: {}\n",
+            report.path.display(),
+            html_text(&report.error)
+        ));
+    }
+    section.push_str("\n</details>\n\n");
+    section
+}
+
+/// One block per failed test, the first of each suite naming it.
+fn failed_tests(full_report: &FullReport) -> Vec<String> {
+    let mut blocks = Vec::new();
+    for suite in &full_report.failed {
+        for (i, testcase) in suite.value.failed_testcases.iter().enumerate() {
+            let mut block = String::new();
+            if i == 0 {
+                block.push_str(&format!("**{}**\n\n", html_text(&suite.value.name)));
+            }
+            block.push_str(&failed_test(testcase));
+            blocks.push(block);
+        }
+    }
+    blocks
+}
+
+fn failed_test(testcase: &FailedTestCase) -> String {
+    let failure = &testcase.failure;
+    let message = failure
+        .message
+        .as_deref()
+        .and_then(|m| m.lines().map(str::trim).find(|l| !l.is_empty()))
+        .map(|m| format!(": {}", html_text(m)))
+        .unwrap_or_default();
+    let mut block = format!(
+        "<details>\n<summary>:x: <code>{}</code>{}</summary>\n\n",
+        html_text(&testcase.name),
+        message
+    );
+    let stack_trace = if failure.stack_trace.trim().is_empty() {
+        failure.message.as_deref().unwrap_or_default()
+    } else {
+        failure.stack_trace.as_str()
+    };
+    if !stack_trace.trim().is_empty() {
+        block.push_str(&code_block(stack_trace));
+    }
+    block.push_str("</details>\n\n");
+    block
+}
+
+/// Long stack traces are cut down to their first lines.
+fn code_block(text: &str) -> String {
+    let lines: Vec<&str> = text.trim_end().lines().collect();
+    let mut code = lines
+        .iter()
+        .take(STACK_TRACE_MAX_LINES)
+        .cloned()
+        .collect::<Vec<&str>>()
+        .join("\n");
+    if lines.len() > STACK_TRACE_MAX_LINES {
+        code.push_str(&format!(
+            "\n... {} more lines",
+            lines.len() - STACK_TRACE_MAX_LINES
+        ));
+    }
+    // the fence has to be longer than any run of backticks in the text
+    let longest_run = code
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{}text\n{}\n{}\n\n", fence, code, fence)
+}
+
+fn slowest_tests(full_report: &FullReport) -> String {
+    let mut testcases: Vec<(&str, &TestCase)> = Vec::new();
+    for suite in &full_report.all_suites {
+        collect_testcases(&suite.value, &mut testcases);
+    }
+    testcases.retain(|(_, t)| t.skipped.is_none());
+    if testcases.is_empty() {
+        return String::new();
+    }
+    testcases.sort_by_key(|(_, t)| Reverse(t.time));
+
+    let mut table =
+        "#### Slowest tests\n\n| Test | Suite | Duration |\n| --- | --- | ---: |\n".to_owned();
+    for (suite, testcase) in testcases.iter().take(SLOWEST_TESTS) {
+        table.push_str(&format!(
+            "| {} | {} | {} |\n",
+            table_cell(&testcase.name),
+            table_cell(suite),
+            duration(testcase.time)
+        ));
+    }
+    table.push('\n');
+    table
+}
+
+fn collect_testcases<'a>(suite: &'a TestSuite, testcases: &mut Vec<(&'a str, &'a TestCase)>) {
+    testcases.extend(suite.testcases.iter().map(|t| (suite.name.as_str(), t)));
+    for nested in &suite.testsuites {
+        collect_testcases(nested, testcases);
+    }
+}
+
+fn duration(time: Duration) -> String {
+    if time < Duration::milliseconds(1) {
+        "0ms".to_owned()
+    } else {
+        display::duration(time.to_std().unwrap())
+    }
+}
+
+fn html_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Table cells hold a single line, in which pipes would end the cell.
+fn table_cell(text: &str) -> String {
+    html_text(&text.split_whitespace().collect::<Vec<&str>>().join(" ")).replace('|', "\\|")
+}
+
+/// Last resort when even the summary does not fit.
+fn truncate(doc: &mut String, max_size: usize) {
+    if doc.len() > max_size {
+        let mut end = max_size;
+        while !doc.is_char_boundary(end) {
+            end -= 1;
+        }
+        doc.truncate(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    use super::*;
+    use crate::junit::{
+        fixtures::{self, failure, suite, testcase, unreadable},
+        TestFailure, TestSkipped,
+    };
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    fn timed(name: &str, millis: i64) -> TestCase {
+        TestCase {
+            time: Duration::milliseconds(millis),
+            ..testcase(name)
+        }
+    }
+
+    fn failed(name: &str, stack_trace: &str) -> TestCase {
+        TestCase {
+            failure: Some(TestFailure {
+                stack_trace: stack_trace.to_owned(),
+                ..failure("expected <90> | got 100")
+            }),
+            ..timed(name, 20)
+        }
+    }
+
+    fn full_report(testcases: Vec<TestCase>) -> FullReport {
+        fixtures::full_report(vec![suite("cart", testcases)], vec![])
+    }
+
+    #[test]
+    fn render_failed_report() {
+        let skipped = TestCase {
+            skipped: Some(TestSkipped::default()),
+            ..timed("ships abroad", 0)
+        };
+        let testcases = vec![
+            timed("adds items", 1200),
+            failed(
+                "applies discounts",
+                "AssertionError: expected <90>\n\tat CartTest.java:12",
+            ),
+            skipped,
+        ];
+        let full_report = fixtures::full_report(
+            vec![suite("cart", testcases)],
+            vec![unreadable(PathBuf::from("reports/broken.xml"))],
+        );
+
+        let expected = concat!(
+            "### :x: Test suite failed with _1_ failures and _1_ errors\n\n",
+            "| Tests | Passed | Failed | Errors | Skipped | Flaky | Duration |\n",
+            "| ---: | ---: | ---: | ---: | ---: | ---: | ---: |\n",
+            "| 4 | 1 | 1 | 1 | 1 | 0 | 1s 220ms |\n\n",
+            "<details>\n<summary>:warning: <em>1</em> report files could not be read</summary>\n\n",
+            "- `reports/broken.xml`: unexpected end of file\n",
+            "\n</details>\n\n",
+            "#### Failed tests\n\n",
+            "**cart**\n\n",
+            "<details>\n<summary>:x: <code>applies discounts</code>: expected &lt;90&gt; | got 100</summary>\n\n",
+            "```text\nAssertionError: expected <90>\n\tat CartTest.java:12\n```\n\n",
+            "</details>\n\n",
+            "#### Slowest tests\n\n",
+            "| Test | Suite | Duration |\n| --- | --- | ---: |\n",
+            "| adds items | cart | 1s 200ms |\n",
+            "| applies discounts | cart | 20ms |\n\n",
+        );
+        assert_eq!(document(&full_report, GITHUB_COMMENT_MAX_SIZE), expected);
+    }
+
+    #[test]
+    fn leave_out_failed_tests_beyond_max_size() {
+        let stack_trace = "AssertionError\n".repeat(80);
+        let testcases = (0..20)
+            .map(|i| failed(&format!("test {}", i), &stack_trace))
+            .collect();
+        let full_report = full_report(testcases);
+
+        let doc = document(&full_report, 8_000);
+        assert!(doc.len() <= 8_000);
+        let shown = doc.matches("<details>").count();
+        assert!(shown > 0);
+        assert!(doc.contains(&format!(
+            "_{} more failed tests are not shown._",
+            20 - shown
+        )));
+        assert!(doc.contains("... 30 more lines\n```"));
+
+        let doc = document(&full_report, 100);
+        assert_eq!(doc.len(), 100);
+        assert!(doc.starts_with("### :x: Test suite failed"));
+    }
+
+    #[test]
+    fn prefer_failed_tests_to_slowest_tests() {
+        let full_report = full_report(vec![
+            timed("adds items", 1200),
+            failed("applies discounts", "AssertionError: expected <90>"),
+        ]);
+        let whole = document(&full_report, GITHUB_COMMENT_MAX_SIZE);
+        let slowest_at = whole.find("#### Slowest tests").unwrap();
+
+        // room for the failed test, but not for the slowest tests
+        let doc = document(&full_report, slowest_at + OMITTED_NOTE_SIZE);
+        assert_eq!(doc, &whole[..slowest_at]);
+        assert!(doc.contains("<code>applies discounts</code>"));
+
+        assert_eq!(document(&full_report, whole.len()), whole);
+    }
+
+    #[test]
+    fn fence_stack_traces_containing_backticks() {
+        assert_eq!(
+            code_block("expected ```x``` to be `y`"),
+            "````text\nexpected ```x``` to be `y`\n````\n\n"
+        );
+    }
+}
@@ -0,0 +1,420 @@
+use crate::junit::{FailedTestCase, FullReport, SourceLocation, UnreadableReport};
+use serde::Serialize;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_VERSION: &str = "2.1.0";
+/// Failures which do not name their type
+const TEST_FAILURE_RULE: &str = "TestFailure";
+const UNREADABLE_REPORT_RULE: &str = "UnreadableReport";
+
+/// Renders the failed tests as a Static Analysis Results Interchange Format
+/// (SARIF) log, for code scanning dashboards. Source files are located
+/// within `project_dir`, the way they are checked out.
+pub struct SarifReport {
+    compact: bool,
+    project_dir: PathBuf,
+    sink: Box<dyn io::Write>,
+}
+impl SarifReport {
+    pub fn sink_to(compact: bool, project_dir: PathBuf, sink: Box<dyn io::Write>) -> Self {
+        SarifReport {
+            compact,
+            project_dir,
+            sink,
+        }
+    }
+    pub fn stdout(compact: bool, project_dir: PathBuf) -> Self {
+        SarifReport::sink_to(compact, project_dir, Box::new(io::stdout()))
+    }
+}
+
+impl SarifReport {
+    pub fn render(&mut self, full_report: &FullReport) -> anyhow::Result<()> {
+        let log = Log::new(full_report, &self.project_dir);
+        if self.compact {
+            serde_json::ser::to_writer(&mut self.sink, &log)?;
+        } else {
+            serde_json::ser::to_writer_pretty(&mut self.sink, &log)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Log {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+#[derive(Debug, Serialize)]
+struct Run {
+    tool: Tool,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: String,
+    short_description: Message,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: String,
+    rule_index: usize,
+    level: &'static str,
+    message: Message,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    locations: Vec<Location>,
+    properties: ResultProperties,
+}
+
+/// What cikit knows of a failure besides what SARIF describes.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultProperties {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suite: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    testcase: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    classname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stack_trace: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<Region>,
+}
+
+#[derive(Debug, Serialize)]
+struct ArtifactLocation {
+    uri: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: u32,
+}
+
+impl Log {
+    fn new(full_report: &FullReport, project_dir: &Path) -> Self {
+        let mut rules: Vec<Rule> = Vec::new();
+        let mut results = Vec::new();
+        for report in &full_report.unreadable_reports {
+            results.push(unreadable_result(report, project_dir, &mut rules));
+        }
+        for suite in &full_report.failed {
+            for testcase in &suite.value.failed_testcases {
+                results.push(failed_result(
+                    &suite.value.name,
+                    testcase,
+                    project_dir,
+                    &mut rules,
+                ));
+            }
+        }
+
+        Log {
+            schema: SCHEMA,
+            version: SARIF_VERSION,
+            runs: vec![Run {
+                tool: Tool {
+                    driver: Driver {
+                        name: "cikit",
+                        version: env!("CARGO_PKG_VERSION"),
+                        rules,
+                    },
+                },
+                results,
+            }],
+        }
+    }
+}
+
+/// Rules are listed in the order results first refer to them.
+fn rule_index(id: &str, description: &str, rules: &mut Vec<Rule>) -> usize {
+    match rules.iter().position(|r| r.id == id) {
+        Some(index) => index,
+        None => {
+            rules.push(Rule {
+                id: id.to_owned(),
+                short_description: Message {
+                    text: description.to_owned(),
+                },
+            });
+            rules.len() - 1
+        }
+    }
+}
+
+fn failed_result(
+    suite: &str,
+    testcase: &FailedTestCase,
+    project_dir: &Path,
+    rules: &mut Vec<Rule>,
+) -> SarifResult {
+    let failure = &testcase.failure;
+    let rule_id = Some(failure.classname.trim())
+        .filter(|c| !c.is_empty())
+        .unwrap_or(TEST_FAILURE_RULE);
+    let description = format!("Tests failing with {}", rule_id);
+    let rule_index = rule_index(rule_id, &description, rules);
+    let message = failure
+        .message
+        .as_deref()
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .or_else(|| failure.stack_trace.lines().next())
+        .unwrap_or("The test failed");
+
+    SarifResult {
+        rule_id: rule_id.to_owned(),
+        rule_index,
+        level: "error",
+        message: Message {
+            text: format!("{} > {}: {}", suite, testcase.name, message),
+        },
+        locations: testcase
+            .source_location(project_dir)
+            .map(location)
+            .into_iter()
+            .collect(),
+        properties: ResultProperties {
+            suite: Some(suite.to_owned()),
+            testcase: Some(testcase.name.clone()),
+            classname: Some(testcase.classname.clone()),
+            stack_trace: Some(failure.stack_trace.clone()).filter(|s| !s.trim().is_empty()),
+        },
+    }
+}
+
+/// Located at the report file itself, which may hide any number of failures,
+/// unless the report lies outside of the project.
+fn unreadable_result(
+    report: &UnreadableReport,
+    project_dir: &Path,
+    rules: &mut Vec<Rule>,
+) -> SarifResult {
+    let rule_index = rule_index(
+        UNREADABLE_REPORT_RULE,
+        "Test reports which could not be read",
+        rules,
+    );
+    let relative_path = match report.path.strip_prefix(project_dir) {
+        Ok(path) => Some(path),
+        Err(_) if report.path.is_relative() => Some(report.path.as_path()),
+        Err(_) => None,
+    };
+    let path = relative_path.unwrap_or(&report.path);
+    SarifResult {
+        rule_id: UNREADABLE_REPORT_RULE.to_owned(),
+        rule_index,
+        level: "error",
+        message: Message {
+            text: format!("Couldn't read {}: {}", path.display(), report.error),
+        },
+        locations: relative_path
+            .map(|path| {
+                location(SourceLocation {
+                    file: path.to_string_lossy().into_owned(),
+                    line: None,
+                })
+            })
+            .into_iter()
+            .collect(),
+        properties: ResultProperties {
+            suite: None,
+            testcase: None,
+            classname: None,
+            stack_trace: None,
+        },
+    }
+}
+
+/// SARIF URIs use forward slashes, whatever the platform.
+fn location(location: SourceLocation) -> Location {
+    Location {
+        physical_location: PhysicalLocation {
+            artifact_location: ArtifactLocation {
+                uri: location.file.replace('\\', "/"),
+            },
+            region: location.line.map(|start_line| Region { start_line }),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate pretty_assertions;
+
+    extern crate uuid;
+
+    use super::*;
+    use crate::junit::{
+        fixtures::{self, failure, suite, testcase, unreadable},
+        TestCase, TestFailure,
+    };
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+    use std::{env, fs};
+    use uuid::Uuid;
+
+    fn failed(name: &str, classname: &str, location: Option<SourceLocation>) -> TestCase {
+        TestCase {
+            classname: "tests.test_cart".to_owned(),
+            failure: Some(TestFailure {
+                classname: classname.to_owned(),
+                ..failure("assert 100 == 90")
+            }),
+            location,
+            ..testcase(name)
+        }
+    }
+
+    fn project_dir() -> PathBuf {
+        let mut dir = env::temp_dir();
+        dir.push(format!("cikit/sarif-{}", Uuid::new_v4()));
+        fs::create_dir_all(dir.join("tests")).unwrap();
+        fs::write(dir.join("tests/test_cart.py"), "").unwrap();
+        dir
+    }
+
+    fn full_report(project_dir: &Path) -> FullReport {
+        let testcases = vec![
+            failed(
+                "test_discounts",
+                "AssertionError",
+                Some(SourceLocation {
+                    file: "tests/test_cart.py".to_owned(),
+                    line: Some(12),
+                }),
+            ),
+            failed(
+                "test_totals",
+                "",
+                Some(SourceLocation {
+                    file: "/usr/lib/python3/unittest.py".to_owned(),
+                    line: Some(3),
+                }),
+            ),
+            failed("test_checkout", "AssertionError", None),
+        ];
+        fixtures::full_report(
+            vec![suite("tests.test_cart", testcases)],
+            vec![unreadable(project_dir.join("reports/broken.xml"))],
+        )
+    }
+
+    #[test]
+    fn render_failed_tests() {
+        let project_dir = project_dir();
+        let log = Log::new(&full_report(&project_dir), &project_dir);
+        let value: Value = serde_json::to_value(&log).unwrap();
+
+        assert_eq!(value["version"], "2.1.0");
+        let rules: Vec<&str> = value["runs"][0]["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            rules,
+            vec!["UnreadableReport", "AssertionError", "TestFailure"]
+        );
+
+        let results = &value["runs"][0]["results"];
+        assert_eq!(
+            results[0],
+            json!({
+                "ruleId": "UnreadableReport",
+                "ruleIndex": 0,
+                "level": "error",
+                "message": { "text": "Couldn't read reports/broken.xml: unexpected end of file" },
+                "locations": [{ "physicalLocation": { "artifactLocation": { "uri": "reports/broken.xml" } } }],
+                "properties": {}
+            })
+        );
+        assert_eq!(
+            results[1],
+            json!({
+                "ruleId": "AssertionError",
+                "ruleIndex": 1,
+                "level": "error",
+                "message": { "text": "tests.test_cart > test_discounts: assert 100 == 90" },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": "tests/test_cart.py" },
+                        "region": { "startLine": 12 }
+                    }
+                }],
+                "properties": {
+                    "suite": "tests.test_cart",
+                    "testcase": "test_discounts",
+                    "classname": "tests.test_cart",
+                    "stackTrace": "AssertionError: assert 100 == 90"
+                }
+            })
+        );
+        assert_eq!(results[2]["ruleId"], "TestFailure");
+        assert_eq!(results[2]["ruleIndex"], 2);
+        assert!(results[2].get("locations").is_none());
+        assert_eq!(results[3]["ruleIndex"], 1);
+        assert!(results[3].get("locations").is_none());
+    }
+
+    #[test]
+    fn leave_out_unreadable_reports_outside_of_the_project() {
+        let project_dir = project_dir();
+        let full_report = fixtures::full_report(
+            vec![],
+            vec![unreadable(PathBuf::from("/var/tmp/reports/broken.xml"))],
+        );
+        let value = serde_json::to_value(Log::new(&full_report, &project_dir)).unwrap();
+
+        let result = &value["runs"][0]["results"][0];
+        assert_eq!(
+            result["message"]["text"],
+            "Couldn't read /var/tmp/reports/broken.xml: unexpected end of file"
+        );
+        assert!(result.get("locations").is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::junit::{self, FailedTestSuite, Summary};
+use crate::junit::{self, FailedTestSuite, HasOutcome, Summary, TestOutcome, UnreadableReport};
 use crate::{config, junit::FullReport};
 use crate::{gcs::ReportUrl, github::GithubContext};
 use serde_derive::Deserialize;
@@ -47,6 +47,18 @@ impl Block {
             fields: vec![],
         }
     }
+    fn unreadable_reports(reports: &[UnreadableReport]) -> Block {
+        let mut mrkdwn = "*Unreadable reports:*\n".to_owned();
+
+        for report in reports {
+            mrkdwn.push_str(&format!("- `{}`\n", report.path.display()))
+        }
+
+        Block::Section {
+            text: Text::mrkdwn(&mrkdwn),
+            fields: vec![],
+        }
+    }
     fn headline_with_summary(headline: &str, summary: &Summary) -> Block {
         Block::Section {
             text: Text::mrkdwn(headline),
@@ -117,65 +129,69 @@ impl SlackNotifier {
         ctx: &GithubContext,
         _report_url: Option<&ReportUrl>,
     ) -> anyhow::Result<()> {
-        match &self.config {
-            config::SlackNotifications {
-                user_handles,
-                webhook_url,
-            } => {
-                let mut headline = String::new();
-                if let Some(slack_handle) = user_handles.get(&ctx.actor) {
-                    headline.push_str(&format!("<@{}> ", slack_handle))
-                }
-                headline.push_str(&format!(
-                    "build for PR <{}|{}>",
-                    ctx.event.pull_request.html_url, ctx.event.pull_request.title
-                ));
-
-                if full_report.is_successful() {
-                    headline.push_str(" :heavy_tick:");
-                } else {
-                    headline.push_str(" :heavy_exclamation_mark:");
-                }
-                let summary_block = Block::headline_with_summary(&headline, &full_report.summary);
-
-                let message: Blocks = if full_report.is_successful() {
-                    Blocks {
-                        blocks: vec![summary_block, Block::Divider],
-                    }
-                } else {
-                    let failed_suites_blocks: Vec<Block> = full_report
-                        .failed
-                        .iter()
-                        .map(|suite| Block::failed_testsuite(&suite.value))
-                        .collect();
-
-                    let mut blocks = vec![
-                        summary_block,
-                        Block::Divider,
-                        Block::Section {
-                            text: Text::mrkdwn("Failed test suites:"),
-                            fields: vec![],
-                        },
-                        Block::Divider,
-                    ];
-
-                    blocks.extend(failed_suites_blocks);
-
-                    Blocks { blocks }
-                };
-
-                let mut response_body = String::new();
-                let mut resp = self.client.post(webhook_url).json(&message).send()?;
-                if !resp.status().is_success() {
-                    resp.read_to_string(&mut response_body)?;
-                    warn!(
-                        "Server responded with non-successful status code: {}",
-                        response_body
-                    );
-                }
-                Ok(())
+        let config::SlackNotifications {
+            user_handles,
+            webhook_url,
+        } = &self.config;
+        let mut headline = String::new();
+        if let Some(slack_handle) = user_handles.get(&ctx.actor) {
+            headline.push_str(&format!("<@{}> ", slack_handle))
+        }
+        headline.push_str(&format!(
+            "build for PR <{}|{}>",
+            ctx.event.pull_request.html_url, ctx.event.pull_request.title
+        ));
+
+        headline.push_str(match full_report.outcome() {
+            TestOutcome::Failed | TestOutcome::Errored => " :heavy_exclamation_mark:",
+            TestOutcome::Flaky => " :warning:",
+            TestOutcome::Passed | TestOutcome::Skipped => " :heavy_tick:",
+        });
+        if full_report.summary.flaky > 0 {
+            headline.push_str(&format!(" ({} flaky tests)", full_report.summary.flaky));
+        }
+        let summary_block = Block::headline_with_summary(&headline, &full_report.summary);
+
+        let message: Blocks = if full_report.is_successful() {
+            Blocks {
+                blocks: vec![summary_block, Block::Divider],
             }
+        } else {
+            let failed_suites_blocks: Vec<Block> = full_report
+                .failed
+                .iter()
+                .map(|suite| Block::failed_testsuite(&suite.value))
+                .collect();
+
+            let mut blocks = vec![
+                summary_block,
+                Block::Divider,
+                Block::Section {
+                    text: Text::mrkdwn("Failed test suites:"),
+                    fields: vec![],
+                },
+                Block::Divider,
+            ];
+
+            blocks.extend(failed_suites_blocks);
+            if !full_report.unreadable_reports.is_empty() {
+                blocks.push(Block::Divider);
+                blocks.push(Block::unreadable_reports(&full_report.unreadable_reports));
+            }
+
+            Blocks { blocks }
+        };
+
+        let mut response_body = String::new();
+        let mut resp = self.client.post(webhook_url).json(&message).send()?;
+        if !resp.status().is_success() {
+            resp.read_to_string(&mut response_body)?;
+            warn!(
+                "Server responded with non-successful status code: {}",
+                response_body
+            );
         }
+        Ok(())
     }
 }
 
@@ -2,9 +2,10 @@ import React from 'react';
 import './App.css';
 import * as FailedSuites from './components/FailedSuites';
 import * as AllSuites from './components/AllSuites';
-import { FailedTestSuite, GithubContext, Summary, TestSuite } from './dtos';
+import { FailedTestSuite, GithubContext, Summary, TestSuite, UnreadableReport } from './dtos';
 import { SummaryFragment } from './components/Summary';
 import { GithubContextFragment } from './components/GithubContext';
+import { UnreadableReportsFragment } from './components/UnreadableReports';
 
 interface AppProps {
   datasetUri: string
@@ -12,6 +13,7 @@ interface AppProps {
 interface AppState {
   failed: FailedTestSuite[];
   all: TestSuite[];
+  unreadable: UnreadableReport[];
   summary: Summary;
   githubEvent: GithubContext | null;
 }
@@ -27,9 +29,11 @@ class App extends React.Component<AppProps, AppState> {
         failures: 0,
         errors: 0,
         skipped: 0,
+        flaky: 0,
       },
       failed: [],
       all: [],
+      unreadable: [],
       githubEvent: null
     };
   }
@@ -41,6 +45,7 @@ class App extends React.Component<AppProps, AppState> {
         summary: result.summary,
         failed: result.failed,
         all: result.allSuites,
+        unreadable: result.unreadableReports,
         githubEvent: result.githubEvent
       }));
   }
@@ -51,6 +56,7 @@ class App extends React.Component<AppProps, AppState> {
         <GithubContextFragment context={this.state.githubEvent} />
         <SummaryFragment summary={this.state.summary} />
         {(this.state.failed.length > 0) ? <FailedSuites.Component failed={this.state.failed} /> : (<></>)}
+        <UnreadableReportsFragment reports={this.state.unreadable} />
         <AllSuites.Component all={this.state.all} />
       </section>
     );
@@ -1,11 +1,40 @@
 import React from "react";
 import { TestSuite } from "../dtos";
 import { showDuration } from "../utils";
+import { EnvironmentPanel } from "./Environment";
 
 interface Props {
     all: TestSuite[]
 }
 
+interface RowsProps {
+    suite: TestSuite;
+    depth: number;
+    keyPrefix: string;
+}
+
+const SuiteRows = (props: RowsProps): JSX.Element => {
+    let suite = props.suite;
+    let key = props.keyPrefix + "/" + suite.name;
+    return (
+        <>
+            <tr key={key + "-all-tests"} className={props.depth > 0 ? "nested" : undefined}>
+                <td style={{ paddingLeft: (props.depth + 1) + "em" }}>
+                    {suite.name}
+                    <EnvironmentPanel metadata={suite.metadata} />
+                </td>
+                <td>{suite.tests}</td>
+                <td>{suite.failures + suite.errors}</td>
+                <td>{suite.skipped}</td>
+                <td>{showDuration(suite.time)}</td>
+            </tr>
+            {suite.testsuites.map(nested => {
+                return (<SuiteRows key={key + "/" + nested.name} suite={nested} depth={props.depth + 1} keyPrefix={key} />)
+            })}
+        </>
+    )
+}
+
 export class Component extends React.Component<Props, any> {
     render() {
         return (
@@ -23,15 +52,7 @@ export class Component extends React.Component<Props, any> {
                     </thead>
                     <tbody>
                         {this.props.all.map(suite => {
-                            return (
-                                <tr key={suite.name + "-all-tests"}>
-                                    <td>{suite.name}</td>
-                                    <td>{suite.tests}</td>
-                                    <td>{suite.failures}</td>
-                                    <td>{suite.skipped}</td>
-                                    <td>{showDuration(suite.time)}</td>
-                                </tr>
-                            )
+                            return (<SuiteRows key={suite.name} suite={suite} depth={0} keyPrefix="" />)
                         })
                         }
                     </tbody>
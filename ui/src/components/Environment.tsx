@@ -0,0 +1,42 @@
+import React from 'react';
+import { SuiteMetadata } from '../dtos';
+
+interface Props {
+    metadata: SuiteMetadata;
+}
+
+export const EnvironmentPanel = (props: Props) => {
+    let metadata = props.metadata;
+    let attributes: [string, string | undefined][] = [
+        ["Hostname", metadata.hostname],
+        ["Timestamp", metadata.timestamp],
+        ["Package", metadata.package],
+        ["Id", metadata.id],
+        ["File", metadata.file],
+    ];
+    let rows = attributes
+        .filter(([_, value]) => value)
+        .concat(metadata.properties.map((p): [string, string | undefined] => [p.name, p.value]));
+
+    if (rows.length === 0) {
+        return <></>
+    } else {
+        return (
+            <details className="environment">
+                <summary>Environment</summary>
+                <table className="pure-table">
+                    <tbody>
+                        {rows.map(([name, value]) => {
+                            return (
+                                <tr key={name}>
+                                    <th>{name}</th>
+                                    <td><code>{value}</code></td>
+                                </tr>
+                            )
+                        })}
+                    </tbody>
+                </table>
+            </details>
+        )
+    }
+}
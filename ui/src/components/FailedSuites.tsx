@@ -1,20 +1,30 @@
 import { FailedTestCase as FailedTestCaseDto, FailedTestSuite } from '../dtos';
 import { Component as FailedTestCaseComponent } from './FailedTestCase';
+import { OutputFragment } from './Output';
+import { EnvironmentPanel } from './Environment';
 import { showDuration } from '../utils';
 import React from 'react';
 
 interface FragProps {
-    failedTestcases: FailedTestCaseDto[];
+    suite: FailedTestSuite;
     isExpanded: boolean;
 }
 
 const FailedTestsFragment = (props: FragProps) => {
+    let suite = props.suite;
     return (<>
-        {props.isExpanded ? props.failedTestcases.map(test => {
+        {props.isExpanded ? suite.failedTestcases.map((test: FailedTestCaseDto) => {
             return (
                 <FailedTestCaseComponent testCase={test} />)
         }) : null}
-
+        {props.isExpanded ? (
+            <tr key={suite.name + "-details"} className="failedtests">
+                <td colSpan={5}>
+                    <EnvironmentPanel metadata={suite.metadata} />
+                    <OutputFragment label="system-out" output={suite.systemOut} />
+                    <OutputFragment label="system-err" output={suite.systemErr} />
+                </td>
+            </tr>) : null}
     </>)
 }
 
@@ -62,12 +72,12 @@ export class Component extends React.Component<Props, State> {
                                     <tr key={suite.name}>
                                         <td>{suite.name}</td>
                                         <td>{suite.tests}</td>
-                                        <td className="failedcount"><button title="toggle test cases" onClick={() => this.handleOnClick(suite.name)}>{suite.failures}</button></td>
+                                        <td className="failedcount"><button title="toggle test cases" onClick={() => this.handleOnClick(suite.name)}>{suite.failures + suite.errors}</button></td>
                                         <td>{suite.skipped}</td>
                                         <td>{showDuration(suite.time)}</td>
                                     </tr>
 
-                                    <FailedTestsFragment failedTestcases={suite.failedTestcases} isExpanded={this.state.expandedSuite === suite.name} />
+                                    <FailedTestsFragment suite={suite} isExpanded={this.state.expandedSuite === suite.name} />
                                 </>
                             )
                         })
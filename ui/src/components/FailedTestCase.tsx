@@ -1,5 +1,6 @@
 import { FailedTestCase, } from '../dtos';
-import { showDuration } from '../utils';
+import { attachmentHref, showDuration } from '../utils';
+import { OutputFragment } from './Output';
 import React from 'react';
 
 interface Props {
@@ -36,6 +37,24 @@ export class Component extends React.Component<Props, State> {
                             <pre>
                                 {testCase.failure.stackTrace}
                             </pre>
+                            {testCase.attachments.map((attachment, i) => {
+                                let href = attachmentHref(attachment.path);
+                                return (
+                                    <a key={testCase.name + "-attachment-" + i} className="attachment" href={href}>
+                                        {attachment.contentType?.startsWith("image/") ?
+                                            <img src={href} alt={attachment.name} /> : null}
+                                        {attachment.name}
+                                    </a>)
+                            })}
+                            <OutputFragment label="system-out" output={testCase.systemOut} />
+                            <OutputFragment label="system-err" output={testCase.systemErr} />
+                            {testCase.attempts.map((attempt, i) => {
+                                return (
+                                    <details key={testCase.name + "-attempt-" + i} className="output">
+                                        <summary>re-run {i + 1}: {attempt.failure.message}</summary>
+                                        <pre>{attempt.failure.stackTrace}</pre>
+                                    </details>)
+                            })}
                         </section>) : null
                     }
                 </td>
@@ -0,0 +1,19 @@
+import React from 'react';
+
+interface Props {
+    label: string;
+    output?: string;
+}
+
+export const OutputFragment = (props: Props) => {
+    if (!props.output) {
+        return <></>
+    } else {
+        return (
+            <details className="output">
+                <summary>{props.label}</summary>
+                <pre>{props.output}</pre>
+            </details>
+        )
+    }
+}
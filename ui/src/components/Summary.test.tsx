@@ -8,7 +8,8 @@ test('renders learn react link', () => {
         tests: 20,
         failures: 2,
         errors: 1,
-        skipped: 5
+        skipped: 5,
+        flaky: 0
     };
     let distribution = allocateWithin(summary, 24);
     expect(distribution.failed).toBe(4);
@@ -23,7 +23,7 @@ export function allocateWithin(summary: Summary, slots: number): Distribution {
 export const SummaryFragment = (props: Props) => {
     let dist = allocateWithin(props.summary, 24);
     return (<>
-        <p>{props.summary.failures} failures, {props.summary.errors} errors, {props.summary.skipped} skipped </p>
+        <p>{props.summary.failures} failures, {props.summary.errors} errors, {props.summary.skipped} skipped, {props.summary.flaky} flaky </p>
         {(props.summary.tests < 1) ? (<></>) : (
             <div className="pure-g summary-bar">
                 <div className={"failed pure-u-" + dist.failed + "-24"}></div>
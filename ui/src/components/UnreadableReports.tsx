@@ -0,0 +1,36 @@
+import React from 'react';
+import { UnreadableReport } from '../dtos';
+
+interface Props {
+    reports: UnreadableReport[];
+}
+
+export const UnreadableReportsFragment = (props: Props) => {
+    if (props.reports.length === 0) {
+        return <></>
+    } else {
+        return (
+            <section>
+                <h2>Unreadable reports</h2>
+                <table className="pure-table pure-table-bordered">
+                    <thead>
+                        <tr>
+                            <th>File</th>
+                            <th>Error</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {props.reports.map(report => {
+                            return (
+                                <tr key={report.path}>
+                                    <td><code>{report.path}</code></td>
+                                    <td>{report.error}</td>
+                                </tr>
+                            )
+                        })}
+                    </tbody>
+                </table>
+            </section>
+        )
+    }
+}
@@ -4,13 +4,50 @@ export interface Failure {
     message?: String;
 }
 
+export interface TestAttempt {
+    kind: "flakyFailure" | "flakyError" | "rerunFailure" | "rerunError";
+    failure: Failure;
+    systemOut?: string;
+    systemErr?: string;
+}
+
+export interface Attachment {
+    name: string;
+    contentType?: string;
+    path: string;
+}
+
 export interface FailedTestCase extends TestCase {
     failure: Failure;
+    attempts: TestAttempt[];
+    attachments: Attachment[];
+}
+
+export interface TestSkipped {
+    message?: string;
+    details?: string;
 }
 
 export interface TestCase {
     name: string;
     time: number;
+    skipped?: TestSkipped;
+    systemOut?: string;
+    systemErr?: string;
+}
+
+export interface Property {
+    name: string;
+    value?: string;
+}
+
+export interface SuiteMetadata {
+    hostname?: string;
+    timestamp?: string;
+    package?: string;
+    id?: string;
+    file?: string;
+    properties: Property[];
 }
 
 export interface TestSuite {
@@ -18,12 +55,22 @@ export interface TestSuite {
     time: number;
     tests: number;
     failures: number;
+    errors: number;
     skipped: number;
-    timestamp: Date;
+    flaky: number;
+    metadata: SuiteMetadata;
+    testsuites: TestSuite[];
 }
 
 export interface FailedTestSuite extends TestSuite {
     failedTestcases: FailedTestCase[];
+    systemOut?: string;
+    systemErr?: string;
+}
+
+export interface UnreadableReport {
+    path: string;
+    error: string;
 }
 
 export interface Summary {
@@ -32,6 +79,7 @@ export interface Summary {
     failures: number;
     errors: number;
     skipped: number;
+    flaky: number;
 }
 
 interface PullRequest {
@@ -11,3 +11,10 @@ export const showDuration = humanizeDuration.humanizer({
         },
     },
 });
+
+// Attachments are copied within the report directory, mirroring their path.
+export const attachmentHref = (path: string) => "attachments/" + path
+    .split(/[\\/]/)
+    .filter(part => part !== "" && part !== "." && part !== ".." && !part.endsWith(":"))
+    .map(encodeURIComponent)
+    .join("/");